@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS task_checklist_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT false,
+    completed_by UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    completed_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS task_checklist_items_task_idx
+ON task_checklist_items (task_id, position);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'task_checklist_items_set_updated_at'
+    ) THEN
+        CREATE TRIGGER task_checklist_items_set_updated_at
+        BEFORE UPDATE ON task_checklist_items
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
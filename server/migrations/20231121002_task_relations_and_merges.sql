@@ -0,0 +1,49 @@
+CREATE TABLE IF NOT EXISTS task_relations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL CHECK (kind IN ('duplicate_of', 'relates_to')),
+    source_task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    target_task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    created_by UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (source_task_id <> target_task_id)
+);
+
+-- A pair of tasks carries at most one relation of each kind, whichever
+-- direction it was created in.
+CREATE UNIQUE INDEX IF NOT EXISTS task_relations_pair_unique
+ON task_relations (kind, LEAST(source_task_id, target_task_id), GREATEST(source_task_id, target_task_id));
+
+CREATE INDEX IF NOT EXISTS task_relations_target_idx ON task_relations (target_task_id);
+
+CREATE TABLE IF NOT EXISTS task_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    actor_id UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    kind TEXT NOT NULL,
+    data JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS task_events_task_idx ON task_events (task_id, created_at);
+
+CREATE TABLE IF NOT EXISTS task_merges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source_task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    target_task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    merged_by UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    previous_status TEXT NOT NULL,
+    previous_completed_at TIMESTAMPTZ NULL,
+    moved_comment_ids UUID[] NOT NULL DEFAULT '{}',
+    added_label_ids UUID[] NOT NULL DEFAULT '{}',
+    moved_outgoing_relation_ids UUID[] NOT NULL DEFAULT '{}',
+    moved_incoming_relation_ids UUID[] NOT NULL DEFAULT '{}',
+    back_reference_id UUID NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    reverted_at TIMESTAMPTZ NULL,
+    reverted_by UUID NULL REFERENCES users (id) ON DELETE SET NULL
+);
+
+-- Only one live merge per duplicate; a reverted merge frees the task up again.
+CREATE UNIQUE INDEX IF NOT EXISTS task_merges_active_source_unique
+ON task_merges (source_task_id)
+WHERE reverted_at IS NULL;
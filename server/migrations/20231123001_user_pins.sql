@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS user_pins (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- Exactly one target is set; deleting the target drops the pin with it.
+    task_id UUID NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    project_id UUID NULL REFERENCES projects (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((task_id IS NULL) <> (project_id IS NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_pins_task_unique
+ON user_pins (user_id, task_id)
+WHERE task_id IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_pins_project_unique
+ON user_pins (user_id, project_id)
+WHERE project_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS user_pins_user_idx ON user_pins (user_id, position);
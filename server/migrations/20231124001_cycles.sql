@@ -0,0 +1,45 @@
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS cycle_length_days INTEGER NULL
+    CHECK (cycle_length_days IS NULL OR cycle_length_days BETWEEN 1 AND 90);
+
+CREATE TABLE IF NOT EXISTS cycles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    starts_on DATE NOT NULL,
+    ends_on DATE NOT NULL,
+    completed_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (project_id, number),
+    CHECK (ends_on > starts_on)
+);
+
+CREATE INDEX IF NOT EXISTS cycles_open_idx ON cycles (ends_on) WHERE completed_at IS NULL;
+
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS cycle_id UUID NULL REFERENCES cycles (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS tasks_cycle_idx ON tasks (cycle_id) WHERE cycle_id IS NOT NULL;
+
+-- Append-only log of tasks entering and leaving a cycle, used for progress
+-- reporting after the fact.
+CREATE TABLE IF NOT EXISTS cycle_scope_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    cycle_id UUID NOT NULL REFERENCES cycles (id) ON DELETE CASCADE,
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    change TEXT NOT NULL CHECK (change IN ('added', 'removed', 'carried_in', 'carried_out')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS cycle_scope_changes_cycle_idx ON cycle_scope_changes (cycle_id, change);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'cycles_set_updated_at'
+    ) THEN
+        CREATE TRIGGER cycles_set_updated_at
+        BEFORE UPDATE ON cycles
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
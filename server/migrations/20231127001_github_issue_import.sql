@@ -0,0 +1,40 @@
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_node_id TEXT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_repository TEXT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_issue_number INTEGER NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_url TEXT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_updated_at TIMESTAMPTZ NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS tasks_github_node_id_unique
+ON tasks (github_node_id)
+WHERE github_node_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS github_imports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    repository TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'completed', 'failed')),
+    pages_fetched INTEGER NOT NULL DEFAULT 0,
+    pages_not_modified INTEGER NOT NULL DEFAULT 0,
+    issues_seen INTEGER NOT NULL DEFAULT 0,
+    tasks_created INTEGER NOT NULL DEFAULT 0,
+    tasks_updated INTEGER NOT NULL DEFAULT 0,
+    error TEXT NULL,
+    requested_by UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at TIMESTAMPTZ NULL,
+    finished_at TIMESTAMPTZ NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS github_imports_active_unique
+ON github_imports (project_id, repository)
+WHERE status IN ('queued', 'running');
+
+-- Validators from earlier responses, keyed by request URL, so re-syncs can
+-- send If-None-Match and have unchanged pages answered with 304.
+CREATE TABLE IF NOT EXISTS github_etags (
+    url TEXT PRIMARY KEY,
+    etag TEXT NOT NULL,
+    next_url TEXT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
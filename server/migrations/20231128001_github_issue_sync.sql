@@ -0,0 +1,23 @@
+-- Last state both a task and its linked issue agreed on. Each sync diffs the
+-- two sides against this snapshot to tell which side changed a field.
+CREATE TABLE IF NOT EXISTS github_issue_sync_state (
+    task_id UUID PRIMARY KEY REFERENCES tasks (id) ON DELETE CASCADE,
+    snapshot JSONB NOT NULL,
+    remote_updated_at TIMESTAMPTZ NOT NULL,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS github_sync_conflicts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    field TEXT NOT NULL
+        CHECK (field IN ('title', 'body', 'state', 'labels', 'assignees')),
+    base_value JSONB NOT NULL,
+    local_value JSONB NOT NULL,
+    remote_value JSONB NOT NULL,
+    winner TEXT NOT NULL CHECK (winner IN ('local', 'remote')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS github_sync_conflicts_task_idx
+ON github_sync_conflicts (task_id, created_at DESC);
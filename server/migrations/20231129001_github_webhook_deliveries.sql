@@ -0,0 +1,22 @@
+-- Raw webhook deliveries, keyed by X-GitHub-Delivery so redeliveries are
+-- recognised and can be replayed from the stored payload.
+CREATE TABLE IF NOT EXISTS github_webhook_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    event TEXT NOT NULL,
+    action TEXT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'received'
+        CHECK (status IN ('received', 'processed', 'ignored', 'failed')),
+    error TEXT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    processed_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX IF NOT EXISTS github_webhook_deliveries_received_idx
+ON github_webhook_deliveries (received_at DESC);
+
+ALTER TABLE task_comments ADD COLUMN IF NOT EXISTS github_comment_id BIGINT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS task_comments_github_comment_id_unique
+ON task_comments (github_comment_id)
+WHERE github_comment_id IS NOT NULL;
@@ -0,0 +1,27 @@
+-- GitHub OAuth tokens, encrypted by the application (AES-256-GCM). key_id
+-- names the configured key both ciphertexts were sealed with so rows can be
+-- re-encrypted after a key rotation.
+CREATE TABLE IF NOT EXISTS user_github_tokens (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    key_id TEXT NOT NULL,
+    access_token BYTEA NOT NULL,
+    access_token_expires_at TIMESTAMPTZ NULL,
+    refresh_token BYTEA NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS user_github_tokens_key_idx ON user_github_tokens (key_id);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'user_github_tokens_set_updated_at'
+    ) THEN
+        CREATE TRIGGER user_github_tokens_set_updated_at
+        BEFORE UPDATE ON user_github_tokens
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
@@ -0,0 +1,34 @@
+-- GitHub App installations and the repositories each one grants access to,
+-- maintained from the installation and installation_repositories webhooks.
+CREATE TABLE IF NOT EXISTS github_installations (
+    id BIGINT PRIMARY KEY,
+    account_login TEXT NOT NULL,
+    account_type TEXT NOT NULL,
+    repository_selection TEXT NOT NULL DEFAULT 'selected',
+    suspended_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS github_installation_repositories (
+    installation_id BIGINT NOT NULL REFERENCES github_installations (id) ON DELETE CASCADE,
+    repository_id BIGINT NOT NULL,
+    full_name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (installation_id, repository_id)
+);
+
+CREATE INDEX IF NOT EXISTS github_installation_repositories_name_idx
+ON github_installation_repositories (LOWER(full_name));
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'github_installations_set_updated_at'
+    ) THEN
+        CREATE TRIGGER github_installations_set_updated_at
+        BEFORE UPDATE ON github_installations
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
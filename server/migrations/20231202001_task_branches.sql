@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS task_branches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    repository TEXT NOT NULL,
+    name TEXT NOT NULL,
+    base_branch TEXT NOT NULL,
+    base_sha TEXT NOT NULL,
+    created_by UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS task_branches_repository_name_unique
+ON task_branches (LOWER(repository), name);
+
+CREATE INDEX IF NOT EXISTS task_branches_task_idx ON task_branches (task_id, created_at);
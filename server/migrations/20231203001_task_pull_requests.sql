@@ -0,0 +1,34 @@
+-- Status a task moves to when a pull request referencing it opens or merges;
+-- NULL turns the transition off for the project.
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS pr_opened_status TEXT NULL DEFAULT 'in_review'
+    CHECK (pr_opened_status IN ('backlog', 'todo', 'in_progress', 'in_review', 'done', 'canceled'));
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS pr_merged_status TEXT NULL DEFAULT 'done'
+    CHECK (pr_merged_status IN ('backlog', 'todo', 'in_progress', 'in_review', 'done', 'canceled'));
+
+CREATE TABLE IF NOT EXISTS task_pull_requests (
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    repository TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    head_ref TEXT NOT NULL,
+    state TEXT NOT NULL CHECK (state IN ('open', 'draft', 'closed', 'merged')),
+    merged_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (task_id, repository, number)
+);
+
+CREATE INDEX IF NOT EXISTS task_pull_requests_pr_idx ON task_pull_requests (repository, number);
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'task_pull_requests_set_updated_at'
+    ) THEN
+        CREATE TRIGGER task_pull_requests_set_updated_at
+        BEFORE UPDATE ON task_pull_requests
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
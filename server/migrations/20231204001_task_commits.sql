@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS task_commits (
+    task_id UUID NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    repository TEXT NOT NULL,
+    sha TEXT NOT NULL,
+    message TEXT NOT NULL,
+    url TEXT NOT NULL,
+    author_name TEXT NOT NULL,
+    author_login TEXT NULL,
+    author_id UUID NULL REFERENCES users (id) ON DELETE SET NULL,
+    closes BOOLEAN NOT NULL DEFAULT false,
+    committed_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (task_id, repository, sha)
+);
+
+CREATE INDEX IF NOT EXISTS task_commits_task_idx ON task_commits (task_id, committed_at);
@@ -0,0 +1,4 @@
+-- GraphQL imports page with cursors instead of URLs; the last processed
+-- cursor lets a failed import's successor pick up where it stopped.
+ALTER TABLE github_imports ADD COLUMN IF NOT EXISTS graphql_cursor TEXT NULL;
+ALTER TABLE github_imports ADD COLUMN IF NOT EXISTS graphql_cost INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Whether users.email is the verified primary address reported by GitHub's
+-- /user/emails, as opposed to a profile email stored before it was checked.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,66 @@
+-- Maps GitHub labels of a repository to Keyflow labels. Rows can be created
+-- by hand with only `github_name` set (e.g. `type:bug` -> `bug`); the label's
+-- node ID is filled in the first time it is seen, after which renames on
+-- either side keep the mapping.
+CREATE TABLE IF NOT EXISTS github_label_mappings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    repository TEXT NOT NULL,
+    github_node_id TEXT NULL,
+    github_name TEXT NOT NULL,
+    label_id UUID NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS github_label_mappings_node_unique
+ON github_label_mappings (github_node_id)
+WHERE github_node_id IS NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS github_label_mappings_name_unique
+ON github_label_mappings (repository, LOWER(github_name));
+
+-- One GitHub label per Keyflow label and repository, so pushing labels back
+-- to GitHub is unambiguous.
+CREATE UNIQUE INDEX IF NOT EXISTS github_label_mappings_label_unique
+ON github_label_mappings (repository, label_id);
+
+-- Milestones seen on issues imported into a project, and the cycle each one
+-- stands for.
+CREATE TABLE IF NOT EXISTS github_milestones (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id UUID NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+    repository TEXT NOT NULL,
+    github_node_id TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    state TEXT NOT NULL CHECK (state IN ('open', 'closed')),
+    due_on DATE NULL,
+    cycle_id UUID NULL REFERENCES cycles (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (project_id, github_node_id)
+);
+
+ALTER TABLE cycles ADD COLUMN IF NOT EXISTS name TEXT NULL;
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS github_milestone_id TEXT NULL;
+
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'github_label_mappings_set_updated_at'
+    ) THEN
+        CREATE TRIGGER github_label_mappings_set_updated_at
+        BEFORE UPDATE ON github_label_mappings
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_trigger WHERE tgname = 'github_milestones_set_updated_at'
+    ) THEN
+        CREATE TRIGGER github_milestones_set_updated_at
+        BEFORE UPDATE ON github_milestones
+        FOR EACH ROW
+        EXECUTE FUNCTION set_updated_at();
+    END IF;
+END $$;
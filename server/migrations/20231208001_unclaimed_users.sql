@@ -0,0 +1,3 @@
+-- Users created for GitHub assignees who have not signed in yet. Signing in
+-- with the same GitHub account claims the row, keeping its assignments.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS claimed BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1,15 @@
+-- GitLab sits beside GitHub as a second forge. A user signs in through one
+-- of them, so each row carries the account ID of the forge it came from.
+ALTER TABLE users ALTER COLUMN github_id DROP NOT NULL;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS gitlab_id BIGINT NULL UNIQUE;
+
+-- The github_* link columns, tokens, imports and webhook deliveries hold the
+-- data of whichever forge `forge` names.
+ALTER TABLE tasks ADD COLUMN IF NOT EXISTS forge TEXT NOT NULL DEFAULT 'github'
+    CHECK (forge IN ('github', 'gitlab'));
+ALTER TABLE user_github_tokens ADD COLUMN IF NOT EXISTS forge TEXT NOT NULL DEFAULT 'github'
+    CHECK (forge IN ('github', 'gitlab'));
+ALTER TABLE github_imports ADD COLUMN IF NOT EXISTS forge TEXT NOT NULL DEFAULT 'github'
+    CHECK (forge IN ('github', 'gitlab'));
+ALTER TABLE github_webhook_deliveries ADD COLUMN IF NOT EXISTS forge TEXT NOT NULL DEFAULT 'github'
+    CHECK (forge IN ('github', 'gitlab'));
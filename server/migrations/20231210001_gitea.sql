@@ -0,0 +1,15 @@
+-- Gitea and Forgejo instances join GitHub and GitLab as a third forge.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS gitea_id BIGINT NULL UNIQUE;
+
+ALTER TABLE tasks DROP CONSTRAINT IF EXISTS tasks_forge_check;
+ALTER TABLE tasks ADD CONSTRAINT tasks_forge_check
+    CHECK (forge IN ('github', 'gitlab', 'gitea'));
+ALTER TABLE user_github_tokens DROP CONSTRAINT IF EXISTS user_github_tokens_forge_check;
+ALTER TABLE user_github_tokens ADD CONSTRAINT user_github_tokens_forge_check
+    CHECK (forge IN ('github', 'gitlab', 'gitea'));
+ALTER TABLE github_imports DROP CONSTRAINT IF EXISTS github_imports_forge_check;
+ALTER TABLE github_imports ADD CONSTRAINT github_imports_forge_check
+    CHECK (forge IN ('github', 'gitlab', 'gitea'));
+ALTER TABLE github_webhook_deliveries DROP CONSTRAINT IF EXISTS github_webhook_deliveries_forge_check;
+ALTER TABLE github_webhook_deliveries ADD CONSTRAINT github_webhook_deliveries_forge_check
+    CHECK (forge IN ('github', 'gitlab', 'gitea'));
@@ -0,0 +1,30 @@
+-- Background work (imports, issue syncs, webhook deliveries, cycle
+-- rollovers) is queued here so it survives restarts. Workers claim due jobs
+-- with FOR UPDATE SKIP LOCKED; finished jobs are deleted, and jobs that ran
+-- out of attempts stay behind as 'dead' until an admin retries them.
+CREATE TABLE IF NOT EXISTS jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    unique_key TEXT NULL,
+    status TEXT NOT NULL DEFAULT 'queued'
+        CHECK (status IN ('queued', 'running', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Refreshed while a worker holds the job; a stale value means the worker
+    -- died and the job can be claimed again.
+    locked_at TIMESTAMPTZ NULL,
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- A job with a key is only queued once; one may run while the next waits.
+CREATE UNIQUE INDEX IF NOT EXISTS jobs_queued_unique_key
+ON jobs (unique_key)
+WHERE unique_key IS NOT NULL AND status = 'queued';
+
+CREATE INDEX IF NOT EXISTS jobs_due
+ON jobs (run_at)
+WHERE status = 'queued';
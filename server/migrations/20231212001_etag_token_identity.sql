@@ -0,0 +1,6 @@
+-- Anonymous and authenticated listings differ (private issues), so cached
+-- validators are kept per token as well as per URL.
+ALTER TABLE github_etags ADD COLUMN IF NOT EXISTS token_fingerprint TEXT NOT NULL DEFAULT 'anonymous';
+
+ALTER TABLE github_etags DROP CONSTRAINT IF EXISTS github_etags_pkey;
+ALTER TABLE github_etags ADD CONSTRAINT github_etags_pkey PRIMARY KEY (url, token_fingerprint);
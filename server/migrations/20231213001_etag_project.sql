@@ -0,0 +1,10 @@
+-- A 304 skips the page's issues, which is only right for the project that
+-- imported them, so cached validators are kept per project too. Existing
+-- entries cannot be attributed and are dropped; the next import refetches.
+DELETE FROM github_etags;
+
+ALTER TABLE github_etags
+    ADD COLUMN IF NOT EXISTS project_id UUID NOT NULL REFERENCES projects (id) ON DELETE CASCADE;
+
+ALTER TABLE github_etags DROP CONSTRAINT IF EXISTS github_etags_pkey;
+ALTER TABLE github_etags ADD CONSTRAINT github_etags_pkey PRIMARY KEY (url, token_fingerprint, project_id);
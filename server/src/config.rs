@@ -1,21 +1,92 @@
-use crate::error::AppError;
+use crate::{error::AppError, github::branches::DEFAULT_BRANCH_TEMPLATE};
 use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use reqwest::Url;
 use std::env;
 use tracing::warn;
 
+#[derive(Clone)]
+pub struct GitHubAppConfig {
+    pub app_id: i64,
+    pub private_key_pem: String,
+}
+
+#[derive(Clone)]
+pub struct GitLabConfig {
+    /// `https://gitlab.com` or a self-managed instance; the API is served
+    /// under `/api/v4`.
+    pub url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Compared with the `X-Gitlab-Token` header of webhook deliveries.
+    pub webhook_token: Option<String>,
+    /// Used when no user is acting, like `GITHUB_API_TOKEN`.
+    pub api_token: Option<String>,
+}
+
+impl GitLabConfig {
+    pub fn api_url(&self) -> String {
+        format!("{}/api/v4", self.url)
+    }
+
+    /// Uploaded avatars are served by the instance, the rest by Gravatar.
+    pub fn avatar_origins(&self) -> Vec<String> {
+        vec![self.url.clone(), "https://secure.gravatar.com".to_string()]
+    }
+}
+
+/// A Gitea or Forgejo instance; both speak the same API.
+#[derive(Clone)]
+pub struct GiteaConfig {
+    pub url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Key of the `X-Gitea-Signature` HMAC on webhook deliveries.
+    pub webhook_secret: Option<String>,
+    /// Used when no user is acting, like `GITHUB_API_TOKEN`.
+    pub api_token: Option<String>,
+}
+
+impl GiteaConfig {
+    pub fn api_url(&self) -> String {
+        format!("{}/api/v1", self.url)
+    }
+}
+
 #[derive(Clone)]
 pub struct AppConfig {
     pub database_url: String,
     pub session_signing_keys: Vec<String>,
+    pub token_encryption_keys: Vec<String>,
     pub app_base_url: String,
     pub github_client_id: String,
     pub github_client_secret: String,
+    /// `https://github.com` or the GitHub Enterprise Server host.
+    pub github_web_url: String,
+    /// REST API root, e.g. `https://api.github.com` or `https://ghe.example.com/api/v3`.
+    pub github_api_url: String,
+    /// Extra PEM certificates trusted for GitHub, for servers behind an
+    /// internal CA.
+    pub github_ca_bundle: Option<Vec<u8>>,
+    pub github_api_token: Option<String>,
+    pub github_webhook_secret: Option<String>,
+    pub github_app: Option<GitHubAppConfig>,
+    /// Set when GitLab sign-in and issue sync are enabled.
+    pub gitlab: Option<GitLabConfig>,
+    /// Set when Gitea (or Forgejo) sign-in and issue sync are enabled.
+    pub gitea: Option<GiteaConfig>,
     pub frontend_origin: String,
     pub cookie_secure: bool,
     pub port: u16,
     pub auth_rate_limit_per_minute: u32,
     pub auth_rate_limit_burst: u32,
+    pub webhook_rate_limit_per_minute: u32,
     pub allow_insecure_cookies: bool,
+    pub cycle_rollover_interval_secs: u64,
+    /// Background jobs run concurrently per process.
+    pub job_workers: usize,
+    /// How long shutdown waits for running jobs before abandoning them.
+    pub job_drain_timeout_secs: u64,
+    pub branch_name_template: String,
 }
 
 impl AppConfig {
@@ -24,6 +95,12 @@ impl AppConfig {
 
         let database_url = env::var("DATABASE_URL").map_err(|_| AppError::Config("DATABASE_URL missing".into()))?;
         let session_signing_keys = load_session_keys()?;
+        let token_encryption_keys: Vec<String> = env::var("TOKEN_ENCRYPTION_KEYS")
+            .map_err(|_| AppError::Config("TOKEN_ENCRYPTION_KEYS missing".into()))?
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
         let app_base_url = env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
         let frontend_origin =
             env::var("FRONTEND_ORIGIN").unwrap_or_else(|_| "http://localhost:5173".to_string());
@@ -31,6 +108,27 @@ impl AppConfig {
             env::var("GITHUB_CLIENT_ID").map_err(|_| AppError::Config("GITHUB_CLIENT_ID missing".into()))?;
         let github_client_secret =
             env::var("GITHUB_CLIENT_SECRET").map_err(|_| AppError::Config("GITHUB_CLIENT_SECRET missing".into()))?;
+        let github_web_url = load_url("GITHUB_WEB_URL")?.unwrap_or_else(|| "https://github.com".to_string());
+        // GitHub Enterprise Server serves the API under /api/v3 of its own host.
+        let github_api_url = load_url("GITHUB_API_URL")?.unwrap_or_else(|| {
+            if github_web_url == "https://github.com" {
+                "https://api.github.com".to_string()
+            } else {
+                format!("{}/api/v3", github_web_url)
+            }
+        });
+        let github_ca_bundle = env::var("GITHUB_CA_CERT_PATH")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|path| {
+                std::fs::read(&path).map_err(|e| AppError::Config(format!("cannot read GITHUB_CA_CERT_PATH: {}", e)))
+            })
+            .transpose()?;
+        let github_api_token = env::var("GITHUB_API_TOKEN").ok().filter(|v| !v.trim().is_empty());
+        let github_webhook_secret = env::var("GITHUB_WEBHOOK_SECRET").ok().filter(|v| !v.trim().is_empty());
+        let github_app = load_github_app()?;
+        let gitlab = load_gitlab()?;
+        let gitea = load_gitea()?;
         let port = env::var("PORT")
             .ok()
             .and_then(|p| p.parse::<u16>().ok())
@@ -45,6 +143,32 @@ impl AppConfig {
             .and_then(|v| v.parse::<u32>().ok())
             .filter(|v| *v > 0)
             .unwrap_or(10);
+        let webhook_rate_limit_per_minute = env::var("WEBHOOK_RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(600);
+
+        let cycle_rollover_interval_secs = env::var("CYCLE_ROLLOVER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(900);
+
+        let job_workers = env::var("JOB_WORKERS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(4);
+        let job_drain_timeout_secs = env::var("JOB_DRAIN_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(30);
+
+        let branch_name_template = env::var("BRANCH_NAME_TEMPLATE")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_BRANCH_TEMPLATE.to_string());
 
         let cookie_secure = app_base_url.starts_with("https://");
         let allow_insecure_cookies = env::var("ALLOW_INSECURE_COOKIES")
@@ -61,22 +185,36 @@ impl AppConfig {
         Ok(Self {
             database_url,
             session_signing_keys,
+            token_encryption_keys,
             app_base_url,
             github_client_id,
             github_client_secret,
+            github_web_url,
+            github_api_url,
+            github_ca_bundle,
+            github_api_token,
+            github_webhook_secret,
+            github_app,
+            gitlab,
+            gitea,
             frontend_origin,
             cookie_secure,
             port,
             auth_rate_limit_per_minute,
             auth_rate_limit_burst,
+            webhook_rate_limit_per_minute,
             allow_insecure_cookies,
+            cycle_rollover_interval_secs,
+            job_workers,
+            job_drain_timeout_secs,
+            branch_name_template,
         })
     }
 
-    pub fn oauth_client(&self) -> Result<BasicClient, AppError> {
-        let auth_url = AuthUrl::new("https://github.com/login/oauth/authorize".into())
+    pub fn github_oauth_client(&self) -> Result<BasicClient, AppError> {
+        let auth_url = AuthUrl::new(format!("{}/login/oauth/authorize", self.github_web_url))
             .map_err(|e| AppError::Config(format!("invalid auth url: {}", e)))?;
-        let token_url = TokenUrl::new("https://github.com/login/oauth/access_token".into())
+        let token_url = TokenUrl::new(format!("{}/login/oauth/access_token", self.github_web_url))
             .map_err(|e| AppError::Config(format!("invalid token url: {}", e)))?;
         let redirect = RedirectUrl::new(format!("{}/auth/github/callback", self.app_base_url))
             .map_err(|e| AppError::Config(format!("invalid redirect url: {}", e)))?;
@@ -91,6 +229,113 @@ impl AppConfig {
             .set_redirect_uri(redirect),
         )
     }
+
+    pub fn gitlab_oauth_client(&self, gitlab: &GitLabConfig) -> Result<BasicClient, AppError> {
+        let auth_url = AuthUrl::new(format!("{}/oauth/authorize", gitlab.url))
+            .map_err(|e| AppError::Config(format!("invalid gitlab auth url: {}", e)))?;
+        let token_url = TokenUrl::new(format!("{}/oauth/token", gitlab.url))
+            .map_err(|e| AppError::Config(format!("invalid gitlab token url: {}", e)))?;
+        let redirect = RedirectUrl::new(format!("{}/auth/gitlab/callback", self.app_base_url))
+            .map_err(|e| AppError::Config(format!("invalid redirect url: {}", e)))?;
+
+        Ok(
+            BasicClient::new(
+                ClientId::new(gitlab.client_id.clone()),
+                Some(ClientSecret::new(gitlab.client_secret.clone())),
+                auth_url,
+                Some(token_url),
+            )
+            .set_redirect_uri(redirect),
+        )
+    }
+
+    pub fn gitea_oauth_client(&self, gitea: &GiteaConfig) -> Result<BasicClient, AppError> {
+        let auth_url = AuthUrl::new(format!("{}/login/oauth/authorize", gitea.url))
+            .map_err(|e| AppError::Config(format!("invalid gitea auth url: {}", e)))?;
+        let token_url = TokenUrl::new(format!("{}/login/oauth/access_token", gitea.url))
+            .map_err(|e| AppError::Config(format!("invalid gitea token url: {}", e)))?;
+        let redirect = RedirectUrl::new(format!("{}/auth/gitea/callback", self.app_base_url))
+            .map_err(|e| AppError::Config(format!("invalid redirect url: {}", e)))?;
+
+        Ok(
+            BasicClient::new(
+                ClientId::new(gitea.client_id.clone()),
+                Some(ClientSecret::new(gitea.client_secret.clone())),
+                auth_url,
+                Some(token_url),
+            )
+            .set_redirect_uri(redirect),
+        )
+    }
+
+    /// Configuration for tests, pointed at the given GitHub (usually the
+    /// fake one from `test_support`) instead of read from the environment.
+    #[cfg(test)]
+    pub fn for_tests(database_url: &str, github_web_url: &str, github_api_url: &str) -> Self {
+        Self {
+            database_url: database_url.to_string(),
+            session_signing_keys: vec!["test-session-signing-key-0123456789abcdef".to_string()],
+            token_encryption_keys: vec!["test:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string()],
+            app_base_url: "http://localhost:8080".to_string(),
+            github_client_id: "test-client".to_string(),
+            github_client_secret: "test-secret".to_string(),
+            github_web_url: github_web_url.to_string(),
+            github_api_url: github_api_url.to_string(),
+            github_ca_bundle: None,
+            github_api_token: None,
+            github_webhook_secret: Some("test-webhook-secret".to_string()),
+            github_app: None,
+            gitlab: None,
+            gitea: None,
+            frontend_origin: "http://localhost:5173".to_string(),
+            cookie_secure: false,
+            port: 0,
+            auth_rate_limit_per_minute: 600,
+            auth_rate_limit_burst: 600,
+            webhook_rate_limit_per_minute: 600,
+            allow_insecure_cookies: true,
+            cycle_rollover_interval_secs: 900,
+            job_workers: 1,
+            job_drain_timeout_secs: 5,
+            branch_name_template: DEFAULT_BRANCH_TEMPLATE.to_string(),
+        }
+    }
+
+    /// GraphQL lives beside the REST API on github.com but at `/api/graphql`
+    /// on Enterprise Server.
+    pub fn github_graphql_url(&self) -> String {
+        match self.github_api_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => format!("{}/graphql", self.github_api_url),
+        }
+    }
+
+    /// Origins the browser loads GitHub avatars from: the avatar CDN on
+    /// github.com, the server itself (or its `avatars.` subdomain when
+    /// subdomain isolation is on) for Enterprise Server.
+    pub fn github_avatar_origins(&self) -> Vec<String> {
+        if self.github_web_url == "https://github.com" {
+            return vec!["https://avatars.githubusercontent.com".to_string()];
+        }
+        let mut origins = vec![self.github_web_url.clone()];
+        if let Some(host) = Url::parse(&self.github_web_url).ok().and_then(|url| url.host_str().map(str::to_string)) {
+            origins.push(format!("https://avatars.{}", host));
+        }
+        origins
+    }
+}
+
+/// An absolute http(s) URL without a trailing slash, or `None` when unset.
+fn load_url(name: &str) -> Result<Option<String>, AppError> {
+    let Some(raw) = env::var(name).ok().filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let url = Url::parse(raw.trim()).map_err(|e| AppError::Config(format!("invalid {}: {}", name, e)))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(AppError::Config(format!("{} must be an http(s) URL", name)));
+    }
+
+    Ok(Some(raw.trim().trim_end_matches('/').to_string()))
 }
 
 fn load_session_keys() -> Result<Vec<String>, AppError> {
@@ -129,3 +374,76 @@ fn load_session_keys() -> Result<Vec<String>, AppError> {
     Ok(keys)
 }
 
+
+/// `GITHUB_APP_PRIVATE_KEY` may hold the PEM itself (with `\n` escapes when it
+/// has to fit on one line) or `GITHUB_APP_PRIVATE_KEY_PATH` may point at it.
+fn load_github_app() -> Result<Option<GitHubAppConfig>, AppError> {
+    let app_id = env::var("GITHUB_APP_ID").ok().filter(|v| !v.trim().is_empty());
+    let private_key_pem = match env::var("GITHUB_APP_PRIVATE_KEY").ok().filter(|v| !v.trim().is_empty()) {
+        Some(inline) => Some(inline.replace("\\n", "\n")),
+        None => env::var("GITHUB_APP_PRIVATE_KEY_PATH")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|path| {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| AppError::Config(format!("cannot read GITHUB_APP_PRIVATE_KEY_PATH: {}", e)))
+            })
+            .transpose()?,
+    };
+
+    match (app_id, private_key_pem) {
+        (None, None) => Ok(None),
+        (Some(app_id), Some(private_key_pem)) => {
+            let app_id = app_id
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| AppError::Config("GITHUB_APP_ID must be numeric".into()))?;
+            Ok(Some(GitHubAppConfig { app_id, private_key_pem }))
+        }
+        _ => Err(AppError::Config(
+            "GITHUB_APP_ID and a GitHub App private key must be configured together".into(),
+        )),
+    }
+}
+
+/// GitLab is enabled by `GITLAB_CLIENT_ID` and `GITLAB_CLIENT_SECRET`;
+/// `GITLAB_URL` points at a self-managed instance instead of gitlab.com.
+fn load_gitlab() -> Result<Option<GitLabConfig>, AppError> {
+    let client_id = env::var("GITLAB_CLIENT_ID").ok().filter(|v| !v.trim().is_empty());
+    let client_secret = env::var("GITLAB_CLIENT_SECRET").ok().filter(|v| !v.trim().is_empty());
+
+    match (client_id, client_secret) {
+        (None, None) => Ok(None),
+        (Some(client_id), Some(client_secret)) => Ok(Some(GitLabConfig {
+            url: load_url("GITLAB_URL")?.unwrap_or_else(|| "https://gitlab.com".to_string()),
+            client_id,
+            client_secret,
+            webhook_token: env::var("GITLAB_WEBHOOK_TOKEN").ok().filter(|v| !v.trim().is_empty()),
+            api_token: env::var("GITLAB_API_TOKEN").ok().filter(|v| !v.trim().is_empty()),
+        })),
+        _ => Err(AppError::Config(
+            "GITLAB_CLIENT_ID and GITLAB_CLIENT_SECRET must be configured together".into(),
+        )),
+    }
+}
+
+/// Gitea or Forgejo is enabled by `GITEA_URL`, `GITEA_CLIENT_ID` and
+/// `GITEA_CLIENT_SECRET`; there is no public default instance.
+fn load_gitea() -> Result<Option<GiteaConfig>, AppError> {
+    let client_id = env::var("GITEA_CLIENT_ID").ok().filter(|v| !v.trim().is_empty());
+    let client_secret = env::var("GITEA_CLIENT_SECRET").ok().filter(|v| !v.trim().is_empty());
+
+    match (load_url("GITEA_URL")?, client_id, client_secret) {
+        (None, None, None) => Ok(None),
+        (Some(url), Some(client_id), Some(client_secret)) => Ok(Some(GiteaConfig {
+            url,
+            client_id,
+            client_secret,
+            webhook_secret: env::var("GITEA_WEBHOOK_SECRET").ok().filter(|v| !v.trim().is_empty()),
+            api_token: env::var("GITEA_API_TOKEN").ok().filter(|v| !v.trim().is_empty()),
+        })),
+        _ => Err(AppError::Config(
+            "GITEA_URL, GITEA_CLIENT_ID and GITEA_CLIENT_SECRET must be configured together".into(),
+        )),
+    }
+}
@@ -1,9 +1,10 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::Serialize;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -28,8 +29,22 @@ pub enum AppError {
     BadRequest(String),
     #[error("forbidden: {0}")]
     Forbidden(String),
+    #[error("not found")]
+    NotFound,
+    #[error("conflict: {0}")]
+    Conflict(String),
+    /// The user's GitHub token lacks scopes a feature needs; the client should
+    /// send them through `/auth/github/upgrade` with these scopes.
+    #[error("github scope required: {}", .0.join(", "))]
+    ScopeRequired(Vec<String>),
     #[error("too many requests")]
     TooManyRequests,
+    /// GitHub's rate limit for the token will not reset soon enough to wait.
+    #[error("github rate limit exceeded; retry in {}s", .0.as_secs())]
+    GitHubRateLimited(Duration),
+    /// GitHub keeps failing and calls are paused until the breaker closes.
+    #[error("github unavailable; retry in {}s", .0.as_secs())]
+    GitHubUnavailable(Duration),
     #[error("internal server error")]
     Internal,
 }
@@ -46,10 +61,37 @@ impl IntoResponse for AppError {
             AppError::Token(_) | AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            AppError::NotFound => (StatusCode::NOT_FOUND, "not found".to_string()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             AppError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "too many requests".to_string()),
+            AppError::GitHubRateLimited(wait) | AppError::GitHubUnavailable(wait) => {
+                let (message, code) = match &self {
+                    AppError::GitHubRateLimited(_) => ("github rate limit exceeded", "github_rate_limited"),
+                    _ => ("github unavailable", "github_unavailable"),
+                };
+                let body = Json(ErrorBody {
+                    error: message.to_string(),
+                    code: Some(code),
+                    required_scopes: None,
+                });
+                let retry_after = [(RETRY_AFTER, wait.as_secs().max(1).to_string())];
+                return (StatusCode::SERVICE_UNAVAILABLE, retry_after, body).into_response();
+            }
+            AppError::ScopeRequired(scopes) => {
+                let body = Json(ErrorBody {
+                    error: "github scope required".to_string(),
+                    code: Some("scope_required"),
+                    required_scopes: Some(scopes.clone()),
+                });
+                return (StatusCode::FORBIDDEN, body).into_response();
+            }
         };
 
-        let body = Json(ErrorBody { error: message });
+        let body = Json(ErrorBody {
+            error: message,
+            code: None,
+            required_scopes: None,
+        });
 
         (status, body).into_response()
     }
@@ -58,6 +100,10 @@ impl IntoResponse for AppError {
 #[derive(Serialize)]
 struct ErrorBody {
     error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    required_scopes: Option<Vec<String>>,
 }
 
 impl From<jsonwebtoken::errors::Error> for AppError {
@@ -0,0 +1,289 @@
+//! What Keyflow needs from a code forge: signing users in, reading and
+//! updating issues, creating branches and receiving webhooks.
+//!
+//! The shared types deserialize from GitHub's REST shape, since GitHub was
+//! the first backend; other backends convert their payloads into them.
+//! Pull requests reach Keyflow through webhooks, so a backend covers them by
+//! turning its own events into [`PullRequest`]s in `handle_webhook`.
+
+use crate::{error::AppError, github::scopes::normalize_scopes, state::SharedState};
+use axum::{async_trait, http::HeaderMap};
+use chrono::{DateTime, Utc};
+use oauth2::{basic::BasicTokenResponse, TokenResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    /// Gitea or Forgejo.
+    Gitea,
+}
+
+impl ForgeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "github",
+            ForgeKind::GitLab => "gitlab",
+            ForgeKind::Gitea => "gitea",
+        }
+    }
+
+    /// Reads the value stored in a `forge` column.
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        match value {
+            "github" => Ok(ForgeKind::GitHub),
+            "gitlab" => Ok(ForgeKind::GitLab),
+            "gitea" => Ok(ForgeKind::Gitea),
+            _ => Err(AppError::Internal),
+        }
+    }
+
+    /// The `users` column holding account IDs on this forge.
+    pub fn user_id_column(self) -> &'static str {
+        match self {
+            ForgeKind::GitHub => "github_id",
+            ForgeKind::GitLab => "gitlab_id",
+            ForgeKind::Gitea => "gitea_id",
+        }
+    }
+}
+
+/// Where to send the user to authorize Keyflow, with the values the
+/// callback has to check and present again.
+pub struct AuthorizeRequest {
+    pub url: String,
+    pub state: String,
+    pub pkce_verifier: String,
+}
+
+pub struct ForgeToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: Option<Duration>,
+    /// `None` when the forge does not report them, as on a refresh.
+    pub scopes: Option<Vec<String>>,
+}
+
+impl From<BasicTokenResponse> for ForgeToken {
+    fn from(token: BasicTokenResponse) -> Self {
+        Self {
+            access_token: token.access_token().secret().clone(),
+            refresh_token: token.refresh_token().map(|refresh| refresh.secret().clone()),
+            expires_in: token.expires_in(),
+            scopes: token
+                .scopes()
+                .map(|scopes| normalize_scopes(scopes.iter().map(|scope| scope.as_str()))),
+        }
+    }
+}
+
+/// The signed-in account. `verified_email` is only set for a primary address
+/// the forge has verified.
+pub struct ForgeIdentity {
+    pub id: i64,
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub verified_email: Option<String>,
+}
+
+/// A webhook delivery whose signature checked out.
+pub struct WebhookDelivery {
+    pub id: String,
+    pub event: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Dispatch {
+    Processed,
+    Ignored,
+}
+
+pub enum CreateRef {
+    Created,
+    AlreadyExists,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgeIssue {
+    pub node_id: String,
+    pub number: i32,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub state_reason: Option<String>,
+    pub html_url: String,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub labels: Vec<ForgeLabel>,
+    #[serde(default)]
+    pub assignees: Vec<ForgeAccount>,
+    #[serde(default)]
+    pub milestone: Option<ForgeMilestone>,
+    /// Present when the "issue" is actually a pull request.
+    pub pull_request: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgeLabel {
+    pub node_id: String,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgeMilestone {
+    pub node_id: String,
+    pub number: i32,
+    pub title: String,
+    pub state: String,
+    pub due_on: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForgeAccount {
+    pub id: i64,
+    pub login: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+/// Changes to push to an issue; unset fields are left untouched.
+#[derive(Debug, Default, Serialize)]
+pub struct IssueUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignees: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct PullRequest {
+    pub number: i32,
+    pub title: String,
+    pub body: Option<String>,
+    pub html_url: String,
+    pub state: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub merged: bool,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub head: PullRequestHead,
+}
+
+#[derive(Deserialize)]
+pub struct PullRequestHead {
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+}
+
+#[async_trait]
+pub trait ForgeProvider: Send + Sync {
+    fn kind(&self) -> ForgeKind;
+
+    /// Scopes requested at every sign-in.
+    fn sign_in_scopes(&self) -> Vec<String>;
+
+    /// The canonical form of a repository reference, or `None` when it is
+    /// not one this forge accepts.
+    fn parse_repository(&self, repository: &str) -> Option<String>;
+
+    /// Starts an authorization-code flow with PKCE asking for `scopes`.
+    fn authorize_url(&self, scopes: &[String]) -> AuthorizeRequest;
+
+    async fn exchange_code(&self, code: String, pkce_verifier: String) -> Result<ForgeToken, AppError>;
+
+    async fn refresh_token(&self, refresh_token: String) -> Result<ForgeToken, AppError>;
+
+    async fn fetch_identity(&self, access_token: &str) -> Result<ForgeIdentity, AppError>;
+
+    /// Name and head commit of the repository's default branch.
+    async fn default_branch_head(&self, repository: &str, access_token: &str) -> Result<(String, String), AppError>;
+
+    async fn create_branch(
+        &self,
+        repository: &str,
+        access_token: &str,
+        name: &str,
+        sha: &str,
+    ) -> Result<CreateRef, AppError>;
+
+    /// One page of the repository's issues, most recently updated first.
+    /// Pages are numbered from 1; a short page is the last one. Forges that
+    /// list pull requests as issues mark them with `pull_request`.
+    async fn list_issues(
+        &self,
+        repository: &str,
+        page: u32,
+        per_page: u32,
+        access_token: Option<&str>,
+    ) -> Result<Vec<ForgeIssue>, AppError>;
+
+    async fn fetch_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: Option<&str>,
+    ) -> Result<ForgeIssue, AppError>;
+
+    async fn update_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: &str,
+        update: &IssueUpdate,
+    ) -> Result<ForgeIssue, AppError>;
+
+    /// Whether `login` can be assigned issues in the repository.
+    async fn is_assignable(&self, repository: &str, login: &str, access_token: Option<&str>) -> Result<bool, AppError>;
+
+    /// Checks the delivery's signature. `Forbidden` when no webhook secret is
+    /// configured, `Unauthorized` when the signature does not match.
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookDelivery, AppError>;
+
+    /// Applies a stored delivery once it has been acknowledged.
+    async fn handle_webhook(&self, state: &SharedState, event: &str, payload: Value) -> Result<Dispatch, AppError>;
+}
+
+/// The configured backends.
+#[derive(Clone)]
+pub struct Forges {
+    github: Arc<dyn ForgeProvider>,
+    gitlab: Option<Arc<dyn ForgeProvider>>,
+    gitea: Option<Arc<dyn ForgeProvider>>,
+}
+
+impl Forges {
+    pub fn new(
+        github: Arc<dyn ForgeProvider>,
+        gitlab: Option<Arc<dyn ForgeProvider>>,
+        gitea: Option<Arc<dyn ForgeProvider>>,
+    ) -> Self {
+        Self { github, gitlab, gitea }
+    }
+
+    pub fn github(&self) -> &dyn ForgeProvider {
+        self.github.as_ref()
+    }
+
+    /// Fails for a forge the server is not configured for.
+    pub fn get(&self, kind: ForgeKind) -> Result<&dyn ForgeProvider, AppError> {
+        let forge = match kind {
+            ForgeKind::GitHub => Some(self.github()),
+            ForgeKind::GitLab => self.gitlab.as_deref(),
+            ForgeKind::Gitea => self.gitea.as_deref(),
+        };
+        forge.ok_or_else(|| AppError::BadRequest(format!("{} is not configured", kind.as_str())))
+    }
+}
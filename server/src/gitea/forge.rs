@@ -0,0 +1,328 @@
+use crate::{
+    config::{AppConfig, GiteaConfig},
+    error::AppError,
+    forge::{
+        AuthorizeRequest, CreateRef, Dispatch, ForgeIdentity, ForgeIssue, ForgeKind, ForgeProvider, ForgeToken,
+        IssueUpdate, WebhookDelivery,
+    },
+    gitea::{
+        issues::GiteaIssue,
+        webhooks::{dispatch, verify_signature, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER},
+    },
+    github::parse_repository,
+    state::SharedState,
+};
+use axum::{async_trait, http::HeaderMap};
+use oauth2::{basic::BasicClient, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RefreshToken, Scope};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// Scopes requested at sign-in. Instances older than Gitea 1.20 grant every
+/// OAuth token full access and ignore them.
+const SIGN_IN_SCOPES: &[&str] = &["read:user", "write:issue", "write:repository"];
+
+/// The most items Gitea returns per page by default (`MAX_RESPONSE_ITEMS`);
+/// larger pages are assembled from several requests.
+const MAX_PAGE_SIZE: u32 = 50;
+
+/// A Gitea or Forgejo instance.
+pub struct GiteaForge {
+    http_client: Client,
+    oauth_client: BasicClient,
+    api_url: String,
+    webhook_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GiteaUser {
+    id: i64,
+    login: String,
+    full_name: Option<String>,
+    avatar_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GiteaEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+#[derive(Deserialize)]
+struct GiteaRepository {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaBranch {
+    commit: GiteaCommit,
+}
+
+#[derive(Deserialize)]
+struct GiteaCommit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaAssignee {
+    login: String,
+}
+
+#[derive(Serialize)]
+struct NewBranch<'a> {
+    new_branch_name: &'a str,
+    old_ref_name: &'a str,
+}
+
+/// Labels are replaced through their own endpoint, by name.
+#[derive(Serialize)]
+struct ReplaceLabels<'a> {
+    labels: &'a [String],
+}
+
+impl GiteaForge {
+    pub fn new(config: &AppConfig, gitea: &GiteaConfig, http_client: Client) -> Result<Self, AppError> {
+        Ok(Self {
+            http_client,
+            oauth_client: config.gitea_oauth_client(gitea)?,
+            api_url: gitea.api_url(),
+            webhook_secret: gitea.webhook_secret.clone(),
+        })
+    }
+
+    fn request(&self, method: Method, url: String, access_token: Option<&str>) -> RequestBuilder {
+        let request = self.http_client.request(method, url);
+        match access_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: String, access_token: Option<&str>) -> Result<T, AppError> {
+        let response = self.request(Method::GET, url, access_token).send().await?;
+        Ok(check(response)?.json::<T>().await?)
+    }
+}
+
+fn check(response: Response) -> Result<Response, AppError> {
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!("gitea api error: {}", response.status())));
+    }
+    Ok(response)
+}
+
+#[async_trait]
+impl ForgeProvider for GiteaForge {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::Gitea
+    }
+
+    fn sign_in_scopes(&self) -> Vec<String> {
+        SIGN_IN_SCOPES.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    fn parse_repository(&self, repository: &str) -> Option<String> {
+        parse_repository(repository).map(|(owner, name)| format!("{}/{}", owner, name))
+    }
+
+    fn authorize_url(&self, scopes: &[String]) -> AuthorizeRequest {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, csrf_token) = self
+            .oauth_client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.iter().cloned().map(Scope::new))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        AuthorizeRequest {
+            url: url.to_string(),
+            state: csrf_token.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+        }
+    }
+
+    async fn exchange_code(&self, code: String, pkce_verifier: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn refresh_token(&self, refresh_token: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn fetch_identity(&self, access_token: &str) -> Result<ForgeIdentity, AppError> {
+        let user = self
+            .get_json::<GiteaUser>(format!("{}/user", self.api_url), Some(access_token))
+            .await?;
+        let emails = self
+            .get_json::<Vec<GiteaEmail>>(format!("{}/user/emails", self.api_url), Some(access_token))
+            .await?;
+
+        Ok(ForgeIdentity {
+            id: user.id,
+            login: user.login,
+            name: user.full_name.filter(|name| !name.is_empty()),
+            avatar_url: user.avatar_url,
+            verified_email: emails
+                .into_iter()
+                .find(|email| email.primary && email.verified)
+                .map(|email| email.email),
+        })
+    }
+
+    async fn default_branch_head(&self, repository: &str, access_token: &str) -> Result<(String, String), AppError> {
+        let repo = self
+            .get_json::<GiteaRepository>(format!("{}/repos/{}", self.api_url, repository), Some(access_token))
+            .await?;
+        let branch = self
+            .get_json::<GiteaBranch>(
+                format!("{}/repos/{}/branches/{}", self.api_url, repository, repo.default_branch),
+                Some(access_token),
+            )
+            .await?;
+
+        Ok((repo.default_branch, branch.commit.id))
+    }
+
+    async fn create_branch(
+        &self,
+        repository: &str,
+        access_token: &str,
+        name: &str,
+        sha: &str,
+    ) -> Result<CreateRef, AppError> {
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}/repos/{}/branches", self.api_url, repository),
+                Some(access_token),
+            )
+            .json(&NewBranch {
+                new_branch_name: name,
+                old_ref_name: sha,
+            })
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(CreateRef::Created),
+            StatusCode::CONFLICT => Ok(CreateRef::AlreadyExists),
+            status => Err(AppError::BadRequest(format!("gitea api error: {}", status))),
+        }
+    }
+
+    async fn list_issues(
+        &self,
+        repository: &str,
+        page: u32,
+        per_page: u32,
+        access_token: Option<&str>,
+    ) -> Result<Vec<ForgeIssue>, AppError> {
+        // Pull requests are filtered out by the server (`type=issues`), and
+        // `sort=recentupdate` gives the order the trait promises; Gitea's
+        // default is newest created first.
+        let size = per_page.min(MAX_PAGE_SIZE);
+        let first = (page.saturating_sub(1) * per_page) / size + 1;
+        let mut issues = Vec::with_capacity(per_page as usize);
+        for server_page in first.. {
+            let url = format!(
+                "{}/repos/{}/issues?state=all&type=issues&sort=recentupdate&limit={}&page={}",
+                self.api_url, repository, size, server_page
+            );
+            let batch = self.get_json::<Vec<GiteaIssue>>(url, access_token).await?;
+            let last = batch.len() < size as usize;
+            issues.extend(batch.into_iter().map(ForgeIssue::from));
+            if last || issues.len() >= per_page as usize {
+                break;
+            }
+        }
+        Ok(issues)
+    }
+
+    async fn fetch_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: Option<&str>,
+    ) -> Result<ForgeIssue, AppError> {
+        let url = format!("{}/repos/{}/issues/{}", self.api_url, repository, number);
+        Ok(self.get_json::<GiteaIssue>(url, access_token).await?.into())
+    }
+
+    async fn update_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: &str,
+        update: &IssueUpdate,
+    ) -> Result<ForgeIssue, AppError> {
+        let url = format!("{}/repos/{}/issues/{}", self.api_url, repository, number);
+        if let Some(labels) = &update.labels {
+            let response = self
+                .request(Method::PUT, format!("{}/labels", url), Some(access_token))
+                .json(&ReplaceLabels { labels })
+                .send()
+                .await?;
+            check(response)?;
+        }
+
+        // The edit endpoint takes the remaining fields under GitHub's names.
+        let response = self
+            .request(Method::PATCH, url, Some(access_token))
+            .json(&IssueUpdate {
+                labels: None,
+                title: update.title.clone(),
+                body: update.body.clone(),
+                state: update.state,
+                assignees: update.assignees.clone(),
+            })
+            .send()
+            .await?;
+        Ok(check(response)?.json::<GiteaIssue>().await?.into())
+    }
+
+    async fn is_assignable(&self, repository: &str, login: &str, access_token: Option<&str>) -> Result<bool, AppError> {
+        let assignees = self
+            .get_json::<Vec<GiteaAssignee>>(format!("{}/repos/{}/assignees", self.api_url, repository), access_token)
+            .await?;
+        Ok(assignees.iter().any(|assignee| assignee.login.eq_ignore_ascii_case(login)))
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookDelivery, AppError> {
+        let secret = self
+            .webhook_secret
+            .as_deref()
+            .ok_or_else(|| AppError::Forbidden("webhooks are not configured".into()))?;
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        if !verify_signature(secret, body, header(SIGNATURE_HEADER)) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let required = |name: &str| {
+            header(name)
+                .map(str::to_string)
+                .ok_or_else(|| AppError::BadRequest(format!("missing {} header", name)))
+        };
+        Ok(WebhookDelivery {
+            event: required(EVENT_HEADER)?,
+            id: required(DELIVERY_HEADER)?,
+        })
+    }
+
+    async fn handle_webhook(&self, state: &SharedState, event: &str, payload: Value) -> Result<Dispatch, AppError> {
+        dispatch(state, self, event, payload).await
+    }
+}
@@ -0,0 +1,100 @@
+//! Gitea's issue payloads and their conversion into the forge types.
+//!
+//! Gitea has no GraphQL node IDs, so issues, labels and milestones get
+//! `gitea:<Type>:<id>` IDs from their instance-wide numeric IDs.
+
+use crate::forge::{ForgeAccount, ForgeIssue, ForgeLabel, ForgeMilestone};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub struct GiteaIssue {
+    pub id: i64,
+    pub number: i32,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub html_url: String,
+    pub updated_at: DateTime<Utc>,
+    /// Gitea sends `null` rather than an empty list.
+    pub labels: Option<Vec<GiteaLabel>>,
+    pub assignees: Option<Vec<GiteaAccount>>,
+    pub milestone: Option<GiteaMilestone>,
+    pub pull_request: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GiteaLabel {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GiteaAccount {
+    pub id: i64,
+    pub login: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GiteaMilestone {
+    pub id: i64,
+    pub title: String,
+    pub state: String,
+    pub due_on: Option<DateTime<Utc>>,
+}
+
+impl From<GiteaIssue> for ForgeIssue {
+    fn from(issue: GiteaIssue) -> Self {
+        Self {
+            node_id: format!("gitea:Issue:{}", issue.id),
+            number: issue.number,
+            title: issue.title,
+            // An empty description is sent as "", where GitHub sends null.
+            body: issue.body.filter(|body| !body.is_empty()),
+            state: issue.state,
+            state_reason: None,
+            html_url: issue.html_url,
+            updated_at: issue.updated_at,
+            labels: issue.labels.unwrap_or_default().into_iter().map(Into::into).collect(),
+            assignees: issue.assignees.unwrap_or_default().into_iter().map(Into::into).collect(),
+            milestone: issue.milestone.map(Into::into),
+            pull_request: issue.pull_request,
+        }
+    }
+}
+
+impl From<GiteaLabel> for ForgeLabel {
+    fn from(label: GiteaLabel) -> Self {
+        Self {
+            node_id: format!("gitea:Label:{}", label.id),
+            name: label.name,
+            color: label.color.trim_start_matches('#').to_string(),
+        }
+    }
+}
+
+impl From<GiteaAccount> for ForgeAccount {
+    fn from(account: GiteaAccount) -> Self {
+        Self {
+            id: account.id,
+            login: account.login,
+            avatar_url: account.avatar_url,
+        }
+    }
+}
+
+impl From<GiteaMilestone> for ForgeMilestone {
+    fn from(milestone: GiteaMilestone) -> Self {
+        Self {
+            node_id: format!("gitea:Milestone:{}", milestone.id),
+            // Milestones are not numbered per repository on Gitea.
+            number: i32::try_from(milestone.id).unwrap_or(i32::MAX),
+            title: milestone.title,
+            state: milestone.state,
+            due_on: milestone.due_on,
+        }
+    }
+}
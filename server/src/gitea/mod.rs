@@ -0,0 +1,6 @@
+//! Gitea and Forgejo, which share an API modelled on GitHub's REST API:
+//! repositories are `owner/name` and issues are numbered per repository.
+
+pub mod forge;
+pub mod issues;
+pub mod webhooks;
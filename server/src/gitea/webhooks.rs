@@ -0,0 +1,85 @@
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeIssue, ForgeProvider, PullRequest},
+    gitea::issues::GiteaIssue,
+    github::{
+        pull_requests::record_pull_request,
+        sync::{sync_issue_event, unlink_issue},
+        webhooks::verify_signature as verify_hub_signature,
+    },
+    state::SharedState,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::info;
+
+pub const SIGNATURE_HEADER: &str = "x-gitea-signature";
+pub const EVENT_HEADER: &str = "x-gitea-event";
+pub const DELIVERY_HEADER: &str = "x-gitea-delivery";
+
+#[derive(Deserialize)]
+struct WebhookRepository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct IssuesEvent {
+    action: String,
+    issue: GiteaIssue,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    pull_request: PullRequest,
+    repository: WebhookRepository,
+}
+
+/// Checks an `X-Gitea-Signature` value, the bare hex HMAC-SHA256 of the raw
+/// request body. Forgejo sends the same header.
+pub fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let prefixed = signature.map(|hex_digest| format!("sha256={}", hex_digest));
+    verify_hub_signature(secret, body, prefixed.as_deref())
+}
+
+/// Routes a stored delivery to the handler for its `X-Gitea-Event`. Label,
+/// assignee and milestone changes arrive as `issues` events too.
+pub async fn dispatch(
+    state: &SharedState,
+    forge: &dyn ForgeProvider,
+    event: &str,
+    payload: Value,
+) -> Result<Dispatch, AppError> {
+    match event {
+        "issues" => handle_issues(state, forge, serde_json::from_value(payload)?).await,
+        "pull_request" => handle_pull_request(state, serde_json::from_value(payload)?).await,
+        _ => Ok(Dispatch::Ignored),
+    }
+}
+
+async fn handle_issues(state: &SharedState, forge: &dyn ForgeProvider, event: IssuesEvent) -> Result<Dispatch, AppError> {
+    let issue: ForgeIssue = event.issue.into();
+    if event.action == "deleted" {
+        return unlink_issue(state, &issue.node_id).await;
+    }
+
+    sync_issue_event(state, forge, &event.repository.full_name, issue).await
+}
+
+async fn handle_pull_request(state: &SharedState, event: PullRequestEvent) -> Result<Dispatch, AppError> {
+    let linked = record_pull_request(state, &event.repository.full_name, &event.pull_request).await?;
+    info!(
+        repository = %event.repository.full_name,
+        number = event.pull_request.number,
+        action = %event.action,
+        linked,
+        "processed gitea pull request event"
+    );
+
+    Ok(if linked > 0 {
+        Dispatch::Processed
+    } else {
+        Dispatch::Ignored
+    })
+}
@@ -0,0 +1,152 @@
+use crate::{
+    error::AppError,
+    github::client::GitHubClient,
+    state::AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// GitHub rejects app JWTs valid for longer than ten minutes.
+const APP_JWT_LIFETIME_MINUTES: i64 = 9;
+/// Cached installation tokens are renewed this long before they expire.
+const TOKEN_RENEW_MARGIN_MINUTES: i64 = 5;
+
+#[derive(Clone)]
+pub struct GitHubApp {
+    app_id: i64,
+    encoding_key: EncodingKey,
+    /// One slot per installation, so renewing one installation's token
+    /// does not hold up lookups for the others.
+    installation_tokens: Arc<Mutex<HashMap<i64, TokenSlot>>>,
+}
+
+type TokenSlot = Arc<tokio::sync::Mutex<Option<InstallationToken>>>;
+
+#[derive(Clone, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct AppClaims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+impl GitHubApp {
+    pub fn new(app_id: i64, private_key_pem: &str) -> Result<Self, AppError> {
+        let encoding_key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+            .map_err(|e| AppError::Config(format!("invalid GITHUB_APP_PRIVATE_KEY: {}", e)))?;
+
+        Ok(Self {
+            app_id,
+            encoding_key,
+            installation_tokens: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Short-lived RS256 JWT that authenticates as the app itself.
+    fn app_jwt(&self) -> Result<String, AppError> {
+        // Backdated to tolerate clock drift between us and GitHub.
+        let issued_at = Utc::now() - Duration::seconds(60);
+        let claims = AppClaims {
+            iat: issued_at.timestamp(),
+            exp: (issued_at + Duration::minutes(APP_JWT_LIFETIME_MINUTES)).timestamp(),
+            iss: self.app_id.to_string(),
+        };
+
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.encoding_key).map_err(AppError::from)
+    }
+
+    /// Returns a token for the installation, reusing the cached one until it
+    /// is close to expiry.
+    pub async fn installation_token(
+        &self,
+        client: &GitHubClient,
+        api_base: &str,
+        installation_id: i64,
+    ) -> Result<String, AppError> {
+        let slot = self
+            .installation_tokens
+            .lock()
+            .unwrap()
+            .entry(installation_id)
+            .or_default()
+            .clone();
+        // Held across the request so concurrent callers for the same
+        // installation share one exchange.
+        let mut current = slot.lock().await;
+        if let Some(cached) = current.as_ref() {
+            if cached.expires_at > Utc::now() + Duration::minutes(TOKEN_RENEW_MARGIN_MINUTES) {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let response = client
+            .post(format!("{}/app/installations/{}/access_tokens", api_base, installation_id))
+            .bearer_auth(&self.app_jwt()?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(AppError::BadRequest(format!(
+                "github api error: {}",
+                response.status()
+            )));
+        }
+
+        let issued = response.json::<InstallationToken>().await?;
+        let token = issued.token.clone();
+        *current = Some(issued);
+
+        Ok(token)
+    }
+
+    /// Drops a cached token, e.g. after the installation was removed.
+    pub fn forget_installation(&self, installation_id: i64) {
+        self.installation_tokens.lock().unwrap().remove(&installation_id);
+    }
+}
+
+/// The active installation of the app that covers `repository`, if any.
+pub async fn installation_for_repository(state: &AppState, repository: &str) -> Result<Option<i64>, AppError> {
+    if state.github_app.is_none() {
+        return Ok(None);
+    }
+
+    let installation_id = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT i.id
+        FROM github_installation_repositories r
+        JOIN github_installations i ON i.id = r.installation_id
+        WHERE LOWER(r.full_name) = LOWER($1) AND i.suspended_at IS NULL
+        LIMIT 1
+        "#,
+    )
+    .bind(repository)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    Ok(installation_id)
+}
+
+/// An installation token for the repository when the app is configured and
+/// installed on it.
+pub async fn installation_token_for(state: &AppState, repository: &str) -> Result<Option<String>, AppError> {
+    let (Some(app), Some(installation_id)) =
+        (&state.github_app, installation_for_repository(state, repository).await?)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        app.installation_token(&state.github, &state.config.github_api_url, installation_id)
+            .await?,
+    ))
+}
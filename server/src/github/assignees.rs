@@ -0,0 +1,44 @@
+use crate::{
+    error::AppError,
+    forge::{ForgeAccount, ForgeKind},
+};
+use sqlx::PgConnection;
+
+/// Makes sure every forge account assigned to an issue has a Keyflow user,
+/// creating unclaimed placeholders keyed by the forge's account ID for people
+/// who have not signed in yet. Placeholders track login and avatar changes;
+/// claimed users are refreshed when they sign in.
+pub async fn ensure_assignee_users(
+    conn: &mut PgConnection,
+    kind: ForgeKind,
+    accounts: &[ForgeAccount],
+) -> Result<(), AppError> {
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    let ids: Vec<i64> = accounts.iter().map(|account| account.id).collect();
+    let logins: Vec<&str> = accounts.iter().map(|account| account.login.as_str()).collect();
+    let avatars: Vec<Option<&str>> = accounts.iter().map(|account| account.avatar_url.as_deref()).collect();
+
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO users ({column}, login, avatar_url, claimed)
+        SELECT a.id, a.login, a.avatar_url, false
+        FROM UNNEST($1::bigint[], $2::text[], $3::text[]) AS a (id, login, avatar_url)
+        ON CONFLICT ({column}) DO UPDATE
+        SET login = EXCLUDED.login,
+            avatar_url = COALESCE(EXCLUDED.avatar_url, users.avatar_url)
+        WHERE NOT users.claimed
+          AND (users.login, users.avatar_url) IS DISTINCT FROM
+              (EXCLUDED.login, COALESCE(EXCLUDED.avatar_url, users.avatar_url))
+        "#,
+        column = kind.user_id_column(),
+    ))
+    .bind(&ids)
+    .bind(&logins)
+    .bind(&avatars)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "{login}/{task-key}-{slug}";
+
+/// Longest slug, in characters, that goes into a branch name.
+const MAX_SLUG_CHARS: usize = 48;
+
+/// Turns a task title into a branch-name fragment. Latin letters lose their
+/// accents ("Café" becomes "cafe"); letters of other scripts are kept whole
+/// with their marks, so "Исправить вход" becomes "исправить-вход" and kana
+/// keep their dakuten. Everything else collapses into single dashes.
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    let mut pending_dash = false;
+    let mut length = 0;
+    // Whether marks following the last letter belong in the slug.
+    let mut keep_marks = false;
+
+    for c in title.nfc() {
+        // Marks left over after composition stay with the letter before them.
+        if is_combining_mark(c) {
+            if keep_marks {
+                slug.push(c);
+            }
+            continue;
+        }
+        if !c.is_alphanumeric() {
+            pending_dash = true;
+            keep_marks = false;
+            continue;
+        }
+        if length >= MAX_SLUG_CHARS {
+            break;
+        }
+        if pending_dash && !slug.is_empty() {
+            slug.push('-');
+            length += 1;
+        }
+        pending_dash = false;
+
+        if std::iter::once(c).nfkd().next().is_some_and(is_latin) {
+            let base = std::iter::once(c)
+                .nfkd()
+                .filter(|d| d.is_alphanumeric() && !is_combining_mark(*d));
+            slug.extend(base.flat_map(char::to_lowercase));
+            keep_marks = false;
+        } else {
+            slug.extend(c.to_lowercase());
+            keep_marks = true;
+        }
+        length += 1;
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+/// ASCII and the Latin letter blocks, whose accents can be dropped without
+/// turning a letter into a different one.
+fn is_latin(c: char) -> bool {
+    c.is_ascii()
+        || matches!(
+            c,
+            '\u{00C0}'..='\u{024F}'
+                | '\u{1E00}'..='\u{1EFF}'
+                | '\u{2C60}'..='\u{2C7F}'
+                | '\u{A720}'..='\u{A7FF}'
+                | '\u{AB30}'..='\u{AB6F}'
+        )
+}
+
+/// Fills in `{login}`, `{task-key}`, `{number}` and `{slug}`.
+pub fn render_branch_name(template: &str, login: &str, task_key: &str, number: i32, title: &str) -> String {
+    let slug = slugify(title);
+    let name = template
+        .replace("{login}", login)
+        .replace("{task-key}", &task_key.to_lowercase())
+        .replace("{number}", &number.to_string())
+        .replace("{slug}", &slug);
+
+    // An empty slug would otherwise leave a dangling separator behind.
+    name.trim_end_matches(['-', '/', '.']).to_string()
+}
+
+/// Mirrors the rules of `git check-ref-format` for a branch name.
+pub fn is_valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 255
+        && !name.starts_with(['/', '-', '.'])
+        && !name.ends_with(['/', '.'])
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && !name.split('/').any(|part| part.starts_with('.') || part.ends_with(".lock"))
+        && !name
+            .chars()
+            .any(|c| c.is_control() || c.is_whitespace() || matches!(c, '~' | '^' | ':' | '?' | '*' | '[' | '\\'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin_letters_lose_their_accents() {
+        assert_eq!(slugify("Café crème brûlée"), "cafe-creme-brulee");
+        assert_eq!(slugify("Ｆｕｌｌｗｉｄｔｈ ﬁx"), "fullwidth-fix");
+    }
+
+    #[test]
+    fn other_scripts_keep_their_letters_and_marks() {
+        assert_eq!(slugify("Исправить вход, й"), "исправить-вход-й");
+        assert_eq!(slugify("ガイドをパンに"), "ガイドをパンに");
+        assert_eq!(slugify("로그인 수정"), "로그인-수정");
+        assert_eq!(slugify("लॉगिन ठीक करें"), "लॉगिन-ठीक-करें");
+        assert_eq!(slugify("क्या"), "क्या");
+    }
+
+    #[test]
+    fn separators_collapse_into_single_dashes() {
+        assert_eq!(slugify("  Fix -- the   login bug!! "), "fix-the-login-bug");
+        assert_eq!(slugify("?!"), "");
+    }
+
+    #[test]
+    fn slugs_are_capped_at_48_characters() {
+        assert_eq!(slugify(&"a".repeat(60)).chars().count(), MAX_SLUG_CHARS);
+        let slug = slugify(&"word ".repeat(20));
+        assert!(slug.chars().count() <= MAX_SLUG_CHARS);
+        assert!(!slug.ends_with('-'));
+    }
+}
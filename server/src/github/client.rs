@@ -0,0 +1,407 @@
+use crate::error::AppError;
+use chrono::{DateTime, TimeZone, Utc};
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, RETRY_AFTER, USER_AGENT},
+    Client, Method, Response, StatusCode,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Attempts per request, the first one included.
+const MAX_ATTEMPTS: u32 = 3;
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(8);
+/// Rate-limit waits up to this long are slept through; longer ones fail the
+/// request so callers are not stuck for minutes.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+/// GitHub asks for at least a minute when a secondary limit gives no hint.
+const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+/// Consecutive failures (5xx or transport errors) that open the breaker.
+const BREAKER_THRESHOLD: u32 = 5;
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+/// Remaining quota below this share of the limit is logged.
+const LOW_QUOTA_RATIO: f64 = 0.1;
+
+/// Wraps the shared HTTP client for GitHub API calls: tracks the rate limit
+/// quota of every token from response headers, waits out primary and
+/// secondary rate limits, retries idempotent requests with jittered backoff
+/// and stops calling GitHub for a while when it keeps failing.
+#[derive(Clone)]
+pub struct GitHubClient {
+    http: Client,
+    quotas: Arc<Mutex<HashMap<(String, String), Quota>>>,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Quota {
+    /// Short fingerprint of the token; the token itself is never exposed.
+    pub token: String,
+    pub resource: String,
+    pub limit: u32,
+    pub remaining: u32,
+    pub used: u32,
+    pub reset_at: DateTime<Utc>,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BreakerStatus {
+    pub open: bool,
+    pub consecutive_failures: u32,
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+pub struct GitHubRequest<'a> {
+    client: &'a GitHubClient,
+    method: Method,
+    url: String,
+    access_token: Option<&'a str>,
+    headers: HeaderMap,
+    body: Option<Result<Vec<u8>, serde_json::Error>>,
+    retryable: bool,
+}
+
+impl GitHubClient {
+    pub fn new(http: Client) -> Self {
+        Self {
+            http,
+            quotas: Arc::new(Mutex::new(HashMap::new())),
+            breaker: Arc::new(Mutex::new(Breaker::default())),
+        }
+    }
+
+    pub fn get(&self, url: impl Into<String>) -> GitHubRequest<'_> {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: impl Into<String>) -> GitHubRequest<'_> {
+        self.request(Method::POST, url)
+    }
+
+    pub fn patch(&self, url: impl Into<String>) -> GitHubRequest<'_> {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn request(&self, method: Method, url: impl Into<String>) -> GitHubRequest<'_> {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("keyflow-server"));
+        headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
+
+        GitHubRequest {
+            client: self,
+            retryable: method.is_idempotent(),
+            method,
+            url: url.into(),
+            access_token: None,
+            headers,
+            body: None,
+        }
+    }
+
+    /// Last known quota of every token and resource, soonest reset first.
+    pub fn quotas(&self) -> Vec<Quota> {
+        let mut quotas: Vec<Quota> = self.quotas.lock().unwrap().values().cloned().collect();
+        quotas.sort_by_key(|quota| quota.reset_at);
+        quotas
+    }
+
+    pub fn breaker_status(&self) -> BreakerStatus {
+        let breaker = self.breaker.lock().unwrap();
+        let retry_after = breaker
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()));
+
+        BreakerStatus {
+            open: retry_after.is_some(),
+            consecutive_failures: breaker.consecutive_failures,
+            retry_after_secs: retry_after.map(|wait| wait.as_secs().max(1)),
+        }
+    }
+
+    /// Fails fast while the breaker is open. Once the cooldown has passed
+    /// requests go through again; a single further failure re-opens it.
+    fn check_breaker(&self) -> Result<(), AppError> {
+        let breaker = self.breaker.lock().unwrap();
+        match breaker
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+        {
+            Some(wait) => Err(AppError::GitHubUnavailable(wait)),
+            None => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.consecutive_failures = 0;
+        breaker.open_until = None;
+    }
+
+    fn record_failure(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.consecutive_failures += 1;
+        if breaker.consecutive_failures >= BREAKER_THRESHOLD {
+            warn!(
+                failures = breaker.consecutive_failures,
+                cooldown_secs = BREAKER_COOLDOWN.as_secs(),
+                "github keeps failing; pausing requests"
+            );
+            breaker.open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+        }
+    }
+
+    /// How long to wait before the token's quota for `resource` is back, if
+    /// it is known to be exhausted.
+    fn exhausted_for(&self, token: &str, resource: &str) -> Option<Duration> {
+        let quotas = self.quotas.lock().unwrap();
+        let quota = quotas.get(&(token.to_string(), resource.to_string()))?;
+        if quota.remaining > 0 {
+            return None;
+        }
+        (quota.reset_at - Utc::now()).to_std().ok()
+    }
+
+    fn record_quota(&self, token: &str, headers: &HeaderMap) {
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+        let (Some(limit), Some(remaining), Some(reset)) = (
+            number("x-ratelimit-limit"),
+            number("x-ratelimit-remaining"),
+            number("x-ratelimit-reset"),
+        ) else {
+            return;
+        };
+        let Some(reset_at) = Utc.timestamp_opt(reset, 0).single() else {
+            return;
+        };
+        let resource = headers
+            .get("x-ratelimit-resource")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("core")
+            .to_string();
+
+        let quota = Quota {
+            token: token.to_string(),
+            resource: resource.clone(),
+            limit: limit.max(0) as u32,
+            remaining: remaining.max(0) as u32,
+            used: number("x-ratelimit-used").unwrap_or(limit - remaining).max(0) as u32,
+            reset_at,
+            observed_at: Utc::now(),
+        };
+        if limit > 0 && (remaining as f64) < limit as f64 * LOW_QUOTA_RATIO {
+            warn!(
+                token = %quota.token,
+                resource = %quota.resource,
+                remaining = quota.remaining,
+                limit = quota.limit,
+                reset_at = %quota.reset_at,
+                "github rate limit quota running low"
+            );
+        }
+        self.quotas.lock().unwrap().insert((token.to_string(), resource), quota);
+    }
+}
+
+impl<'a> GitHubRequest<'a> {
+    pub fn bearer_auth(mut self, access_token: &'a str) -> Self {
+        self.access_token = Some(access_token);
+        self
+    }
+
+    /// Anonymous requests get a much smaller quota but work for public data.
+    pub fn access_token(mut self, access_token: Option<&'a str>) -> Self {
+        self.access_token = access_token;
+        self
+    }
+
+    pub fn header(mut self, name: HeaderName, value: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Marks a non-idempotent request as safe to repeat, e.g. a GraphQL
+    /// query sent as POST.
+    pub fn retry_on_failure(mut self) -> Self {
+        self.retryable = true;
+        self
+    }
+
+    pub fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Self {
+        self.body = Some(serde_json::to_vec(body));
+        self.headers
+            .insert(reqwest::header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self
+    }
+
+    /// Sends the request. Rate limits are waited out (or reported as
+    /// [`AppError::GitHubRateLimited`] when the wait would be too long) and
+    /// transport errors and 5xx responses to idempotent requests are retried;
+    /// any other response is returned for the caller to inspect.
+    pub async fn send(self) -> Result<Response, AppError> {
+        let client = self.client;
+        let body = self.body.transpose()?;
+        let token = token_fingerprint(self.access_token);
+        let resource = resource_for(&self.url);
+        let retryable = self.retryable;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            client.check_breaker()?;
+            if let Some(wait) = client.exhausted_for(&token, resource) {
+                wait_for_rate_limit(wait).await?;
+            }
+
+            let mut request = client
+                .http
+                .request(self.method.clone(), &self.url)
+                .headers(self.headers.clone());
+            if let Some(access_token) = self.access_token {
+                request = request.bearer_auth(access_token);
+            }
+            if let Some(body) = &body {
+                request = request.body(body.clone());
+            }
+
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(err) => {
+                    client.record_failure();
+                    if retryable && attempt < MAX_ATTEMPTS {
+                        warn!(error = %err, attempt, url = %self.url, "github request failed; retrying");
+                        tokio::time::sleep(backoff(attempt)).await;
+                        continue;
+                    }
+                    return Err(err.into());
+                }
+            };
+            client.record_quota(&token, response.headers());
+
+            let status = response.status();
+            if status.is_server_error() {
+                client.record_failure();
+                if retryable && attempt < MAX_ATTEMPTS {
+                    warn!(%status, attempt, url = %self.url, "github server error; retrying");
+                    tokio::time::sleep(backoff(attempt)).await;
+                    continue;
+                }
+                return Ok(response);
+            }
+            client.record_success();
+
+            if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+            let (wait, response) = rate_limit_wait(response).await?;
+            let Some(wait) = wait else {
+                return Ok(response);
+            };
+            // A rate-limited request was not processed, so retrying is safe
+            // whatever the method.
+            if attempt >= MAX_ATTEMPTS {
+                return Err(AppError::GitHubRateLimited(wait));
+            }
+            warn!(wait_secs = wait.as_secs(), url = %self.url, "github rate limit hit; waiting");
+            wait_for_rate_limit(wait).await?;
+        }
+    }
+}
+
+/// Decides whether a 403/429 is a rate limit and how long to back off. A
+/// 403 without rate-limit headers has to be told apart from a permission
+/// error by its message, so the body is read and put back.
+async fn rate_limit_wait(response: Response) -> Result<(Option<Duration>, Response), AppError> {
+    let headers = response.headers();
+    let retry_after = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    if let Some(wait) = retry_after {
+        return Ok((Some(wait), response));
+    }
+
+    let exhausted = headers
+        .get("x-ratelimit-remaining")
+        .is_some_and(|value| value.as_bytes() == b"0");
+    if exhausted {
+        let wait = headers
+            .get("x-ratelimit-reset")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<i64>().ok())
+            .and_then(|reset| Utc.timestamp_opt(reset, 0).single())
+            .and_then(|reset_at| (reset_at - Utc::now()).to_std().ok())
+            .unwrap_or(SECONDARY_LIMIT_WAIT);
+        return Ok((Some(wait), response));
+    }
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = response.bytes().await?;
+    let secondary = status == StatusCode::TOO_MANY_REQUESTS
+        || String::from_utf8_lossy(&bytes)
+            .to_ascii_lowercase()
+            .contains("secondary rate limit");
+
+    let mut rebuilt = http::Response::new(bytes);
+    *rebuilt.status_mut() = status;
+    *rebuilt.headers_mut() = headers;
+    Ok((secondary.then_some(SECONDARY_LIMIT_WAIT), Response::from(rebuilt)))
+}
+
+async fn wait_for_rate_limit(wait: Duration) -> Result<(), AppError> {
+    if wait > MAX_RATE_LIMIT_WAIT {
+        return Err(AppError::GitHubRateLimited(wait));
+    }
+    tokio::time::sleep(wait).await;
+    Ok(())
+}
+
+/// Exponential backoff with full jitter.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(BACKOFF_MAX);
+    Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64))
+}
+
+/// The rate limit bucket GitHub charges a request to.
+fn resource_for(url: &str) -> &'static str {
+    let path = url.split('?').next().unwrap_or(url);
+    if path.ends_with("/graphql") {
+        "graphql"
+    } else if path.contains("/search/") {
+        "search"
+    } else {
+        "core"
+    }
+}
+
+/// Identifies a token without revealing it, for keying per-token state.
+pub fn token_fingerprint(access_token: Option<&str>) -> String {
+    match access_token {
+        Some(token) => hex::encode(&Sha256::digest(token.as_bytes())[..6]),
+        None => "anonymous".to_string(),
+    }
+}
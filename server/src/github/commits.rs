@@ -0,0 +1,141 @@
+use crate::{
+    error::AppError,
+    github::task_refs::{find_commit_references, resolve_task_keys, RefAction},
+    jobs::{enqueue, Job},
+    routes::{fan_out, record_event},
+    state::SharedState,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct PushCommit {
+    pub id: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+    pub url: String,
+    pub author: CommitAuthor,
+}
+
+#[derive(Deserialize)]
+pub struct CommitAuthor {
+    pub name: String,
+    /// Only present when the commit email belongs to a GitHub account.
+    pub username: Option<String>,
+}
+
+/// Attaches pushed commits to the tasks their messages reference. Closing
+/// references (`fixes KF-12`) only complete the task once the commit is on
+/// the repository's default branch; pushes to other branches just attach it.
+/// Returns the number of commits attached.
+pub async fn record_push(
+    state: &SharedState,
+    repository: &str,
+    git_ref: &str,
+    default_branch: Option<&str>,
+    commits: &[PushCommit],
+) -> Result<usize, AppError> {
+    let on_default_branch =
+        default_branch.is_some_and(|branch| git_ref.strip_prefix("refs/heads/") == Some(branch));
+    let mut attached = 0;
+    let mut closed = Vec::new();
+    let mut tx = state.pool.begin().await?;
+
+    for commit in commits {
+        let references = find_commit_references(&commit.message);
+        if references.is_empty() {
+            continue;
+        }
+
+        // Logins are only unique per forge, so the author is looked up among
+        // GitHub accounts and left unset when that is still ambiguous.
+        let author_id = match &commit.author.username {
+            Some(login) => {
+                let matches = sqlx::query_scalar::<_, Uuid>(
+                    "SELECT id FROM users WHERE github_id IS NOT NULL AND LOWER(login) = LOWER($1) LIMIT 2",
+                )
+                .bind(login)
+                .fetch_all(&mut *tx)
+                .await?;
+                match matches[..] {
+                    [id] => Some(id),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+
+        for reference in references {
+            let closes = reference.action == RefAction::Closes;
+            for task_id in resolve_task_keys(&mut tx, std::slice::from_ref(&reference.key)).await? {
+                sqlx::query(
+                    r#"
+                    INSERT INTO task_commits
+                        (task_id, repository, sha, message, url, author_name, author_login, author_id, closes, committed_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    ON CONFLICT (task_id, repository, sha) DO UPDATE
+                    SET closes = task_commits.closes OR EXCLUDED.closes
+                    "#,
+                )
+                .bind(task_id)
+                .bind(repository)
+                .bind(&commit.id)
+                .bind(&commit.message)
+                .bind(&commit.url)
+                .bind(&commit.author.name)
+                .bind(&commit.author.username)
+                .bind(author_id)
+                .bind(closes)
+                .bind(commit.timestamp)
+                .execute(&mut *tx)
+                .await?;
+                attached += 1;
+
+                if closes && on_default_branch && close_task(&mut tx, task_id, author_id, repository, &commit.id).await? {
+                    closed.push(task_id);
+                }
+            }
+        }
+    }
+    for task_id in closed {
+        enqueue(&mut *tx, &Job::IssueSync { task_id, actor_id: None }).await?;
+    }
+    tx.commit().await?;
+
+    Ok(attached)
+}
+
+async fn close_task(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    author_id: Option<Uuid>,
+    repository: &str,
+    sha: &str,
+) -> Result<bool, AppError> {
+    let Some(previous) = sqlx::query_scalar::<_, String>(
+        "SELECT status FROM tasks WHERE id = $1 AND status NOT IN ('done', 'canceled') FOR UPDATE",
+    )
+    .bind(task_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query("UPDATE tasks SET status = 'done', completed_at = COALESCE(completed_at, now()) WHERE id = $1")
+        .bind(task_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let data = json!({
+        "status": { "from": previous, "to": "done" },
+        "commit": { "repository": repository, "sha": sha },
+    });
+    record_event(&mut *conn, task_id, author_id, "commit_closed", data.clone()).await?;
+    fan_out(&mut *conn, task_id, author_id, "task_updated", data).await?;
+
+    Ok(true)
+}
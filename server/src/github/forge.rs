@@ -0,0 +1,241 @@
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    forge::{
+        AuthorizeRequest, CreateRef, Dispatch, ForgeIdentity, ForgeIssue, ForgeKind, ForgeProvider, ForgeToken,
+        IssueUpdate, WebhookDelivery,
+    },
+    github::{
+        client::GitHubClient,
+        git,
+        issues::{self, fetch_issue_page, IssuePage},
+        parse_repository,
+        scopes::BASE_SCOPES,
+        webhooks::{dispatch, verify_signature, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER},
+    },
+    state::SharedState,
+};
+use axum::{async_trait, http::HeaderMap};
+use oauth2::{basic::BasicClient, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RefreshToken, Scope};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+/// GitHub.com or a GitHub Enterprise Server, depending on configuration.
+pub struct GitHubForge {
+    client: GitHubClient,
+    http_client: Client,
+    oauth_client: BasicClient,
+    api_url: String,
+    webhook_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubUser {
+    id: i64,
+    login: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+impl GitHubForge {
+    pub fn new(config: &AppConfig, client: GitHubClient, http_client: Client) -> Result<Self, AppError> {
+        Ok(Self {
+            client,
+            http_client,
+            oauth_client: config.github_oauth_client()?,
+            api_url: config.github_api_url.clone(),
+            webhook_secret: config.github_webhook_secret.clone(),
+        })
+    }
+
+    /// The user's primary address if GitHub has verified it. `None` when
+    /// there is none, or when the token predates the `user:email` scope.
+    async fn fetch_verified_primary_email(&self, access_token: &str) -> Result<Option<String>, AppError> {
+        let response = self
+            .client
+            .get(format!("{}/user/emails", self.api_url))
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+                warn!("github token cannot read user emails; keeping stored email");
+                return Ok(None);
+            }
+            status => return Err(AppError::BadRequest(format!("github api error: {}", status))),
+        }
+
+        let emails = response.json::<Vec<GitHubEmail>>().await?;
+        Ok(emails
+            .into_iter()
+            .find(|email| email.primary && email.verified)
+            .map(|email| email.email))
+    }
+}
+
+#[async_trait]
+impl ForgeProvider for GitHubForge {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitHub
+    }
+
+    fn sign_in_scopes(&self) -> Vec<String> {
+        BASE_SCOPES.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    fn parse_repository(&self, repository: &str) -> Option<String> {
+        parse_repository(repository).map(|(owner, name)| format!("{}/{}", owner, name))
+    }
+
+    fn authorize_url(&self, scopes: &[String]) -> AuthorizeRequest {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, csrf_token) = self
+            .oauth_client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.iter().cloned().map(Scope::new))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        AuthorizeRequest {
+            url: url.to_string(),
+            state: csrf_token.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+        }
+    }
+
+    async fn exchange_code(&self, code: String, pkce_verifier: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn refresh_token(&self, refresh_token: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn fetch_identity(&self, access_token: &str) -> Result<ForgeIdentity, AppError> {
+        let response = self
+            .client
+            .get(format!("{}/user", self.api_url))
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(AppError::BadRequest(format!(
+                "github api error: {}",
+                response.status()
+            )));
+        }
+        let user = response.json::<GitHubUser>().await?;
+        let verified_email = self.fetch_verified_primary_email(access_token).await?;
+
+        Ok(ForgeIdentity {
+            id: user.id,
+            login: user.login,
+            name: user.name,
+            avatar_url: user.avatar_url,
+            verified_email,
+        })
+    }
+
+    async fn default_branch_head(&self, repository: &str, access_token: &str) -> Result<(String, String), AppError> {
+        git::default_branch_head(&self.client, &self.api_url, repository, access_token).await
+    }
+
+    async fn create_branch(
+        &self,
+        repository: &str,
+        access_token: &str,
+        name: &str,
+        sha: &str,
+    ) -> Result<CreateRef, AppError> {
+        git::create_branch(&self.client, &self.api_url, repository, access_token, name, sha).await
+    }
+
+    async fn list_issues(
+        &self,
+        repository: &str,
+        page: u32,
+        per_page: u32,
+        access_token: Option<&str>,
+    ) -> Result<Vec<ForgeIssue>, AppError> {
+        let url = format!(
+            "{}/repos/{}/issues?state=all&sort=updated&direction=desc&per_page={}&page={}",
+            self.api_url, repository, per_page, page
+        );
+        match fetch_issue_page(&self.client, &url, access_token, None).await? {
+            IssuePage::Fetched { issues, .. } => Ok(issues),
+            IssuePage::NotModified => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: Option<&str>,
+    ) -> Result<ForgeIssue, AppError> {
+        issues::fetch_issue(&self.client, &self.api_url, repository, number, access_token).await
+    }
+
+    async fn update_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: &str,
+        update: &IssueUpdate,
+    ) -> Result<ForgeIssue, AppError> {
+        issues::update_issue(&self.client, &self.api_url, repository, number, access_token, update).await
+    }
+
+    async fn is_assignable(&self, repository: &str, login: &str, access_token: Option<&str>) -> Result<bool, AppError> {
+        issues::is_assignable(&self.client, &self.api_url, repository, login, access_token).await
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, body: &[u8]) -> Result<WebhookDelivery, AppError> {
+        let secret = self
+            .webhook_secret
+            .as_deref()
+            .ok_or_else(|| AppError::Forbidden("webhooks are not configured".into()))?;
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        if !verify_signature(secret, body, header(SIGNATURE_HEADER)) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let required = |name: &str| {
+            header(name)
+                .map(str::to_string)
+                .ok_or_else(|| AppError::BadRequest(format!("missing {} header", name)))
+        };
+        Ok(WebhookDelivery {
+            event: required(EVENT_HEADER)?,
+            id: required(DELIVERY_HEADER)?,
+        })
+    }
+
+    async fn handle_webhook(&self, state: &SharedState, event: &str, payload: Value) -> Result<Dispatch, AppError> {
+        dispatch(state, self, event, payload).await
+    }
+}
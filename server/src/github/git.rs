@@ -0,0 +1,122 @@
+use crate::{error::AppError, forge::CreateRef, github::client::GitHubClient};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Repository {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct RepositoryAccess {
+    #[serde(default)]
+    permissions: Option<Permissions>,
+}
+
+#[derive(Deserialize)]
+struct Permissions {
+    push: bool,
+}
+
+#[derive(Deserialize)]
+struct GitRef {
+    object: GitObject,
+}
+
+#[derive(Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+#[derive(Serialize)]
+struct NewRef<'a> {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    sha: &'a str,
+}
+
+/// Name and head commit of the repository's default branch.
+pub async fn default_branch_head(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    access_token: &str,
+) -> Result<(String, String), AppError> {
+    let repo = get_json::<Repository>(client, &format!("{}/repos/{}", api_base, repository), access_token).await?;
+    let head = get_json::<GitRef>(
+        client,
+        &format!("{}/repos/{}/git/ref/heads/{}", api_base, repository, repo.default_branch),
+        access_token,
+    )
+    .await?;
+
+    Ok((repo.default_branch, head.object.sha))
+}
+
+/// Whether the token's user may push to the repository. A repository the
+/// token cannot see counts as not writable.
+pub async fn can_push(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    access_token: &str,
+) -> Result<bool, AppError> {
+    let response = client
+        .get(format!("{}/repos/{}", api_base, repository))
+        .bearer_auth(access_token)
+        .send()
+        .await?;
+
+    match response.status() {
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => Ok(false),
+        status if status.is_success() => {
+            let repo = response.json::<RepositoryAccess>().await?;
+            Ok(repo.permissions.is_some_and(|permissions| permissions.push))
+        }
+        status => Err(AppError::BadRequest(format!("github api error: {}", status))),
+    }
+}
+
+pub async fn create_branch(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    access_token: &str,
+    name: &str,
+    sha: &str,
+) -> Result<CreateRef, AppError> {
+    let response = client
+        .post(format!("{}/repos/{}/git/refs", api_base, repository))
+        .bearer_auth(access_token)
+        .json(&NewRef {
+            git_ref: format!("refs/heads/{}", name),
+            sha,
+        })
+        .send()
+        .await?;
+
+    match response.status() {
+        status if status.is_success() => Ok(CreateRef::Created),
+        // GitHub answers "Reference already exists" with 422.
+        StatusCode::UNPROCESSABLE_ENTITY => Ok(CreateRef::AlreadyExists),
+        status => Err(AppError::BadRequest(format!("github api error: {}", status))),
+    }
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(
+    client: &GitHubClient,
+    url: &str,
+    access_token: &str,
+) -> Result<T, AppError> {
+    let response = client.get(url).bearer_auth(access_token).send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "github api error: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response.json::<T>().await?)
+}
@@ -0,0 +1,317 @@
+use crate::{
+    error::AppError,
+    forge::{ForgeAccount, ForgeIssue, ForgeLabel, ForgeMilestone, PullRequest, PullRequestHead},
+    github::client::GitHubClient,
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+/// Issues per page; 100 is the most a GraphQL connection returns.
+const ISSUES_PER_PAGE: u32 = 100;
+
+/// Issues with their labels, assignees and the pull requests that close them
+/// in one request, oldest update first so a stored cursor stays meaningful
+/// when the import resumes.
+const ISSUES_QUERY: &str = r#"
+query($owner: String!, $name: String!, $first: Int!, $after: String) {
+  rateLimit { cost remaining resetAt }
+  repository(owner: $owner, name: $name) {
+    issues(first: $first, after: $after, orderBy: { field: UPDATED_AT, direction: ASC }) {
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        id
+        number
+        title
+        body
+        state
+        stateReason
+        url
+        updatedAt
+        labels(first: 50) { nodes { id name color } }
+        assignees(first: 20) { nodes { databaseId login avatarUrl } }
+        milestone { id number title state dueOn }
+        closedByPullRequestsReferences(first: 10, includeClosedPrs: true) {
+          nodes {
+            number
+            title
+            body
+            url
+            state
+            isDraft
+            merged
+            mergedAt
+            headRefName
+            repository { nameWithOwner }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+pub struct IssueBatch {
+    pub issues: Vec<GraphQlIssue>,
+    pub end_cursor: Option<String>,
+    pub has_next_page: bool,
+    pub cost: QueryCost,
+}
+
+/// An issue with the pull requests that will close it when merged.
+pub struct GraphQlIssue {
+    pub issue: ForgeIssue,
+    /// Repository and pull request pairs; PRs may live in another repository.
+    pub pull_requests: Vec<(String, PullRequest)>,
+}
+
+/// GraphQL point cost of a query and the points left in the hourly budget.
+#[derive(Clone, Copy, Debug)]
+pub struct QueryCost {
+    pub cost: i32,
+    pub remaining: i32,
+    pub reset_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssuesData {
+    rate_limit: RateLimit,
+    repository: Option<RepositoryNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateLimit {
+    cost: i32,
+    remaining: i32,
+    reset_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryNode {
+    issues: Connection<IssueNode>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    #[serde(default)]
+    page_info: Option<PageInfo>,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssueNode {
+    id: String,
+    number: i32,
+    title: String,
+    body: Option<String>,
+    state: String,
+    state_reason: Option<String>,
+    url: String,
+    updated_at: DateTime<Utc>,
+    labels: Connection<LabelNode>,
+    assignees: Connection<AccountNode>,
+    milestone: Option<MilestoneNode>,
+    closed_by_pull_requests_references: Connection<PullRequestNode>,
+}
+
+#[derive(Deserialize)]
+struct LabelNode {
+    id: String,
+    name: String,
+    color: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountNode {
+    database_id: i64,
+    login: String,
+    avatar_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MilestoneNode {
+    id: String,
+    number: i32,
+    title: String,
+    state: String,
+    due_on: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestNode {
+    number: i32,
+    title: String,
+    body: Option<String>,
+    url: String,
+    state: String,
+    is_draft: bool,
+    merged: bool,
+    merged_at: Option<DateTime<Utc>>,
+    head_ref_name: String,
+    repository: PullRequestRepository,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestRepository {
+    name_with_owner: String,
+}
+
+/// One page of the repository's issues, starting after `after`. Unlike the
+/// REST listing this does not include pull requests.
+pub async fn fetch_issue_batch(
+    client: &GitHubClient,
+    graphql_url: &str,
+    access_token: &str,
+    owner: &str,
+    name: &str,
+    after: Option<&str>,
+) -> Result<IssueBatch, AppError> {
+    let variables = json!({
+        "owner": owner,
+        "name": name,
+        "first": ISSUES_PER_PAGE,
+        "after": after,
+    });
+    let data: IssuesData = query(client, graphql_url, access_token, ISSUES_QUERY, variables).await?;
+    let repository = data.repository.ok_or(AppError::NotFound)?;
+    let page_info = repository.issues.page_info;
+
+    Ok(IssueBatch {
+        issues: repository.issues.nodes.into_iter().map(GraphQlIssue::from).collect(),
+        end_cursor: page_info.as_ref().and_then(|info| info.end_cursor.clone()),
+        has_next_page: page_info.is_some_and(|info| info.has_next_page),
+        cost: QueryCost {
+            cost: data.rate_limit.cost,
+            remaining: data.rate_limit.remaining,
+            reset_at: data.rate_limit.reset_at,
+        },
+    })
+}
+
+async fn query<T: DeserializeOwned>(
+    client: &GitHubClient,
+    graphql_url: &str,
+    access_token: &str,
+    query: &str,
+    variables: Value,
+) -> Result<T, AppError> {
+    let response = client
+        .post(graphql_url)
+        .bearer_auth(access_token)
+        .retry_on_failure()
+        .json(&json!({ "query": query, "variables": variables }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "github api error: {}",
+            response.status()
+        )));
+    }
+
+    // GraphQL reports most failures with a 200 and an `errors` array.
+    let body = response.json::<GraphQlResponse<T>>().await?;
+    match body.data {
+        Some(data) if body.errors.is_empty() => Ok(data),
+        _ => {
+            let messages: Vec<String> = body.errors.into_iter().map(|error| error.message).collect();
+            Err(AppError::BadRequest(format!("github graphql error: {}", messages.join("; "))))
+        }
+    }
+}
+
+impl From<IssueNode> for GraphQlIssue {
+    fn from(node: IssueNode) -> Self {
+        let pull_requests = node
+            .closed_by_pull_requests_references
+            .nodes
+            .into_iter()
+            .map(|pr| {
+                let pull_request = PullRequest {
+                    number: pr.number,
+                    title: pr.title,
+                    body: pr.body,
+                    html_url: pr.url,
+                    // GraphQL has a separate MERGED state; REST calls it closed.
+                    state: if pr.state == "OPEN" { "open" } else { "closed" }.to_string(),
+                    draft: pr.is_draft,
+                    merged: pr.merged,
+                    merged_at: pr.merged_at,
+                    head: PullRequestHead {
+                        git_ref: pr.head_ref_name,
+                    },
+                };
+                (pr.repository.name_with_owner, pull_request)
+            })
+            .collect();
+
+        // Match the REST representation the sync engine works with.
+        let issue = ForgeIssue {
+            node_id: node.id,
+            number: node.number,
+            title: node.title,
+            body: node.body,
+            state: node.state.to_ascii_lowercase(),
+            state_reason: node.state_reason.map(|reason| reason.to_ascii_lowercase()),
+            html_url: node.url,
+            updated_at: node.updated_at,
+            labels: node
+                .labels
+                .nodes
+                .into_iter()
+                .map(|label| ForgeLabel {
+                    node_id: label.id,
+                    name: label.name,
+                    color: label.color,
+                })
+                .collect(),
+            assignees: node
+                .assignees
+                .nodes
+                .into_iter()
+                .map(|account| ForgeAccount {
+                    id: account.database_id,
+                    login: account.login,
+                    avatar_url: account.avatar_url,
+                })
+                .collect(),
+            milestone: node.milestone.map(|milestone| ForgeMilestone {
+                node_id: milestone.id,
+                number: milestone.number,
+                title: milestone.title,
+                state: milestone.state.to_ascii_lowercase(),
+                due_on: milestone.due_on,
+            }),
+            pull_request: None,
+        };
+
+        Self { issue, pull_requests }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    error::AppError,
+    forge::{ForgeIssue, IssueUpdate},
+    github::{client::GitHubClient, next_page_url},
+};
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH},
+    StatusCode,
+};
+
+pub enum IssuePage {
+    NotModified,
+    Fetched {
+        issues: Vec<ForgeIssue>,
+        etag: Option<String>,
+        next_url: Option<String>,
+    },
+}
+
+pub async fn fetch_issue_page(
+    client: &GitHubClient,
+    url: &str,
+    access_token: Option<&str>,
+    etag: Option<&str>,
+) -> Result<IssuePage, AppError> {
+    let mut request = client.get(url).access_token(access_token);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(IssuePage::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "github api error: {}",
+            response.status()
+        )));
+    }
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let next_url = next_page_url(response.headers());
+    let issues = response.json::<Vec<ForgeIssue>>().await?;
+
+    Ok(IssuePage::Fetched {
+        issues,
+        etag,
+        next_url,
+    })
+}
+
+pub async fn fetch_issue(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    number: i32,
+    access_token: Option<&str>,
+) -> Result<ForgeIssue, AppError> {
+    let response = client
+        .get(format!("{}/repos/{}/issues/{}", api_base, repository, number))
+        .access_token(access_token)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "github api error: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response.json::<ForgeIssue>().await?)
+}
+
+/// Whether `login` can be assigned issues in the repository. GitHub silently
+/// drops assignees without access instead of failing the update.
+pub async fn is_assignable(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    login: &str,
+    access_token: Option<&str>,
+) -> Result<bool, AppError> {
+    let response = client
+        .get(format!("{}/repos/{}/assignees/{}", api_base, repository, login))
+        .access_token(access_token)
+        .send()
+        .await?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(false),
+        status if status.is_success() => Ok(true),
+        status => Err(AppError::BadRequest(format!("github api error: {}", status))),
+    }
+}
+
+pub async fn update_issue(
+    client: &GitHubClient,
+    api_base: &str,
+    repository: &str,
+    number: i32,
+    access_token: &str,
+    update: &IssueUpdate,
+) -> Result<ForgeIssue, AppError> {
+    let response = client
+        .patch(format!("{}/repos/{}/issues/{}", api_base, repository, number))
+        .bearer_auth(access_token)
+        .json(update)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!(
+            "github api error: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response.json::<ForgeIssue>().await?)
+}
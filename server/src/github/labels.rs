@@ -0,0 +1,166 @@
+use crate::{error::AppError, forge::ForgeLabel, routes::normalize_color};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Names of the Keyflow labels an issue's labels map to, linking any label
+/// seen for the first time.
+pub async fn local_label_names(
+    conn: &mut PgConnection,
+    repository: &str,
+    labels: &[ForgeLabel],
+) -> Result<Vec<String>, AppError> {
+    let mut label_ids = Vec::with_capacity(labels.len());
+    for label in labels {
+        label_ids.push(link_label(conn, repository, label).await?);
+    }
+
+    let names = sqlx::query_scalar::<_, String>("SELECT name FROM labels WHERE id = ANY($1)")
+        .bind(&label_ids)
+        .fetch_all(conn)
+        .await?;
+
+    Ok(names)
+}
+
+/// Names to send to GitHub for Keyflow labels; labels without a mapping for
+/// the repository keep their own name.
+pub async fn github_label_names(
+    conn: &mut PgConnection,
+    repository: &str,
+    names: &[String],
+) -> Result<Vec<String>, AppError> {
+    let names = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT COALESCE(m.github_name, l.name)
+        FROM labels l
+        LEFT JOIN github_label_mappings m ON m.label_id = l.id AND m.repository = $1
+        WHERE LOWER(l.name) = ANY(SELECT LOWER(name) FROM UNNEST($2::text[]) AS name)
+        "#,
+    )
+    .bind(repository)
+    .bind(names)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(names)
+}
+
+/// Finds the Keyflow label a GitHub label maps to. Labels are tracked by
+/// node ID once seen; before that a mapping configured by name is claimed,
+/// and failing that the label is mirrored as a Keyflow label with the same
+/// name and color. A mirrored label follows renames and color changes made
+/// on GitHub, while a label mapped to a differently named one keeps its name.
+pub async fn link_label(conn: &mut PgConnection, repository: &str, label: &ForgeLabel) -> Result<Uuid, AppError> {
+    let color = normalize_color(&label.color).ok();
+    let linked = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT label_id, github_name FROM github_label_mappings WHERE github_node_id = $1 FOR UPDATE",
+    )
+    .bind(&label.node_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some((label_id, previous_name)) = linked {
+        sqlx::query(
+            r#"
+            UPDATE labels l
+            SET name = CASE
+                    WHEN EXISTS (SELECT 1 FROM labels o WHERE LOWER(o.name) = LOWER($2) AND o.id <> l.id)
+                    THEN l.name
+                    ELSE $2
+                END,
+                color = COALESCE($3, l.color)
+            WHERE l.id = $1
+              AND LOWER(l.name) = LOWER($4)
+              AND (l.name <> $2 OR l.color <> COALESCE($3, l.color))
+            "#,
+        )
+        .bind(label_id)
+        .bind(&label.name)
+        .bind(&color)
+        .bind(&previous_name)
+        .execute(&mut *conn)
+        .await?;
+
+        if previous_name != label.name {
+            // The new name wins over a mapping configured for it by name only.
+            sqlx::query(
+                r#"
+                DELETE FROM github_label_mappings
+                WHERE repository = $1 AND LOWER(github_name) = LOWER($2) AND github_node_id IS NULL
+                "#,
+            )
+            .bind(repository)
+            .bind(&label.name)
+            .execute(&mut *conn)
+            .await?;
+            sqlx::query("UPDATE github_label_mappings SET github_name = $2 WHERE github_node_id = $1")
+                .bind(&label.node_id)
+                .bind(&label.name)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        return Ok(label_id);
+    }
+
+    let claimed = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE github_label_mappings
+        SET github_node_id = $3, github_name = $2
+        WHERE repository = $1 AND LOWER(github_name) = LOWER($2)
+        RETURNING label_id
+        "#,
+    )
+    .bind(repository)
+    .bind(&label.name)
+    .bind(&label.node_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(label_id) = claimed {
+        return Ok(label_id);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO labels (name, color)
+        VALUES ($1, COALESCE($2, '6b7280'))
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&label.name)
+    .bind(&color)
+    .execute(&mut *conn)
+    .await?;
+    let label_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM labels WHERE LOWER(name) = LOWER($1)")
+        .bind(&label.name)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // A Keyflow label already mapped from another GitHub label of the
+    // repository stays unlinked here and is resolved by name.
+    sqlx::query(
+        r#"
+        INSERT INTO github_label_mappings (repository, github_node_id, github_name, label_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(repository)
+    .bind(&label.node_id)
+    .bind(&label.name)
+    .bind(label_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(label_id)
+}
+
+/// Drops the mapping of a label deleted on GitHub; the Keyflow label stays.
+pub async fn forget_label(conn: &mut PgConnection, node_id: &str) -> Result<bool, AppError> {
+    let deleted = sqlx::query("DELETE FROM github_label_mappings WHERE github_node_id = $1")
+        .bind(node_id)
+        .execute(conn)
+        .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
@@ -0,0 +1,155 @@
+use crate::{error::AppError, forge::ForgeMilestone, routes::move_task_to_cycle};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Puts a task in the cycle its issue's milestone stands for. This only
+/// happens when the issue's milestone changes, so moving the task to another
+/// cycle in Keyflow sticks until the milestone is changed on GitHub.
+pub async fn apply_milestone(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    project_id: Uuid,
+    repository: &str,
+    milestone: Option<&ForgeMilestone>,
+) -> Result<(), AppError> {
+    let target_cycle = match milestone {
+        Some(milestone) => link_milestone(conn, project_id, repository, milestone).await?,
+        None => None,
+    };
+
+    let (previous_milestone, current_cycle) = sqlx::query_as::<_, (Option<String>, Option<Uuid>)>(
+        "SELECT github_milestone_id, cycle_id FROM tasks WHERE id = $1",
+    )
+    .bind(task_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    let node_id = milestone.map(|milestone| milestone.node_id.as_str());
+    if previous_milestone.as_deref() == node_id {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE tasks SET github_milestone_id = $2 WHERE id = $1")
+        .bind(task_id)
+        .bind(node_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let next_cycle = match target_cycle {
+        Some(cycle_id) => {
+            let open = sqlx::query_scalar::<_, bool>("SELECT completed_at IS NULL FROM cycles WHERE id = $1")
+                .bind(cycle_id)
+                .fetch_one(&mut *conn)
+                .await?;
+            if !open {
+                return Ok(());
+            }
+            Some(cycle_id)
+        }
+        None => {
+            // Leave the cycle only if the task was there because of the
+            // milestone it just lost.
+            let previous_cycle = match &previous_milestone {
+                Some(previous) => {
+                    sqlx::query_scalar::<_, Option<Uuid>>(
+                        "SELECT cycle_id FROM github_milestones WHERE project_id = $1 AND github_node_id = $2",
+                    )
+                    .bind(project_id)
+                    .bind(previous)
+                    .fetch_optional(&mut *conn)
+                    .await?
+                    .flatten()
+                }
+                None => None,
+            };
+            if previous_cycle.is_none() || previous_cycle != current_cycle {
+                return Ok(());
+            }
+            None
+        }
+    };
+
+    move_task_to_cycle(conn, task_id, current_cycle, next_cycle).await
+}
+
+/// Records a milestone for the project and returns the cycle it maps to.
+/// A milestone seen for the first time is mapped to the project's cycle that
+/// contains its due date, if any, and names that cycle after it.
+pub async fn link_milestone(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    repository: &str,
+    milestone: &ForgeMilestone,
+) -> Result<Option<Uuid>, AppError> {
+    refresh_milestone(conn, milestone).await?;
+
+    sqlx::query(
+        r#"
+        WITH inserted AS (
+            INSERT INTO github_milestones (project_id, repository, github_node_id, number, title, state, due_on, cycle_id)
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7,
+                (
+                    SELECT id FROM cycles
+                    WHERE project_id = $1 AND starts_on <= $7 AND ends_on > $7
+                    ORDER BY number
+                    LIMIT 1
+                )
+            )
+            ON CONFLICT (project_id, github_node_id) DO NOTHING
+            RETURNING cycle_id
+        )
+        UPDATE cycles SET name = $5
+        WHERE id = (SELECT cycle_id FROM inserted) AND name IS NULL
+        "#,
+    )
+    .bind(project_id)
+    .bind(repository)
+    .bind(&milestone.node_id)
+    .bind(milestone.number)
+    .bind(&milestone.title)
+    .bind(&milestone.state)
+    .bind(milestone.due_on.map(|due_on| due_on.date_naive()))
+    .execute(&mut *conn)
+    .await?;
+
+    let cycle_id = sqlx::query_scalar::<_, Option<Uuid>>(
+        "SELECT cycle_id FROM github_milestones WHERE project_id = $1 AND github_node_id = $2",
+    )
+    .bind(project_id)
+    .bind(&milestone.node_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(cycle_id)
+}
+
+/// Updates every project's copy of a milestone. Cycles still named after the
+/// milestone's old title take the new one.
+pub async fn refresh_milestone(conn: &mut PgConnection, milestone: &ForgeMilestone) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        WITH previous AS (
+            SELECT id, title FROM github_milestones WHERE github_node_id = $1
+        ), updated AS (
+            UPDATE github_milestones m
+            SET number = $2, title = $3, state = $4, due_on = $5
+            FROM previous p
+            WHERE m.id = p.id AND (m.number, m.title, m.state, m.due_on) IS DISTINCT FROM ($2, $3, $4, $5)
+            RETURNING m.cycle_id, p.title AS previous_title
+        )
+        UPDATE cycles c
+        SET name = $3
+        FROM updated u
+        WHERE c.id = u.cycle_id AND (c.name IS NULL OR c.name = u.previous_title) AND c.name IS DISTINCT FROM $3
+        "#,
+    )
+    .bind(&milestone.node_id)
+    .bind(milestone.number)
+    .bind(&milestone.title)
+    .bind(&milestone.state)
+    .bind(milestone.due_on.map(|due_on| due_on.date_naive()))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use reqwest::header::{HeaderMap, LINK};
+
+pub mod app;
+pub mod assignees;
+pub mod branches;
+pub mod client;
+pub mod commits;
+pub mod forge;
+pub mod git;
+pub mod graphql;
+pub mod issues;
+pub mod labels;
+pub mod milestones;
+pub mod pull_requests;
+pub mod scopes;
+pub mod sync;
+pub mod task_refs;
+pub mod tokens;
+pub mod webhooks;
+
+/// Extracts the `rel="next"` target from a GitHub `Link` header.
+pub fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#);
+        is_next.then(|| {
+            target
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+/// Validates an `owner/name` repository reference before it ends up in an
+/// API URL.
+pub fn parse_repository(full_name: &str) -> Option<(&str, &str)> {
+    let (owner, name) = full_name.trim().split_once('/')?;
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part.len() <= 100
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (valid(owner) && valid(name)).then_some((owner, name))
+}
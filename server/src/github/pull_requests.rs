@@ -0,0 +1,169 @@
+use crate::{
+    error::AppError,
+    forge::PullRequest,
+    github::task_refs::{find_task_keys, resolve_task_keys},
+    jobs::{enqueue, Job},
+    routes::{fan_out, record_event, TASK_STATUSES},
+    state::SharedState,
+};
+use serde_json::json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+impl PullRequest {
+    fn link_state(&self) -> &'static str {
+        match (self.state.as_str(), self.merged || self.merged_at.is_some(), self.draft) {
+            ("closed", true, _) => "merged",
+            ("closed", false, _) => "closed",
+            (_, _, true) => "draft",
+            _ => "open",
+        }
+    }
+}
+
+/// Links the pull request to every task it references (by key in its title,
+/// body or branch name, or through a branch created from the task), refreshes
+/// existing links, and applies the projects' open/merge transitions. Returns
+/// the number of linked tasks.
+pub async fn record_pull_request(
+    state: &SharedState,
+    repository: &str,
+    pull_request: &PullRequest,
+) -> Result<usize, AppError> {
+    let link_state = pull_request.link_state();
+    let mut tx = state.pool.begin().await?;
+
+    let text = format!(
+        "{}\n{}\n{}",
+        pull_request.title,
+        pull_request.body.as_deref().unwrap_or_default(),
+        pull_request.head.git_ref
+    );
+    let mut task_ids = resolve_task_keys(&mut tx, &find_task_keys(&text)).await?;
+    let branch_tasks = sqlx::query_scalar::<_, Uuid>(
+        "SELECT task_id FROM task_branches WHERE LOWER(repository) = LOWER($1) AND name = $2",
+    )
+    .bind(repository)
+    .bind(&pull_request.head.git_ref)
+    .fetch_all(&mut *tx)
+    .await?;
+    let already_linked = sqlx::query_scalar::<_, Uuid>(
+        "SELECT task_id FROM task_pull_requests WHERE repository = $1 AND number = $2",
+    )
+    .bind(repository)
+    .bind(pull_request.number)
+    .fetch_all(&mut *tx)
+    .await?;
+    for task_id in branch_tasks.into_iter().chain(already_linked) {
+        if !task_ids.contains(&task_id) {
+            task_ids.push(task_id);
+        }
+    }
+
+    let mut transitioned = Vec::new();
+    for &task_id in &task_ids {
+        link_pull_request(&mut tx, task_id, repository, pull_request).await?;
+
+        if apply_transition(&mut tx, task_id, repository, pull_request.number, link_state).await? {
+            transitioned.push(task_id);
+        }
+    }
+    for task_id in transitioned {
+        enqueue(&mut *tx, &Job::IssueSync { task_id, actor_id: None }).await?;
+    }
+    tx.commit().await?;
+
+    Ok(task_ids.len())
+}
+
+/// Creates or refreshes the link between a task and a pull request.
+pub async fn link_pull_request(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    repository: &str,
+    pull_request: &PullRequest,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO task_pull_requests (task_id, repository, number, title, url, head_ref, state, merged_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (task_id, repository, number) DO UPDATE
+        SET title = EXCLUDED.title,
+            url = EXCLUDED.url,
+            head_ref = EXCLUDED.head_ref,
+            state = EXCLUDED.state,
+            merged_at = EXCLUDED.merged_at
+        "#,
+    )
+    .bind(task_id)
+    .bind(repository)
+    .bind(pull_request.number)
+    .bind(&pull_request.title)
+    .bind(&pull_request.html_url)
+    .bind(&pull_request.head.git_ref)
+    .bind(pull_request.link_state())
+    .bind(pull_request.merged_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves the task to the project's configured status for an opened or merged
+/// pull request. Tasks only ever move forward through the workflow, so a PR
+/// opening never pulls a finished task back into review.
+async fn apply_transition(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    repository: &str,
+    number: i32,
+    link_state: &str,
+) -> Result<bool, AppError> {
+    let (current, opened_status, merged_status) = sqlx::query_as::<_, (String, Option<String>, Option<String>)>(
+        r#"
+        SELECT t.status, p.pr_opened_status, p.pr_merged_status
+        FROM tasks t
+        JOIN projects p ON p.id = t.project_id
+        WHERE t.id = $1
+        FOR UPDATE OF t
+        "#,
+    )
+    .bind(task_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let target = match link_state {
+        "open" => opened_status,
+        "merged" => merged_status,
+        _ => None,
+    };
+    let rank = |status: &str| TASK_STATUSES.iter().position(|candidate| *candidate == status);
+    let Some(target) = target.filter(|target| current != "canceled" && rank(target) > rank(&current)) else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE tasks
+        SET status = $2,
+            completed_at = CASE
+                WHEN $2 IN ('done', 'canceled') THEN COALESCE(completed_at, now())
+                ELSE NULL
+            END
+        WHERE id = $1
+        "#,
+    )
+    .bind(task_id)
+    .bind(&target)
+    .execute(&mut *conn)
+    .await?;
+
+    let data = json!({
+        "status": { "from": current, "to": target },
+        "pull_request": { "repository": repository, "number": number, "state": link_state },
+    });
+    record_event(&mut *conn, task_id, None, "pull_request_transition", data.clone()).await?;
+    fan_out(&mut *conn, task_id, None, "task_updated", data).await?;
+
+    Ok(true)
+}
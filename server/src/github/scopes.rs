@@ -0,0 +1,69 @@
+use crate::{error::AppError, state::AppState};
+use uuid::Uuid;
+
+/// Requested at every sign-in; `user:email` lets us read the verified primary
+/// address of users who keep their email private.
+pub const BASE_SCOPES: &[&str] = &["read:user", "user:email"];
+
+/// Scopes a user can be asked to add when they enable a feature needing them.
+pub const UPGRADABLE_SCOPES: &[&str] = &["repo", "public_repo", "read:org", "user:email", "workflow"];
+
+/// GitHub reports granted scopes comma-separated in one string, which OAuth
+/// clients expecting space-separated values hand back as a single scope.
+pub fn normalize_scopes<'a>(raw: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut scopes: Vec<String> = raw
+        .into_iter()
+        .flat_map(|value| value.split([',', ' ']))
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .map(str::to_string)
+        .collect();
+    scopes.sort();
+    scopes.dedup();
+    scopes
+}
+
+/// Scopes from `required` that `granted` does not cover, taking GitHub's
+/// scope hierarchy into account (e.g. `repo` includes `public_repo`).
+pub fn missing_scopes(granted: &[String], required: &[&str]) -> Vec<String> {
+    let implied_by = |scope: &str| -> &'static [&'static str] {
+        match scope {
+            "public_repo" | "repo:status" | "repo_deployment" | "repo:invite" => &["repo"],
+            "read:org" => &["write:org", "admin:org"],
+            "write:org" => &["admin:org"],
+            "read:user" | "user:email" | "user:follow" => &["user"],
+            _ => &[],
+        }
+    };
+
+    required
+        .iter()
+        .filter(|needed| {
+            !granted
+                .iter()
+                .any(|scope| scope == *needed || implied_by(needed).contains(&scope.as_str()))
+        })
+        .map(|scope| scope.to_string())
+        .collect()
+}
+
+pub async fn granted_scopes(state: &AppState, user_id: Uuid) -> Result<Vec<String>, AppError> {
+    let scopes = sqlx::query_scalar::<_, Vec<String>>("SELECT scopes FROM user_github_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .unwrap_or_default();
+
+    Ok(scopes)
+}
+
+/// Fails with [`AppError::ScopeRequired`] unless the user's stored token was
+/// granted every scope in `required`.
+pub async fn require_scopes(state: &AppState, user_id: Uuid, required: &[&str]) -> Result<(), AppError> {
+    let missing = missing_scopes(&granted_scopes(state, user_id).await?, required);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ScopeRequired(missing))
+    }
+}
@@ -0,0 +1,875 @@
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeIssue, ForgeKind, ForgeProvider, IssueUpdate},
+    github::{
+        assignees::ensure_assignee_users,
+        labels::{github_label_names, local_label_names},
+        milestones::apply_milestone,
+        tokens::access_token_for,
+    },
+    routes::{fan_out, insert_task, record_event},
+    state::SharedState,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{types::Json, PgConnection, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+/// The fields kept in step between a task and its GitHub issue, normalised
+/// so that snapshots taken from either side compare equal when in sync.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueSnapshot {
+    pub title: String,
+    pub body: Option<String>,
+    pub closed: bool,
+    pub labels: Vec<String>,
+    pub assignees: Vec<String>,
+}
+
+impl IssueSnapshot {
+    pub fn new(
+        title: &str,
+        body: Option<&str>,
+        closed: bool,
+        labels: impl IntoIterator<Item = String>,
+        assignees: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let mut labels: Vec<String> = labels.into_iter().collect();
+        labels.sort_by_key(|label| label.to_lowercase());
+        labels.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        let mut assignees: Vec<String> = assignees.into_iter().map(|login| login.to_lowercase()).collect();
+        assignees.sort();
+        assignees.dedup();
+
+        Self {
+            title: title.trim().to_string(),
+            body: body.map(str::trim).filter(|body| !body.is_empty()).map(str::to_string),
+            closed,
+            labels,
+            assignees,
+        }
+    }
+
+    /// `labels` are the Keyflow names the issue's labels map to, so both
+    /// sides compare in Keyflow's terms.
+    pub fn from_issue(issue: &ForgeIssue, labels: Vec<String>) -> Self {
+        Self::new(
+            &issue.title,
+            issue.body.as_deref(),
+            issue.state == "closed",
+            labels,
+            issue.assignees.iter().map(|account| account.login.clone()),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncField {
+    Title,
+    Body,
+    State,
+    Labels,
+    Assignees,
+}
+
+impl SyncField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncField::Title => "title",
+            SyncField::Body => "body",
+            SyncField::State => "state",
+            SyncField::Labels => "labels",
+            SyncField::Assignees => "assignees",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Local => "local",
+            Side::Remote => "remote",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FieldConflict {
+    pub field: SyncField,
+    pub base: serde_json::Value,
+    pub local: serde_json::Value,
+    pub remote: serde_json::Value,
+    pub winner: Side,
+}
+
+/// Outcome of a three-way comparison between the last synced snapshot and
+/// the current local and remote state.
+#[derive(Debug, PartialEq)]
+pub struct SyncPlan {
+    pub merged: IssueSnapshot,
+    /// Fields whose merged value must be written to the task.
+    pub apply_local: Vec<SyncField>,
+    /// Fields whose merged value must be pushed to GitHub.
+    pub push_remote: Vec<SyncField>,
+    pub conflicts: Vec<FieldConflict>,
+}
+
+/// Merges field by field. A field changed on one side only takes that side's
+/// value; a field changed differently on both sides goes to whichever side
+/// was updated last and is reported as a conflict. Without a base snapshot
+/// (a task that was never synced) the local side is taken as the base, so
+/// the issue's current state is pulled in unchanged.
+pub fn reconcile(
+    base: Option<&IssueSnapshot>,
+    local: &IssueSnapshot,
+    remote: &IssueSnapshot,
+    local_updated_at: DateTime<Utc>,
+    remote_updated_at: DateTime<Utc>,
+) -> SyncPlan {
+    let base = base.unwrap_or(local);
+    let local_wins = local_updated_at > remote_updated_at;
+    let mut plan = SyncPlan {
+        merged: IssueSnapshot::default(),
+        apply_local: Vec::new(),
+        push_remote: Vec::new(),
+        conflicts: Vec::new(),
+    };
+
+    plan.merged.title = merge_field(SyncField::Title, &base.title, &local.title, &remote.title, local_wins, &mut plan);
+    plan.merged.body = merge_field(SyncField::Body, &base.body, &local.body, &remote.body, local_wins, &mut plan);
+    plan.merged.closed =
+        merge_field(SyncField::State, &base.closed, &local.closed, &remote.closed, local_wins, &mut plan);
+    plan.merged.labels =
+        merge_field(SyncField::Labels, &base.labels, &local.labels, &remote.labels, local_wins, &mut plan);
+    plan.merged.assignees = merge_field(
+        SyncField::Assignees,
+        &base.assignees,
+        &local.assignees,
+        &remote.assignees,
+        local_wins,
+        &mut plan,
+    );
+
+    plan
+}
+
+fn merge_field<T>(field: SyncField, base: &T, local: &T, remote: &T, local_wins: bool, plan: &mut SyncPlan) -> T
+where
+    T: Clone + PartialEq + Serialize,
+{
+    let local_changed = local != base;
+    let remote_changed = remote != base;
+
+    let merged = match (local_changed, remote_changed) {
+        (false, false) => base.clone(),
+        (true, false) => local.clone(),
+        (false, true) => remote.clone(),
+        (true, true) if local == remote => local.clone(),
+        (true, true) => {
+            let winner = if local_wins { Side::Local } else { Side::Remote };
+            plan.conflicts.push(FieldConflict {
+                field,
+                base: json!(base),
+                local: json!(local),
+                remote: json!(remote),
+                winner,
+            });
+            if local_wins {
+                local.clone()
+            } else {
+                remote.clone()
+            }
+        }
+    };
+
+    if &merged != local {
+        plan.apply_local.push(field);
+    }
+    if &merged != remote {
+        plan.push_remote.push(field);
+    }
+
+    merged
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncOutcome {
+    NotLinked,
+    InSync,
+    Synced { pulled: usize, pushed: usize, conflicts: usize },
+}
+
+/// Everything `sync_task` needs to talk to the issue's forge.
+pub struct SyncContext<'a> {
+    pub pool: &'a PgPool,
+    pub forge: &'a dyn ForgeProvider,
+    pub access_token: Option<&'a str>,
+}
+
+/// How many times a sync starts over when the task changes while the forge
+/// is being talked to.
+const SYNC_ATTEMPTS: usize = 3;
+
+enum Attempt {
+    Finished(SyncOutcome),
+    /// The task changed before the result could be applied; carries the
+    /// issue as last seen, if it is still current.
+    Raced(Option<ForgeIssue>),
+}
+
+/// Brings a linked task and its GitHub issue in line with each other.
+/// `remote` can be passed when the caller already holds a fresh copy of the
+/// issue (e.g. from an import page) to save a request.
+///
+/// The forge is only called outside transactions: the plan is made from a
+/// plain read, pushed, and then applied in a short transaction that starts
+/// over if the task changed in the meantime.
+pub async fn sync_task(
+    ctx: &SyncContext<'_>,
+    task_id: Uuid,
+    mut remote: Option<ForgeIssue>,
+) -> Result<SyncOutcome, AppError> {
+    for _ in 0..SYNC_ATTEMPTS {
+        match sync_once(ctx, task_id, remote.take()).await? {
+            Attempt::Finished(outcome) => return Ok(outcome),
+            Attempt::Raced(latest) => remote = latest,
+        }
+    }
+
+    Err(AppError::Conflict("the task kept changing during sync".into()))
+}
+
+async fn sync_once(ctx: &SyncContext<'_>, task_id: Uuid, remote: Option<ForgeIssue>) -> Result<Attempt, AppError> {
+    let mut conn = ctx.pool.acquire().await?;
+    let Some(local) = load_local(&mut conn, task_id).await? else {
+        return Ok(Attempt::Finished(SyncOutcome::NotLinked));
+    };
+    let base = load_base(&mut conn, task_id).await?;
+    drop(conn);
+
+    let remote = match remote {
+        Some(remote) => remote,
+        None => {
+            ctx.forge
+                .fetch_issue(&local.repository, local.issue_number, ctx.access_token)
+                .await?
+        }
+    };
+    let mut tx = ctx.pool.begin().await?;
+    let remote_labels = local_label_names(&mut tx, &local.repository, &remote.labels).await?;
+    tx.commit().await?;
+    let remote_snapshot = IssueSnapshot::from_issue(&remote, remote_labels);
+
+    let read_snapshot = local.snapshot(base.as_ref());
+    let mut local_snapshot = read_snapshot.clone();
+    // GitHub drops assignees without access to the repository, so assigning
+    // a task to someone who is not a collaborator stays a Keyflow-only change
+    // instead of being pushed and then pulled back as an unassignment.
+    if let (Some(login), Some(base)) = (&local.assignee_login, &base) {
+        if local_snapshot.assignees != base.assignees
+            && !remote_snapshot.assignees.contains(&login.to_lowercase())
+            && !ctx.forge.is_assignable(&local.repository, login, ctx.access_token).await?
+        {
+            local_snapshot.assignees = base.assignees.clone();
+        }
+    }
+    let plan = reconcile(
+        base.as_ref(),
+        &local_snapshot,
+        &remote_snapshot,
+        local.updated_at,
+        remote.updated_at,
+    );
+
+    let mut remote_updated_at = remote.updated_at;
+    let mut pushed = None;
+    if !plan.push_remote.is_empty() {
+        let token = ctx
+            .access_token
+            .ok_or_else(|| AppError::Forbidden("no github token available to push changes".into()))?;
+        let mut update = issue_update(&plan);
+        if let Some(labels) = &mut update.labels {
+            let mut conn = ctx.pool.acquire().await?;
+            *labels = github_label_names(&mut conn, &local.repository, labels).await?;
+        }
+        let updated = ctx
+            .forge
+            .update_issue(&local.repository, local.issue_number, token, &update)
+            .await?;
+        remote_updated_at = updated.updated_at;
+        pushed = Some(updated);
+    }
+
+    let mut tx = ctx.pool.begin().await?;
+    sqlx::query("SELECT 1 FROM tasks WHERE id = $1 FOR UPDATE")
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
+    let current = load_local(&mut tx, task_id).await?;
+    let current_base = load_base(&mut tx, task_id).await?;
+    let unchanged = current_base == base
+        && current.as_ref().is_some_and(|current| {
+            current.repository == local.repository
+                && current.issue_number == local.issue_number
+                && current.snapshot(current_base.as_ref()) == read_snapshot
+        });
+    if !unchanged {
+        tx.rollback().await?;
+        info!(task_id = %task_id, "task changed during sync; starting over");
+        // What was pushed is the issue's latest state; otherwise the copy
+        // read earlier still is.
+        return Ok(Attempt::Raced(Some(pushed.unwrap_or(remote))));
+    }
+    let local = current.ok_or(AppError::NotFound)?;
+
+    apply_milestone(&mut tx, task_id, local.project_id, &local.repository, remote.milestone.as_ref()).await?;
+    if plan.apply_local.is_empty() && plan.push_remote.is_empty() && base.is_some() {
+        tx.commit().await?;
+        return Ok(Attempt::Finished(SyncOutcome::InSync));
+    }
+
+    if !plan.apply_local.is_empty() {
+        ensure_assignee_users(&mut tx, ctx.forge.kind(), &remote.assignees).await?;
+        let assignee_ids: Vec<i64> = plan
+            .merged
+            .assignees
+            .iter()
+            .filter_map(|login| {
+                remote
+                    .assignees
+                    .iter()
+                    .find(|account| account.login.eq_ignore_ascii_case(login))
+                    .map(|account| account.id)
+            })
+            .collect();
+        apply_local(&mut tx, task_id, &local.status, remote.state_reason.as_deref(), ctx.forge.kind(), &assignee_ids, &plan).await?;
+    }
+
+    for conflict in &plan.conflicts {
+        sqlx::query(
+            r#"
+            INSERT INTO github_sync_conflicts (task_id, field, base_value, local_value, remote_value, winner)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(task_id)
+        .bind(conflict.field.as_str())
+        .bind(&conflict.base)
+        .bind(&conflict.local)
+        .bind(&conflict.remote)
+        .bind(conflict.winner.as_str())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    record_sync_state(&mut tx, task_id, &plan.merged, remote_updated_at).await?;
+    sqlx::query("UPDATE tasks SET github_updated_at = $2 WHERE id = $1")
+        .bind(task_id)
+        .bind(remote_updated_at)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!(
+        task_id = %task_id,
+        pulled = plan.apply_local.len(),
+        pushed = plan.push_remote.len(),
+        conflicts = plan.conflicts.len(),
+        "task synced with github issue"
+    );
+
+    Ok(Attempt::Finished(SyncOutcome::Synced {
+        pulled: plan.apply_local.len(),
+        pushed: plan.push_remote.len(),
+        conflicts: plan.conflicts.len(),
+    }))
+}
+
+/// Stores the snapshot both sides agree on after a sync or import.
+pub async fn record_sync_state(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    snapshot: &IssueSnapshot,
+    remote_updated_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO github_issue_sync_state (task_id, snapshot, remote_updated_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (task_id) DO UPDATE
+        SET snapshot = EXCLUDED.snapshot,
+            remote_updated_at = EXCLUDED.remote_updated_at,
+            synced_at = now()
+        "#,
+    )
+    .bind(task_id)
+    .bind(Json(snapshot))
+    .bind(remote_updated_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Syncs the task linked to an issue that changed on its forge. New issues
+/// only become tasks in repositories that were imported into a project;
+/// anything else is not ours to track.
+pub async fn sync_issue_event(
+    state: &SharedState,
+    forge: &dyn ForgeProvider,
+    repository: &str,
+    issue: ForgeIssue,
+) -> Result<Dispatch, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let linked = sqlx::query_scalar::<_, Uuid>("SELECT id FROM tasks WHERE github_node_id = $1")
+        .bind(&issue.node_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let task_id = match linked {
+        Some(task_id) => task_id,
+        None => {
+            let target = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+                r#"
+                SELECT project_id, requested_by FROM github_imports
+                WHERE forge = $1 AND repository = $2 AND status = 'completed'
+                ORDER BY created_at DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(forge.kind().as_str())
+            .bind(repository)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let Some((project_id, requested_by)) = target else {
+                return Ok(Dispatch::Ignored);
+            };
+            let (task_id, _) =
+                link_issue_task(&mut tx, forge.kind(), project_id, repository, requested_by, &issue).await?;
+            task_id
+        }
+    };
+    tx.commit().await?;
+
+    let access_token = access_token_for(state, forge.kind(), repository, None).await?;
+    let ctx = SyncContext {
+        pool: &state.pool,
+        forge,
+        access_token: access_token.as_deref(),
+    };
+    sync_task(&ctx, task_id, Some(issue)).await?;
+
+    Ok(Dispatch::Processed)
+}
+
+/// Detaches the task linked to an issue that was deleted on its forge.
+pub async fn unlink_issue(state: &SharedState, node_id: &str) -> Result<Dispatch, AppError> {
+    let unlinked = sqlx::query(
+        r#"
+        UPDATE tasks
+        SET github_node_id = NULL,
+            github_repository = NULL,
+            github_issue_number = NULL,
+            github_url = NULL,
+            github_updated_at = NULL
+        WHERE github_node_id = $1
+        "#,
+    )
+    .bind(node_id)
+    .execute(&state.pool)
+    .await?;
+
+    Ok(if unlinked.rows_affected() > 0 {
+        Dispatch::Processed
+    } else {
+        Dispatch::Ignored
+    })
+}
+
+/// Finds or creates the task linked to an issue, keyed by the issue's
+/// GraphQL node ID so renamed or transferred repositories keep their tasks,
+/// and refreshes where the issue lives. Returns the task and whether it is new.
+pub async fn link_issue_task(
+    conn: &mut PgConnection,
+    kind: ForgeKind,
+    project_id: Uuid,
+    repository: &str,
+    created_by: Option<Uuid>,
+    issue: &ForgeIssue,
+) -> Result<(Uuid, bool), AppError> {
+    let existing = sqlx::query_scalar::<_, Uuid>("SELECT id FROM tasks WHERE github_node_id = $1 FOR UPDATE")
+        .bind(&issue.node_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let (task_id, created) = match existing {
+        Some(task_id) => (task_id, false),
+        None => {
+            let task = insert_task(conn, project_id, &issue.title, issue.body.as_deref(), created_by).await?;
+            (task.id, true)
+        }
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE tasks
+        SET forge = $6,
+            github_node_id = $2,
+            github_repository = $3,
+            github_issue_number = $4,
+            github_url = $5
+        WHERE id = $1
+        "#,
+    )
+    .bind(task_id)
+    .bind(&issue.node_id)
+    .bind(repository)
+    .bind(issue.number)
+    .bind(&issue.html_url)
+    .bind(kind.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok((task_id, created))
+}
+
+struct LocalTask {
+    project_id: Uuid,
+    repository: String,
+    issue_number: i32,
+    title: String,
+    description: Option<String>,
+    status: String,
+    assignee_login: Option<String>,
+    labels: Vec<String>,
+    updated_at: DateTime<Utc>,
+}
+
+impl LocalTask {
+    fn snapshot(&self, base: Option<&IssueSnapshot>) -> IssueSnapshot {
+        // A task has a single assignee while an issue can have several. If the
+        // local assignee is still one of the synced assignees, treat the list
+        // as unchanged instead of trimming it down on GitHub.
+        let assignees = match (&self.assignee_login, base) {
+            (Some(login), Some(base)) if base.assignees.contains(&login.to_lowercase()) => base.assignees.clone(),
+            (Some(login), _) => vec![login.clone()],
+            (None, _) => Vec::new(),
+        };
+
+        IssueSnapshot::new(
+            &self.title,
+            self.description.as_deref(),
+            matches!(self.status.as_str(), "done" | "canceled"),
+            self.labels.clone(),
+            assignees,
+        )
+    }
+}
+
+async fn load_local(conn: &mut PgConnection, task_id: Uuid) -> Result<Option<LocalTask>, AppError> {
+    let row = sqlx::query_as::<
+        _,
+        (
+            Uuid,
+            Option<String>,
+            Option<i32>,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Vec<String>,
+            DateTime<Utc>,
+        ),
+    >(
+        r#"
+        SELECT t.project_id,
+               t.github_repository,
+               t.github_issue_number,
+               t.title,
+               t.description,
+               t.status,
+               u.login,
+               ARRAY(
+                   SELECT l.name FROM task_labels tl
+                   JOIN labels l ON l.id = tl.label_id
+                   WHERE tl.task_id = t.id
+               ),
+               t.updated_at
+        FROM tasks t
+        LEFT JOIN users u ON u.id = t.assignee_id
+        WHERE t.id = $1
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(row.and_then(
+        |(project_id, repository, issue_number, title, description, status, assignee_login, labels, updated_at)| {
+            Some(LocalTask {
+                project_id,
+                repository: repository?,
+                issue_number: issue_number?,
+                title,
+                description,
+                status,
+                assignee_login,
+                labels,
+                updated_at,
+            })
+        },
+    ))
+}
+
+async fn load_base(conn: &mut PgConnection, task_id: Uuid) -> Result<Option<IssueSnapshot>, AppError> {
+    let base = sqlx::query_scalar::<_, Json<IssueSnapshot>>("SELECT snapshot FROM github_issue_sync_state WHERE task_id = $1")
+        .bind(task_id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(base.map(|Json(snapshot)| snapshot))
+}
+
+async fn apply_local(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    current_status: &str,
+    state_reason: Option<&str>,
+    kind: ForgeKind,
+    assignee_ids: &[i64],
+    plan: &SyncPlan,
+) -> Result<(), AppError> {
+    let merged = &plan.merged;
+    let previous = task_fields(conn, task_id).await?;
+    let locally_closed = matches!(current_status, "done" | "canceled");
+    let status = match (merged.closed, locally_closed) {
+        (true, false) if state_reason == Some("not_planned") => "canceled",
+        (true, false) => "done",
+        (false, true) => "todo",
+        _ => current_status,
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE tasks
+        SET title = $2,
+            description = $3,
+            status = $4,
+            completed_at = CASE
+                WHEN $4 IN ('done', 'canceled') THEN COALESCE(completed_at, now())
+                ELSE NULL
+            END
+        WHERE id = $1
+        "#,
+    )
+    .bind(task_id)
+    .bind(&merged.title)
+    .bind(&merged.body)
+    .bind(status)
+    .execute(&mut *conn)
+    .await?;
+
+    if plan.apply_local.contains(&SyncField::Labels) {
+        sqlx::query(
+            r#"
+            INSERT INTO labels (name)
+            SELECT name FROM UNNEST($1::text[]) AS name
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(&merged.labels)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM task_labels tl
+            USING labels l
+            WHERE tl.task_id = $1 AND l.id = tl.label_id
+              AND NOT (LOWER(l.name) = ANY(SELECT LOWER(name) FROM UNNEST($2::text[]) AS name))
+            "#,
+        )
+        .bind(task_id)
+        .bind(&merged.labels)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO task_labels (task_id, label_id)
+            SELECT $1, l.id FROM labels l
+            WHERE LOWER(l.name) = ANY(SELECT LOWER(name) FROM UNNEST($2::text[]) AS name)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(task_id)
+        .bind(&merged.labels)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    if plan.apply_local.contains(&SyncField::Assignees) {
+        let column = kind.user_id_column();
+        sqlx::query(&format!(
+            r#"
+            UPDATE tasks
+            SET assignee_id = (
+                SELECT u.id FROM users u
+                WHERE u.{column} = ANY($2)
+                ORDER BY array_position($2, u.{column})
+                LIMIT 1
+            )
+            WHERE id = $1
+            "#,
+        ))
+        .bind(task_id)
+        .bind(assignee_ids)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Watchers hear about changes pulled from the forge as they do about
+    // edits made in Keyflow.
+    let current = task_fields(conn, task_id).await?;
+    let mut changes = serde_json::Map::new();
+    if previous.0 != current.0 {
+        changes.insert("title".into(), json!({ "from": previous.0, "to": current.0 }));
+    }
+    if previous.1 != current.1 {
+        changes.insert("description".into(), json!({}));
+    }
+    if previous.2 != current.2 {
+        changes.insert("status".into(), json!({ "from": previous.2, "to": current.2 }));
+    }
+    if previous.3 != current.3 {
+        changes.insert("assignee_id".into(), json!({ "from": previous.3, "to": current.3 }));
+    }
+    if !changes.is_empty() {
+        changes.insert("source".into(), json!(kind.as_str()));
+        let data = serde_json::Value::Object(changes);
+        record_event(&mut *conn, task_id, None, "issue_synced", data.clone()).await?;
+        fan_out(&mut *conn, task_id, None, "task_updated", data).await?;
+    }
+
+    Ok(())
+}
+
+/// Title, description, status and assignee, the task fields a sync can change.
+async fn task_fields(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+) -> Result<(String, Option<String>, String, Option<Uuid>), AppError> {
+    let fields = sqlx::query_as("SELECT title, description, status, assignee_id FROM tasks WHERE id = $1")
+        .bind(task_id)
+        .fetch_one(conn)
+        .await?;
+    Ok(fields)
+}
+
+fn issue_update(plan: &SyncPlan) -> IssueUpdate {
+    let merged = &plan.merged;
+    let mut update = IssueUpdate::default();
+    for field in &plan.push_remote {
+        match field {
+            SyncField::Title => update.title = Some(merged.title.clone()),
+            SyncField::Body => update.body = Some(merged.body.clone().unwrap_or_default()),
+            SyncField::State => update.state = Some(if merged.closed { "closed" } else { "open" }),
+            SyncField::Labels => update.labels = Some(merged.labels.clone()),
+            SyncField::Assignees => update.assignees = Some(merged.assignees.clone()),
+        }
+    }
+    update
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn snapshot(title: &str, closed: bool) -> IssueSnapshot {
+        IssueSnapshot::new(title, None, closed, Vec::new(), Vec::new())
+    }
+
+    fn times(local_newer: bool) -> (DateTime<Utc>, DateTime<Utc>) {
+        let earlier = Utc::now();
+        let later = earlier + Duration::minutes(1);
+        if local_newer {
+            (later, earlier)
+        } else {
+            (earlier, later)
+        }
+    }
+
+    #[test]
+    fn a_change_on_one_side_goes_to_the_other() {
+        let base = snapshot("Widgets wobble", false);
+        let (local_at, remote_at) = times(false);
+
+        let pushed = reconcile(Some(&base), &snapshot("Widgets wobble on resize", false), &base, local_at, remote_at);
+        assert_eq!(pushed.merged.title, "Widgets wobble on resize");
+        assert_eq!((pushed.apply_local, pushed.push_remote), (vec![], vec![SyncField::Title]));
+        assert!(pushed.conflicts.is_empty());
+
+        let pulled = reconcile(Some(&base), &base, &snapshot("Widgets wobble", true), local_at, remote_at);
+        assert!(pulled.merged.closed);
+        assert_eq!((pulled.apply_local, pulled.push_remote), (vec![SyncField::State], vec![]));
+        assert!(pulled.conflicts.is_empty());
+    }
+
+    #[test]
+    fn the_same_change_on_both_sides_is_not_a_conflict() {
+        let base = snapshot("Widgets wobble", false);
+        let both = snapshot("Widgets wobble on resize", true);
+        let (local_at, remote_at) = times(true);
+
+        let plan = reconcile(Some(&base), &both, &both, local_at, remote_at);
+
+        assert_eq!(plan.merged, both);
+        assert!(plan.apply_local.is_empty() && plan.push_remote.is_empty());
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn different_changes_go_to_the_side_updated_last() {
+        let base = snapshot("Widgets wobble", false);
+        let local = snapshot("Widgets wobble on resize", false);
+        let remote = snapshot("Widgets jitter", false);
+
+        let (local_at, remote_at) = times(true);
+        let local_wins = reconcile(Some(&base), &local, &remote, local_at, remote_at);
+        assert_eq!(local_wins.merged.title, "Widgets wobble on resize");
+        assert_eq!((local_wins.apply_local, local_wins.push_remote), (vec![], vec![SyncField::Title]));
+        assert_eq!(local_wins.conflicts.len(), 1);
+        assert_eq!(local_wins.conflicts[0].field, SyncField::Title);
+        assert_eq!(local_wins.conflicts[0].winner, Side::Local);
+        assert_eq!(local_wins.conflicts[0].base, json!("Widgets wobble"));
+
+        let (local_at, remote_at) = times(false);
+        let remote_wins = reconcile(Some(&base), &local, &remote, local_at, remote_at);
+        assert_eq!(remote_wins.merged.title, "Widgets jitter");
+        assert_eq!((remote_wins.apply_local, remote_wins.push_remote), (vec![SyncField::Title], vec![]));
+        assert_eq!(remote_wins.conflicts.len(), 1);
+        assert_eq!(remote_wins.conflicts[0].winner, Side::Remote);
+    }
+
+    #[test]
+    fn without_a_base_the_issue_is_pulled_in() {
+        let local = snapshot("Widgets wobble", false);
+        let remote = snapshot("Widgets jitter", true);
+        let (local_at, remote_at) = times(true);
+
+        let plan = reconcile(None, &local, &remote, local_at, remote_at);
+
+        assert_eq!(plan.merged, remote);
+        assert_eq!(plan.apply_local, vec![SyncField::Title, SyncField::State]);
+        assert!(plan.push_remote.is_empty());
+        assert!(plan.conflicts.is_empty());
+    }
+}
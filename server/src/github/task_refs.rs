@@ -0,0 +1,243 @@
+use crate::error::AppError;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// A `KEY-123` task reference, with the project key upper-cased.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TaskKey {
+    pub project_key: String,
+    pub number: i32,
+}
+
+/// Finds task keys such as `KF-12` in free text, branch names included
+/// (`octocat/kf-12-fix-login`). Keys must stand alone: `xKF-12` and `KF-12a`
+/// are not references. Duplicates are returned once, in order of appearance.
+pub fn find_task_keys(text: &str) -> Vec<TaskKey> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut keys: Vec<TaskKey> = Vec::new();
+    for (_, _, key) in key_spans(&chars) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    keys
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefAction {
+    /// `fixes KF-12`: the task is done once the commit reaches the default branch.
+    Closes,
+    /// Any other mention; the commit is only attached to the task.
+    References,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitReference {
+    pub key: TaskKey,
+    pub action: RefAction,
+}
+
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// Task references in a commit message. A key directly after a closing
+/// keyword (`Fixes KF-1`, `closes: KF-1`) closes the task, as does every key
+/// in a list following one (`fixes KF-1, KF-2 and KF-3`). Everything else,
+/// `refs KF-1` included, only references it. Reverts never close anything.
+/// A key both closed and referenced is reported once, as closing.
+pub fn find_commit_references(message: &str) -> Vec<CommitReference> {
+    let chars: Vec<char> = message.chars().collect();
+    let is_revert = message.trim_start().starts_with("Revert \"");
+    let mut references: Vec<CommitReference> = Vec::new();
+    let mut previous: Option<(usize, RefAction)> = None;
+
+    for (start, end, key) in key_spans(&chars) {
+        let gap: String = chars[previous.map_or(0, |(end, _)| end)..start].iter().collect();
+        let continues_list = matches!(previous, Some((_, RefAction::Closes))) && is_list_separator(&gap);
+        let action = if !is_revert && (continues_list || ends_with_closing_keyword(&gap)) {
+            RefAction::Closes
+        } else {
+            RefAction::References
+        };
+        previous = Some((end, action));
+
+        match references.iter_mut().find(|existing| existing.key == key) {
+            Some(existing) if action == RefAction::Closes => existing.action = action,
+            Some(_) => {}
+            None => references.push(CommitReference { key, action }),
+        }
+    }
+
+    references
+}
+
+/// `, `, ` and `, `, and ` or plain whitespace between two keys of a list.
+fn is_list_separator(gap: &str) -> bool {
+    let rest = gap.trim().trim_start_matches(',').trim_start();
+    rest.is_empty() || rest.eq_ignore_ascii_case("and")
+}
+
+/// Whether the text before a key ends in a closing keyword, optionally
+/// followed by a colon.
+fn ends_with_closing_keyword(gap: &str) -> bool {
+    let trimmed = gap.trim_end();
+    if trimmed.len() == gap.len() && !gap.is_empty() && !gap.ends_with(':') {
+        // The key is glued to the preceding word, e.g. `fix(KF-1)`.
+        return false;
+    }
+    let trimmed = trimmed.strip_suffix(':').unwrap_or(trimmed);
+    let word_start = trimmed
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphabetic())
+        .last()
+        .map_or(trimmed.len(), |(index, _)| index);
+    let word = &trimmed[word_start..];
+
+    CLOSING_KEYWORDS.iter().any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+/// Start and end offsets (in chars) of every key in `chars`.
+fn key_spans(chars: &[char]) -> Vec<(usize, usize, TaskKey)> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let starts_word = i == 0 || !chars[i - 1].is_ascii_alphanumeric();
+        if !(starts_word && chars[i].is_ascii_alphabetic()) {
+            i += 1;
+            continue;
+        }
+
+        let key_end = i + chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric()).count();
+        let key_len = key_end - i;
+        if !(2..=10).contains(&key_len) || chars.get(key_end) != Some(&'-') {
+            i = key_end.max(i + 1);
+            continue;
+        }
+
+        let digits_start = key_end + 1;
+        let digits_end = digits_start + chars[digits_start..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let ends_word = chars.get(digits_end).is_none_or(|c| !c.is_alphanumeric());
+        if digits_end > digits_start && ends_word {
+            let digits: String = chars[digits_start..digits_end].iter().collect();
+            if let Ok(number) = digits.parse::<i32>() {
+                let key = TaskKey {
+                    project_key: chars[i..key_end].iter().collect::<String>().to_ascii_uppercase(),
+                    number,
+                };
+                spans.push((i, digits_end, key));
+            }
+        }
+        i = digits_end.max(key_end + 1);
+    }
+
+    spans
+}
+
+/// Resolves keys to existing task IDs; unknown keys are dropped.
+pub async fn resolve_task_keys(conn: &mut PgConnection, keys: &[TaskKey]) -> Result<Vec<Uuid>, AppError> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let project_keys: Vec<&str> = keys.iter().map(|key| key.project_key.as_str()).collect();
+    let numbers: Vec<i32> = keys.iter().map(|key| key.number).collect();
+    let task_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT t.id
+        FROM UNNEST($1::text[], $2::int[]) AS k (project_key, number)
+        JOIN projects p ON p.key = k.project_key
+        JOIN tasks t ON t.project_id = p.id AND t.number = k.number
+        "#,
+    )
+    .bind(&project_keys)
+    .bind(&numbers)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(task_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(project_key: &str, number: i32) -> TaskKey {
+        TaskKey {
+            project_key: project_key.to_string(),
+            number,
+        }
+    }
+
+    /// Project key, task number and action of an expected reference.
+    type Expected = (&'static str, i32, RefAction);
+
+    /// Commit messages as developers actually write them, with the
+    /// references each should produce.
+    const CORPUS: &[(&str, &[Expected])] = &[
+        ("Fix login redirect loop\n\nFixes KF-123", &[("KF", 123, RefAction::Closes)]),
+        ("fixes kf-123: handle expired sessions", &[("KF", 123, RefAction::Closes)]),
+        ("Closes: KF-7", &[("KF", 7, RefAction::Closes)]),
+        ("resolved KF-8 by retrying the upload", &[("KF", 8, RefAction::Closes)]),
+        (
+            "Tidy up importer\n\nFixes KF-1, KF-2 and KF-3",
+            &[
+                ("KF", 1, RefAction::Closes),
+                ("KF", 2, RefAction::Closes),
+                ("KF", 3, RefAction::Closes),
+            ],
+        ),
+        (
+            "Fix KF-10 and refs KF-11",
+            &[("KF", 10, RefAction::Closes), ("KF", 11, RefAction::References)],
+        ),
+        ("refs KF-42: extract helper", &[("KF", 42, RefAction::References)]),
+        ("KF-42 wip", &[("KF", 42, RefAction::References)]),
+        ("fix(KF-9): trim whitespace in titles", &[("KF", 9, RefAction::References)]),
+        ("Bump serde to 1.0.190", &[]),
+        ("Upgrade to UTF-8 everywhere", &[("UTF", 8, RefAction::References)]),
+        ("hotfix KF-5 typo", &[("KF", 5, RefAction::References)]),
+        ("Revert \"Fixes KF-12\"\n\nThis reverts commit 0b1c2d3.", &[("KF", 12, RefAction::References)]),
+        (
+            "Merge pull request #31 from octocat/kf-31-fix-login\n\nFixes KF-31",
+            &[("KF", 31, RefAction::Closes)],
+        ),
+        ("See KF-4; closes OPS-17", &[("KF", 4, RefAction::References), ("OPS", 17, RefAction::Closes)]),
+        ("Fixes KF-12a before release", &[]),
+        ("Fixes #123", &[]),
+    ];
+
+    #[test]
+    fn commit_reference_corpus() {
+        for (message, expected) in CORPUS {
+            let expected: Vec<CommitReference> = expected
+                .iter()
+                .map(|(project_key, number, action)| CommitReference {
+                    key: key(project_key, *number),
+                    action: *action,
+                })
+                .collect();
+            assert_eq!(find_commit_references(message), expected, "message: {:?}", message);
+        }
+    }
+
+    #[test]
+    fn closing_wins_over_earlier_mention() {
+        assert_eq!(
+            find_commit_references("Prepare KF-3 for review\n\nCloses KF-3"),
+            vec![CommitReference {
+                key: key("KF", 3),
+                action: RefAction::Closes,
+            }]
+        );
+    }
+
+    #[test]
+    fn task_keys_in_branch_names() {
+        assert_eq!(find_task_keys("octocat/kf-12-fix-login"), vec![key("KF", 12)]);
+        assert_eq!(find_task_keys("KF-1 KF-1 ops-2"), vec![key("KF", 1), key("OPS", 2)]);
+    }
+}
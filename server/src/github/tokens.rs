@@ -0,0 +1,286 @@
+use crate::{
+    error::AppError,
+    forge::{ForgeKind, ForgeToken},
+    github::app::installation_token_for,
+    state::AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use oauth2::{basic::BasicErrorResponseType, RequestTokenError};
+use sqlx::PgConnection;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Tokens this close to expiry are refreshed before use.
+const REFRESH_MARGIN_MINUTES: i64 = 5;
+
+struct StoredToken {
+    key_id: String,
+    access_token: Vec<u8>,
+    access_token_expires_at: Option<DateTime<Utc>>,
+    refresh_token: Option<Vec<u8>>,
+}
+
+pub async fn store_user_token(
+    state: &AppState,
+    kind: ForgeKind,
+    user_id: Uuid,
+    token: &ForgeToken,
+) -> Result<(), AppError> {
+    let mut conn = state.pool.acquire().await?;
+    let scopes = token.scopes.clone().unwrap_or_default();
+    save_token(&mut conn, state, kind, user_id, token, Some(&scopes)).await
+}
+
+/// The user's access token for the forge, refreshed first if it is about to
+/// expire. `None` means the user has to sign in with that forge again; an
+/// error means the refresh could not be completed and may succeed later.
+pub async fn user_access_token(state: &AppState, kind: ForgeKind, user_id: Uuid) -> Result<Option<String>, AppError> {
+    let mut tx = state.pool.begin().await?;
+    // Refresh tokens are single-use, so concurrent refreshes for the same user
+    // must not race.
+    let Some(stored) = sqlx::query_as::<_, (String, Vec<u8>, Option<DateTime<Utc>>, Option<Vec<u8>>)>(
+        r#"
+        SELECT key_id, access_token, access_token_expires_at, refresh_token
+        FROM user_github_tokens
+        WHERE user_id = $1 AND forge = $2
+        FOR UPDATE
+        "#,
+    )
+    .bind(user_id)
+    .bind(kind.as_str())
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|(key_id, access_token, access_token_expires_at, refresh_token)| StoredToken {
+        key_id,
+        access_token,
+        access_token_expires_at,
+        refresh_token,
+    }) else {
+        return Ok(None);
+    };
+
+    let cipher = &state.token_cipher;
+    let expiring = stored
+        .access_token_expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now() + Duration::minutes(REFRESH_MARGIN_MINUTES));
+    if !expiring {
+        let access_token = cipher.open(&stored.key_id, &stored.access_token, &context(user_id, "access"))?;
+        if stored.key_id != cipher.primary_key_id() {
+            reseal(&mut tx, state, user_id, &stored).await?;
+        }
+        tx.commit().await?;
+        return Ok(Some(access_token));
+    }
+
+    let Some(sealed_refresh) = &stored.refresh_token else {
+        forget_token(&mut tx, kind, user_id).await?;
+        tx.commit().await?;
+        return Ok(None);
+    };
+    let refresh_token = cipher.open(&stored.key_id, sealed_refresh, &context(user_id, "refresh"))?;
+
+    match state.forges.get(kind)?.refresh_token(refresh_token).await {
+        Ok(token) => {
+            save_token(&mut tx, state, kind, user_id, &token, None).await?;
+            tx.commit().await?;
+            info!(user_id = %user_id, forge = kind.as_str(), "refreshed access token");
+            Ok(Some(token.access_token))
+        }
+        Err(err) if refresh_token_rejected(&err) => {
+            warn!(user_id = %user_id, forge = kind.as_str(), error = %err, "refresh token rejected; user must sign in again");
+            forget_token(&mut tx, kind, user_id).await?;
+            tx.commit().await?;
+            Ok(None)
+        }
+        // Timeouts and forge outages say nothing about the token, which is
+        // kept for the next attempt.
+        Err(err) => {
+            warn!(user_id = %user_id, forge = kind.as_str(), error = %err, "token refresh failed");
+            Err(err)
+        }
+    }
+}
+
+/// Whether the forge answered that the refresh token is no longer valid.
+/// GitHub reports this as `bad_refresh_token` rather than `invalid_grant`.
+fn refresh_token_rejected(err: &AppError) -> bool {
+    match err {
+        AppError::OAuth(RequestTokenError::ServerResponse(response)) => match response.error() {
+            BasicErrorResponseType::InvalidGrant => true,
+            BasicErrorResponseType::Extension(code) => code == "bad_refresh_token",
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// The token to act on `repository` with: the user's own token when a user
+/// is acting, otherwise (on GitHub) the GitHub App installation covering the
+/// repository, and finally the configured server token.
+pub async fn access_token_for(
+    state: &AppState,
+    kind: ForgeKind,
+    repository: &str,
+    user_id: Option<Uuid>,
+) -> Result<Option<String>, AppError> {
+    if let Some(user_id) = user_id {
+        if let Some(token) = user_access_token(state, kind, user_id).await? {
+            return Ok(Some(token));
+        }
+    }
+
+    match kind {
+        ForgeKind::GitHub => {
+            if let Some(token) = installation_token_for(state, repository).await? {
+                return Ok(Some(token));
+            }
+            Ok(state.config.github_api_token.clone())
+        }
+        ForgeKind::GitLab => Ok(state.config.gitlab.as_ref().and_then(|gitlab| gitlab.api_token.clone())),
+        ForgeKind::Gitea => Ok(state.config.gitea.as_ref().and_then(|gitea| gitea.api_token.clone())),
+    }
+}
+
+/// Re-encrypts every token sealed with a key other than the primary one, so
+/// retired keys can be dropped from the configuration afterwards.
+pub async fn reseal_tokens(state: &AppState) -> Result<u64, AppError> {
+    let primary = state.token_cipher.primary_key_id();
+    let user_ids = sqlx::query_scalar::<_, Uuid>("SELECT user_id FROM user_github_tokens WHERE key_id <> $1")
+        .bind(primary)
+        .fetch_all(&state.pool)
+        .await?;
+
+    let mut resealed = 0;
+    for user_id in user_ids {
+        let mut tx = state.pool.begin().await?;
+        let stored = sqlx::query_as::<_, (String, Vec<u8>, Option<DateTime<Utc>>, Option<Vec<u8>>)>(
+            r#"
+            SELECT key_id, access_token, access_token_expires_at, refresh_token
+            FROM user_github_tokens
+            WHERE user_id = $1 AND key_id <> $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .bind(primary)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some((key_id, access_token, access_token_expires_at, refresh_token)) = stored {
+            let stored = StoredToken {
+                key_id,
+                access_token,
+                access_token_expires_at,
+                refresh_token,
+            };
+            reseal(&mut tx, state, user_id, &stored).await?;
+            resealed += 1;
+        }
+        tx.commit().await?;
+    }
+
+    Ok(resealed)
+}
+
+async fn save_token(
+    conn: &mut PgConnection,
+    state: &AppState,
+    kind: ForgeKind,
+    user_id: Uuid,
+    token: &ForgeToken,
+    scopes: Option<&[String]>,
+) -> Result<(), AppError> {
+    let cipher = &state.token_cipher;
+    let access = cipher.seal(&token.access_token, &context(user_id, "access"))?;
+    let refresh = token
+        .refresh_token
+        .as_deref()
+        .map(|refresh| cipher.seal(refresh, &context(user_id, "refresh")))
+        .transpose()?;
+    let expires_at = token
+        .expires_in
+        .and_then(|expires_in| Duration::from_std(expires_in).ok())
+        .map(|expires_in| Utc::now() + expires_in);
+
+    // A refresh response does not repeat the granted scopes; keep the ones
+    // stored at sign-in in that case.
+    sqlx::query(
+        r#"
+        INSERT INTO user_github_tokens
+            (user_id, key_id, access_token, access_token_expires_at, refresh_token, scopes, forge)
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, '{}'), $7)
+        ON CONFLICT (user_id) DO UPDATE
+        SET forge = EXCLUDED.forge,
+            key_id = EXCLUDED.key_id,
+            access_token = EXCLUDED.access_token,
+            access_token_expires_at = EXCLUDED.access_token_expires_at,
+            refresh_token = EXCLUDED.refresh_token,
+            scopes = COALESCE($6, user_github_tokens.scopes)
+        "#,
+    )
+    .bind(user_id)
+    .bind(&access.key_id)
+    .bind(&access.bytes)
+    .bind(expires_at)
+    .bind(refresh.map(|sealed| sealed.bytes))
+    .bind(scopes)
+    .bind(kind.as_str())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn reseal(
+    conn: &mut PgConnection,
+    state: &AppState,
+    user_id: Uuid,
+    stored: &StoredToken,
+) -> Result<(), AppError> {
+    let cipher = &state.token_cipher;
+    let access_context = context(user_id, "access");
+    let refresh_context = context(user_id, "refresh");
+
+    let access = cipher.seal(
+        &cipher.open(&stored.key_id, &stored.access_token, &access_context)?,
+        &access_context,
+    )?;
+    let refresh = stored
+        .refresh_token
+        .as_deref()
+        .map(|sealed| {
+            cipher
+                .open(&stored.key_id, sealed, &refresh_context)
+                .and_then(|plaintext| cipher.seal(&plaintext, &refresh_context))
+        })
+        .transpose()?;
+
+    sqlx::query(
+        r#"
+        UPDATE user_github_tokens
+        SET key_id = $2, access_token = $3, refresh_token = $4
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&access.key_id)
+    .bind(&access.bytes)
+    .bind(refresh.map(|sealed| sealed.bytes))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn forget_token(conn: &mut PgConnection, kind: ForgeKind, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM user_github_tokens WHERE user_id = $1 AND forge = $2")
+        .bind(user_id)
+        .bind(kind.as_str())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+fn context(user_id: Uuid, kind: &str) -> Vec<u8> {
+    format!("user_github_tokens:{}:{}", user_id, kind).into_bytes()
+}
@@ -0,0 +1,455 @@
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeAccount, ForgeIssue, ForgeLabel, ForgeMilestone, ForgeProvider, PullRequest},
+    github::{
+        commits::{record_push, PushCommit},
+        labels::{forget_label, link_label},
+        milestones::refresh_milestone,
+        pull_requests::record_pull_request,
+        sync::{sync_issue_event, unlink_issue},
+    },
+    routes::fan_out,
+    state::SharedState,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::PgConnection;
+use subtle::ConstantTimeEq;
+use tracing::info;
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+pub const EVENT_HEADER: &str = "x-github-event";
+pub const DELIVERY_HEADER: &str = "x-github-delivery";
+
+#[derive(Deserialize)]
+struct WebhookRepository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct IssuesEvent {
+    action: String,
+    issue: ForgeIssue,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct IssueCommentEvent {
+    action: String,
+    issue: ForgeIssue,
+    comment: WebhookComment,
+}
+
+#[derive(Deserialize)]
+struct WebhookComment {
+    id: i64,
+    body: Option<String>,
+    user: ForgeAccount,
+}
+
+#[derive(Deserialize)]
+struct InstallationEvent {
+    action: String,
+    installation: WebhookInstallation,
+    #[serde(default)]
+    repositories: Vec<WebhookInstallationRepository>,
+}
+
+#[derive(Deserialize)]
+struct InstallationRepositoriesEvent {
+    action: String,
+    installation: WebhookInstallation,
+    #[serde(default)]
+    repositories_added: Vec<WebhookInstallationRepository>,
+    #[serde(default)]
+    repositories_removed: Vec<WebhookInstallationRepository>,
+}
+
+#[derive(Deserialize)]
+struct WebhookInstallation {
+    id: i64,
+    account: WebhookInstallationAccount,
+    repository_selection: String,
+}
+
+#[derive(Deserialize)]
+struct WebhookInstallationAccount {
+    login: String,
+    #[serde(rename = "type")]
+    account_type: String,
+}
+
+#[derive(Deserialize)]
+struct WebhookInstallationRepository {
+    id: i64,
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    pull_request: PullRequest,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct LabelEvent {
+    action: String,
+    label: ForgeLabel,
+    repository: WebhookRepository,
+}
+
+#[derive(Deserialize)]
+struct MilestoneEvent {
+    action: String,
+    milestone: ForgeMilestone,
+}
+
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+    repository: PushRepository,
+}
+
+/// Push payloads carry the default branch, which decides whether closing
+/// references take effect.
+#[derive(Deserialize)]
+struct PushRepository {
+    full_name: String,
+    default_branch: Option<String>,
+}
+
+/// Checks an `X-Hub-Signature-256` value (`sha256=<hex>`) against the HMAC of
+/// the raw request body.
+pub fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|value| value.strip_prefix("sha256="))
+        .and_then(|hex_digest| hex::decode(hex_digest).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    let actual = mac.finalize().into_bytes();
+
+    actual.as_slice().ct_eq(&expected).into()
+}
+
+/// Routes a stored delivery to the handler for its event type. `forge` is
+/// the GitHub backend the delivery came through.
+pub async fn dispatch(
+    state: &SharedState,
+    forge: &dyn ForgeProvider,
+    event: &str,
+    payload: Value,
+) -> Result<Dispatch, AppError> {
+    match event {
+        "issues" => handle_issues(state, forge, serde_json::from_value(payload)?).await,
+        "issue_comment" => handle_issue_comment(state, serde_json::from_value(payload)?).await,
+        "installation" => handle_installation(state, serde_json::from_value(payload)?).await,
+        "installation_repositories" => {
+            handle_installation_repositories(state, serde_json::from_value(payload)?).await
+        }
+        "pull_request" => handle_pull_request(state, serde_json::from_value(payload)?).await,
+        "push" => handle_push(state, serde_json::from_value(payload)?).await,
+        "label" => handle_label(state, serde_json::from_value(payload)?).await,
+        "milestone" => handle_milestone(state, serde_json::from_value(payload)?).await,
+        _ => Ok(Dispatch::Ignored),
+    }
+}
+
+async fn handle_issues(state: &SharedState, forge: &dyn ForgeProvider, event: IssuesEvent) -> Result<Dispatch, AppError> {
+    if event.action == "deleted" {
+        return unlink_issue(state, &event.issue.node_id).await;
+    }
+
+    sync_issue_event(state, forge, &event.repository.full_name, event.issue).await
+}
+
+async fn handle_issue_comment(state: &SharedState, event: IssueCommentEvent) -> Result<Dispatch, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let Some(task_id) = sqlx::query_scalar::<_, Uuid>("SELECT id FROM tasks WHERE github_node_id = $1")
+        .bind(&event.issue.node_id)
+        .fetch_optional(&mut *tx)
+        .await?
+    else {
+        return Ok(Dispatch::Ignored);
+    };
+    let body = event.comment.body.unwrap_or_default();
+
+    match event.action.as_str() {
+        "created" => {
+            let inserted = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+                r#"
+                INSERT INTO task_comments (task_id, author_id, body, github_comment_id)
+                SELECT $1, (SELECT id FROM users WHERE github_id = $2), $3, $4
+                ON CONFLICT DO NOTHING
+                RETURNING id, author_id
+                "#,
+            )
+            .bind(task_id)
+            .bind(event.comment.user.id)
+            .bind(&body)
+            .bind(event.comment.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if let Some((comment_id, author_id)) = inserted {
+                fan_out(
+                    &mut tx,
+                    task_id,
+                    author_id,
+                    "comment_added",
+                    json!({ "comment_id": comment_id, "source": "github" }),
+                )
+                .await?;
+            }
+        }
+        "edited" => {
+            sqlx::query("UPDATE task_comments SET body = $2 WHERE github_comment_id = $1")
+                .bind(event.comment.id)
+                .bind(&body)
+                .execute(&mut *tx)
+                .await?;
+        }
+        "deleted" => {
+            sqlx::query("DELETE FROM task_comments WHERE github_comment_id = $1")
+                .bind(event.comment.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        _ => return Ok(Dispatch::Ignored),
+    }
+    tx.commit().await?;
+
+    Ok(Dispatch::Processed)
+}
+
+async fn handle_installation(state: &SharedState, event: InstallationEvent) -> Result<Dispatch, AppError> {
+    let installation_id = event.installation.id;
+    let mut tx = state.pool.begin().await?;
+
+    match event.action.as_str() {
+        "created" | "new_permissions_accepted" => {
+            upsert_installation(&mut tx, &event.installation).await?;
+            add_installation_repositories(&mut tx, installation_id, &event.repositories).await?;
+        }
+        "deleted" => {
+            sqlx::query("DELETE FROM github_installations WHERE id = $1")
+                .bind(installation_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        "suspend" | "unsuspend" => {
+            upsert_installation(&mut tx, &event.installation).await?;
+            sqlx::query(
+                r#"
+                UPDATE github_installations
+                SET suspended_at = CASE WHEN $2 THEN now() ELSE NULL END
+                WHERE id = $1
+                "#,
+            )
+            .bind(installation_id)
+            .bind(event.action == "suspend")
+            .execute(&mut *tx)
+            .await?;
+        }
+        _ => return Ok(Dispatch::Ignored),
+    }
+    tx.commit().await?;
+
+    if let (Some(app), "deleted" | "suspend") = (&state.github_app, event.action.as_str()) {
+        app.forget_installation(installation_id);
+    }
+    info!(installation_id, action = %event.action, "github app installation updated");
+
+    Ok(Dispatch::Processed)
+}
+
+async fn handle_installation_repositories(
+    state: &SharedState,
+    event: InstallationRepositoriesEvent,
+) -> Result<Dispatch, AppError> {
+    if !matches!(event.action.as_str(), "added" | "removed") {
+        return Ok(Dispatch::Ignored);
+    }
+
+    let installation_id = event.installation.id;
+    let mut tx = state.pool.begin().await?;
+    upsert_installation(&mut tx, &event.installation).await?;
+    add_installation_repositories(&mut tx, installation_id, &event.repositories_added).await?;
+
+    let removed: Vec<i64> = event.repositories_removed.iter().map(|repository| repository.id).collect();
+    sqlx::query(
+        "DELETE FROM github_installation_repositories WHERE installation_id = $1 AND repository_id = ANY($2)",
+    )
+    .bind(installation_id)
+    .bind(&removed)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Dispatch::Processed)
+}
+
+async fn upsert_installation(conn: &mut PgConnection, installation: &WebhookInstallation) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO github_installations (id, account_login, account_type, repository_selection)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (id) DO UPDATE
+        SET account_login = EXCLUDED.account_login,
+            account_type = EXCLUDED.account_type,
+            repository_selection = EXCLUDED.repository_selection
+        "#,
+    )
+    .bind(installation.id)
+    .bind(&installation.account.login)
+    .bind(&installation.account.account_type)
+    .bind(&installation.repository_selection)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn add_installation_repositories(
+    conn: &mut PgConnection,
+    installation_id: i64,
+    repositories: &[WebhookInstallationRepository],
+) -> Result<(), AppError> {
+    let ids: Vec<i64> = repositories.iter().map(|repository| repository.id).collect();
+    let names: Vec<&str> = repositories.iter().map(|repository| repository.full_name.as_str()).collect();
+
+    sqlx::query(
+        r#"
+        INSERT INTO github_installation_repositories (installation_id, repository_id, full_name)
+        SELECT $1, r.id, r.full_name FROM UNNEST($2::bigint[], $3::text[]) AS r (id, full_name)
+        ON CONFLICT (installation_id, repository_id) DO UPDATE SET full_name = EXCLUDED.full_name
+        "#,
+    )
+    .bind(installation_id)
+    .bind(&ids)
+    .bind(&names)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn handle_pull_request(state: &SharedState, event: PullRequestEvent) -> Result<Dispatch, AppError> {
+    if !matches!(
+        event.action.as_str(),
+        "opened" | "edited" | "reopened" | "closed" | "ready_for_review" | "converted_to_draft" | "synchronize"
+    ) {
+        return Ok(Dispatch::Ignored);
+    }
+
+    let linked = record_pull_request(state, &event.repository.full_name, &event.pull_request).await?;
+    info!(
+        repository = %event.repository.full_name,
+        number = event.pull_request.number,
+        action = %event.action,
+        linked,
+        "processed github pull request event"
+    );
+
+    Ok(if linked > 0 {
+        Dispatch::Processed
+    } else {
+        Dispatch::Ignored
+    })
+}
+
+async fn handle_push(state: &SharedState, event: PushEvent) -> Result<Dispatch, AppError> {
+    let attached = record_push(
+        state,
+        &event.repository.full_name,
+        &event.git_ref,
+        event.repository.default_branch.as_deref(),
+        &event.commits,
+    )
+    .await?;
+    info!(
+        repository = %event.repository.full_name,
+        git_ref = %event.git_ref,
+        commits = event.commits.len(),
+        attached,
+        "processed github push event"
+    );
+
+    Ok(if attached > 0 {
+        Dispatch::Processed
+    } else {
+        Dispatch::Ignored
+    })
+}
+
+/// Only labels already mapped are followed; new ones are picked up when an
+/// issue carrying them is synced.
+async fn handle_label(state: &SharedState, event: LabelEvent) -> Result<Dispatch, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let linked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM github_label_mappings WHERE github_node_id = $1)",
+    )
+    .bind(&event.label.node_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !linked {
+        return Ok(Dispatch::Ignored);
+    }
+
+    match event.action.as_str() {
+        "edited" => {
+            link_label(&mut tx, &event.repository.full_name, &event.label).await?;
+        }
+        "deleted" => {
+            forget_label(&mut tx, &event.label.node_id).await?;
+        }
+        _ => return Ok(Dispatch::Ignored),
+    }
+    tx.commit().await?;
+
+    Ok(Dispatch::Processed)
+}
+
+async fn handle_milestone(state: &SharedState, event: MilestoneEvent) -> Result<Dispatch, AppError> {
+    let mut tx = state.pool.begin().await?;
+    let linked = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM github_milestones WHERE github_node_id = $1)",
+    )
+    .bind(&event.milestone.node_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !linked {
+        return Ok(Dispatch::Ignored);
+    }
+
+    match event.action.as_str() {
+        "edited" | "closed" | "opened" => refresh_milestone(&mut tx, &event.milestone).await?,
+        "deleted" => {
+            // Tasks stay in their cycles; only the link to GitHub goes away.
+            sqlx::query("UPDATE tasks SET github_milestone_id = NULL WHERE github_milestone_id = $1")
+                .bind(&event.milestone.node_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM github_milestones WHERE github_node_id = $1")
+                .bind(&event.milestone.node_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        _ => return Ok(Dispatch::Ignored),
+    }
+    tx.commit().await?;
+
+    Ok(Dispatch::Processed)
+}
@@ -0,0 +1,372 @@
+use crate::{
+    config::{AppConfig, GitLabConfig},
+    error::AppError,
+    forge::{
+        AuthorizeRequest, CreateRef, Dispatch, ForgeIdentity, ForgeIssue, ForgeKind, ForgeProvider, ForgeToken,
+        IssueUpdate, WebhookDelivery,
+    },
+    gitlab::{
+        encode_segment,
+        issues::GitLabIssue,
+        parse_project_path,
+        webhooks::{dispatch, DELIVERY_HEADER, EVENT_HEADER, TOKEN_HEADER},
+    },
+    state::SharedState,
+};
+use axum::{async_trait, http::HeaderMap};
+use oauth2::{basic::BasicClient, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RefreshToken, Scope};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+
+/// Scopes requested at sign-in: `api` covers reading and updating issues and
+/// creating branches, which GitLab has no narrower scope for.
+const SIGN_IN_SCOPES: &[&str] = &["read_user", "api"];
+
+/// GitLab.com or a self-managed GitLab instance.
+pub struct GitLabForge {
+    http_client: Client,
+    oauth_client: BasicClient,
+    api_url: String,
+    webhook_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabUser {
+    id: i64,
+    username: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+    email: Option<String>,
+    confirmed_at: Option<String>,
+}
+
+/// Error bodies carry a `message` that is a string or, for validation
+/// errors, an object of messages per field.
+#[derive(Deserialize)]
+struct GitLabError {
+    message: Value,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabBranch {
+    commit: GitLabCommit,
+}
+
+#[derive(Deserialize)]
+struct GitLabCommit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabMember {
+    id: i64,
+    username: String,
+}
+
+#[derive(Serialize)]
+struct NewBranch<'a> {
+    branch: &'a str,
+    #[serde(rename = "ref")]
+    git_ref: &'a str,
+}
+
+/// The issue edit API names fields differently from [`IssueUpdate`] and
+/// takes assignees by user ID.
+#[derive(Serialize)]
+struct GitLabIssueUpdate<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_event: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee_ids: Option<Vec<i64>>,
+}
+
+impl GitLabForge {
+    pub fn new(config: &AppConfig, gitlab: &GitLabConfig, http_client: Client) -> Result<Self, AppError> {
+        Ok(Self {
+            http_client,
+            oauth_client: config.gitlab_oauth_client(gitlab)?,
+            api_url: gitlab.api_url(),
+            webhook_token: gitlab.webhook_token.clone(),
+        })
+    }
+
+    fn project_url(&self, repository: &str) -> String {
+        format!("{}/projects/{}", self.api_url, encode_segment(repository))
+    }
+
+    fn request(&self, method: Method, url: String, access_token: Option<&str>) -> RequestBuilder {
+        let request = self.http_client.request(method, url);
+        match access_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: String, access_token: Option<&str>) -> Result<T, AppError> {
+        let response = self.request(Method::GET, url, access_token).send().await?;
+        Ok(check(response)?.json::<T>().await?)
+    }
+
+    /// GitLab assigns by user ID, so logins are looked up one by one; a login
+    /// without an account is dropped, as GitHub drops unknown assignees.
+    async fn user_ids(&self, logins: &[String], access_token: &str) -> Result<Vec<i64>, AppError> {
+        let mut ids = Vec::with_capacity(logins.len());
+        for login in logins {
+            let users = self
+                .get_json::<Vec<GitLabMember>>(
+                    format!("{}/users?username={}", self.api_url, encode_segment(login)),
+                    Some(access_token),
+                )
+                .await?;
+            ids.extend(users.into_iter().map(|user| user.id));
+        }
+        Ok(ids)
+    }
+}
+
+fn check(response: Response) -> Result<Response, AppError> {
+    if !response.status().is_success() {
+        return Err(AppError::BadRequest(format!("gitlab api error: {}", response.status())));
+    }
+    Ok(response)
+}
+
+#[async_trait]
+impl ForgeProvider for GitLabForge {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitLab
+    }
+
+    fn sign_in_scopes(&self) -> Vec<String> {
+        SIGN_IN_SCOPES.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    fn parse_repository(&self, repository: &str) -> Option<String> {
+        parse_project_path(repository)
+    }
+
+    fn authorize_url(&self, scopes: &[String]) -> AuthorizeRequest {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, csrf_token) = self
+            .oauth_client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(scopes.iter().cloned().map(Scope::new))
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        AuthorizeRequest {
+            url: url.to_string(),
+            state: csrf_token.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+        }
+    }
+
+    async fn exchange_code(&self, code: String, pkce_verifier: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_code(AuthorizationCode::new(code))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn refresh_token(&self, refresh_token: String) -> Result<ForgeToken, AppError> {
+        let token = self
+            .oauth_client
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(&self.http_client)
+            .await?;
+
+        Ok(token.into())
+    }
+
+    async fn fetch_identity(&self, access_token: &str) -> Result<ForgeIdentity, AppError> {
+        let user = self
+            .get_json::<GitLabUser>(format!("{}/user", self.api_url), Some(access_token))
+            .await?;
+
+        Ok(ForgeIdentity {
+            id: user.id,
+            login: user.username,
+            name: user.name,
+            avatar_url: user.avatar_url,
+            // GitLab only reports the confirmation of the primary address.
+            verified_email: user.email.filter(|_| user.confirmed_at.is_some()),
+        })
+    }
+
+    async fn default_branch_head(&self, repository: &str, access_token: &str) -> Result<(String, String), AppError> {
+        let project = self
+            .get_json::<GitLabProject>(self.project_url(repository), Some(access_token))
+            .await?;
+        let branch = self
+            .get_json::<GitLabBranch>(
+                format!(
+                    "{}/repository/branches/{}",
+                    self.project_url(repository),
+                    encode_segment(&project.default_branch)
+                ),
+                Some(access_token),
+            )
+            .await?;
+
+        Ok((project.default_branch, branch.commit.id))
+    }
+
+    async fn create_branch(
+        &self,
+        repository: &str,
+        access_token: &str,
+        name: &str,
+        sha: &str,
+    ) -> Result<CreateRef, AppError> {
+        let response = self
+            .request(
+                Method::POST,
+                format!("{}/repository/branches", self.project_url(repository)),
+                Some(access_token),
+            )
+            .json(&NewBranch { branch: name, git_ref: sha })
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => Ok(CreateRef::Created),
+            // GitLab answers "Branch already exists" with 400, but also an
+            // invalid name or ref, so only that message counts.
+            StatusCode::BAD_REQUEST => {
+                let message = match response.json::<GitLabError>().await {
+                    Ok(GitLabError {
+                        message: Value::String(message),
+                    }) => message,
+                    Ok(GitLabError { message }) => message.to_string(),
+                    Err(_) => String::new(),
+                };
+                if message.eq_ignore_ascii_case("branch already exists") {
+                    Ok(CreateRef::AlreadyExists)
+                } else {
+                    Err(AppError::BadRequest(format!("gitlab api error: 400 Bad Request: {}", message)))
+                }
+            }
+            status => Err(AppError::BadRequest(format!("gitlab api error: {}", status))),
+        }
+    }
+
+    async fn list_issues(
+        &self,
+        repository: &str,
+        page: u32,
+        per_page: u32,
+        access_token: Option<&str>,
+    ) -> Result<Vec<ForgeIssue>, AppError> {
+        let url = format!(
+            "{}/issues?state=all&order_by=updated_at&sort=desc&with_labels_details=true&per_page={}&page={}",
+            self.project_url(repository),
+            per_page,
+            page
+        );
+        let issues = self.get_json::<Vec<GitLabIssue>>(url, access_token).await?;
+        Ok(issues.into_iter().map(Into::into).collect())
+    }
+
+    async fn fetch_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: Option<&str>,
+    ) -> Result<ForgeIssue, AppError> {
+        // The single-issue endpoint has no label details; the list does.
+        let url = format!(
+            "{}/issues?iids[]={}&with_labels_details=true",
+            self.project_url(repository),
+            number
+        );
+        let issues = self.get_json::<Vec<GitLabIssue>>(url, access_token).await?;
+        issues.into_iter().next().map(Into::into).ok_or(AppError::NotFound)
+    }
+
+    async fn update_issue(
+        &self,
+        repository: &str,
+        number: i32,
+        access_token: &str,
+        update: &IssueUpdate,
+    ) -> Result<ForgeIssue, AppError> {
+        let assignee_ids = match &update.assignees {
+            Some(logins) => Some(self.user_ids(logins, access_token).await?),
+            None => None,
+        };
+        let body = GitLabIssueUpdate {
+            title: update.title.as_deref(),
+            description: update.body.as_deref(),
+            state_event: update.state.map(|state| if state == "closed" { "close" } else { "reopen" }),
+            labels: update.labels.as_ref().map(|labels| labels.join(",")),
+            assignee_ids,
+        };
+        let response = self
+            .request(
+                Method::PUT,
+                format!("{}/issues/{}", self.project_url(repository), number),
+                Some(access_token),
+            )
+            .json(&body)
+            .send()
+            .await?;
+        check(response)?;
+
+        self.fetch_issue(repository, number, Some(access_token)).await
+    }
+
+    async fn is_assignable(&self, repository: &str, login: &str, access_token: Option<&str>) -> Result<bool, AppError> {
+        let url = format!(
+            "{}/members/all?query={}",
+            self.project_url(repository),
+            encode_segment(login)
+        );
+        let members = self.get_json::<Vec<GitLabMember>>(url, access_token).await?;
+        Ok(members.iter().any(|member| member.username.eq_ignore_ascii_case(login)))
+    }
+
+    fn verify_webhook(&self, headers: &HeaderMap, _body: &[u8]) -> Result<WebhookDelivery, AppError> {
+        let secret = self
+            .webhook_token
+            .as_deref()
+            .ok_or_else(|| AppError::Forbidden("webhooks are not configured".into()))?;
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        // GitLab sends the configured secret token as is rather than signing
+        // the body.
+        let token = header(TOKEN_HEADER).unwrap_or_default();
+        if !bool::from(token.as_bytes().ct_eq(secret.as_bytes())) {
+            return Err(AppError::Unauthorized);
+        }
+
+        let required = |name: &str| {
+            header(name)
+                .map(str::to_string)
+                .ok_or_else(|| AppError::BadRequest(format!("missing {} header", name)))
+        };
+        Ok(WebhookDelivery {
+            event: required(EVENT_HEADER)?,
+            id: required(DELIVERY_HEADER)?,
+        })
+    }
+
+    async fn handle_webhook(&self, state: &SharedState, event: &str, payload: Value) -> Result<Dispatch, AppError> {
+        dispatch(state, self, event, payload).await
+    }
+}
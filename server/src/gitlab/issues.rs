@@ -0,0 +1,104 @@
+//! GitLab's issue payloads and their conversion into the forge types.
+//!
+//! GitLab identifies issues within a project by `iid`, which is what Keyflow
+//! stores as the issue number. The global IDs become `gid://gitlab/...`
+//! strings, matching what GitLab's GraphQL API reports as node IDs.
+
+use crate::forge::{ForgeAccount, ForgeIssue, ForgeLabel, ForgeMilestone};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct GitLabIssue {
+    pub id: i64,
+    pub iid: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub state: String,
+    pub web_url: String,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub labels: Vec<GitLabLabel>,
+    #[serde(default)]
+    pub assignees: Vec<GitLabAccount>,
+    pub milestone: Option<GitLabMilestone>,
+}
+
+/// Only issues requested with `with_labels_details=true` carry label
+/// objects; otherwise GitLab sends bare names.
+#[derive(Debug, Deserialize)]
+pub struct GitLabLabel {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitLabAccount {
+    pub id: i64,
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitLabMilestone {
+    pub id: i64,
+    pub iid: i32,
+    pub title: String,
+    pub state: String,
+    pub due_date: Option<NaiveDate>,
+}
+
+impl From<GitLabIssue> for ForgeIssue {
+    fn from(issue: GitLabIssue) -> Self {
+        Self {
+            node_id: format!("gid://gitlab/Issue/{}", issue.id),
+            number: issue.iid,
+            title: issue.title,
+            body: issue.description,
+            state: if issue.state == "opened" { "open" } else { "closed" }.to_string(),
+            state_reason: None,
+            html_url: issue.web_url,
+            updated_at: issue.updated_at,
+            labels: issue.labels.into_iter().map(Into::into).collect(),
+            assignees: issue.assignees.into_iter().map(Into::into).collect(),
+            milestone: issue.milestone.map(Into::into),
+            pull_request: None,
+        }
+    }
+}
+
+impl From<GitLabLabel> for ForgeLabel {
+    fn from(label: GitLabLabel) -> Self {
+        Self {
+            node_id: format!("gid://gitlab/ProjectLabel/{}", label.id),
+            name: label.name,
+            color: label.color.trim_start_matches('#').to_string(),
+        }
+    }
+}
+
+impl From<GitLabAccount> for ForgeAccount {
+    fn from(account: GitLabAccount) -> Self {
+        Self {
+            id: account.id,
+            login: account.username,
+            avatar_url: account.avatar_url,
+        }
+    }
+}
+
+impl From<GitLabMilestone> for ForgeMilestone {
+    fn from(milestone: GitLabMilestone) -> Self {
+        Self {
+            node_id: format!("gid://gitlab/Milestone/{}", milestone.id),
+            number: milestone.iid,
+            title: milestone.title,
+            state: if milestone.state == "active" { "open" } else { "closed" }.to_string(),
+            due_on: milestone
+                .due_date
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| Utc.from_utc_datetime(&date)),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+pub mod forge;
+pub mod issues;
+pub mod webhooks;
+
+/// Validates a `group/project` path (subgroups allowed) before it ends up in
+/// an API URL.
+pub fn parse_project_path(path: &str) -> Option<String> {
+    let path = path.trim().trim_matches('/');
+    let parts: Vec<&str> = path.split('/').collect();
+    let valid = |part: &&str| {
+        !part.is_empty()
+            && part.len() <= 255
+            && !part.starts_with(['-', '.'])
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    (parts.len() >= 2 && parts.iter().all(valid)).then(|| path.to_string())
+}
+
+/// Percent-encodes a value used as a single path segment, as GitLab expects
+/// for project paths (`group%2Fproject`) and branch names.
+pub fn encode_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,118 @@
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeKind, ForgeProvider, PullRequest, PullRequestHead},
+    github::{pull_requests::record_pull_request, sync::sync_issue_event, tokens::access_token_for},
+    state::SharedState,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::info;
+
+pub const TOKEN_HEADER: &str = "x-gitlab-token";
+pub const EVENT_HEADER: &str = "x-gitlab-event";
+pub const DELIVERY_HEADER: &str = "x-gitlab-event-uuid";
+
+#[derive(Deserialize)]
+struct WebhookProject {
+    path_with_namespace: String,
+}
+
+/// Issue hooks carry labels and assignees in a different shape than the
+/// API, so only the issue's number is taken and the issue itself is fetched.
+#[derive(Deserialize)]
+struct IssueHook {
+    project: WebhookProject,
+    object_attributes: IssueAttributes,
+}
+
+#[derive(Deserialize)]
+struct IssueAttributes {
+    iid: i32,
+}
+
+#[derive(Deserialize)]
+struct MergeRequestHook {
+    project: WebhookProject,
+    object_attributes: MergeRequestAttributes,
+}
+
+#[derive(Deserialize)]
+struct MergeRequestAttributes {
+    iid: i32,
+    title: String,
+    description: Option<String>,
+    url: String,
+    state: String,
+    action: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    work_in_progress: bool,
+    source_branch: String,
+}
+
+impl From<MergeRequestAttributes> for PullRequest {
+    fn from(merge_request: MergeRequestAttributes) -> Self {
+        let merged = merge_request.state == "merged";
+        Self {
+            number: merge_request.iid,
+            title: merge_request.title,
+            body: merge_request.description,
+            html_url: merge_request.url,
+            state: if merge_request.state == "opened" { "open" } else { "closed" }.to_string(),
+            draft: merge_request.draft || merge_request.work_in_progress,
+            merged,
+            merged_at: None,
+            head: PullRequestHead {
+                git_ref: merge_request.source_branch,
+            },
+        }
+    }
+}
+
+/// Routes a stored delivery to the handler for its `X-Gitlab-Event`.
+pub async fn dispatch(
+    state: &SharedState,
+    forge: &dyn ForgeProvider,
+    event: &str,
+    payload: Value,
+) -> Result<Dispatch, AppError> {
+    match event {
+        "Issue Hook" | "Confidential Issue Hook" => {
+            handle_issue(state, forge, serde_json::from_value(payload)?).await
+        }
+        "Merge Request Hook" => handle_merge_request(state, serde_json::from_value(payload)?).await,
+        _ => Ok(Dispatch::Ignored),
+    }
+}
+
+async fn handle_issue(state: &SharedState, forge: &dyn ForgeProvider, event: IssueHook) -> Result<Dispatch, AppError> {
+    let repository = event.project.path_with_namespace;
+    let access_token = access_token_for(state, ForgeKind::GitLab, &repository, None).await?;
+    let issue = forge
+        .fetch_issue(&repository, event.object_attributes.iid, access_token.as_deref())
+        .await?;
+
+    sync_issue_event(state, forge, &repository, issue).await
+}
+
+async fn handle_merge_request(state: &SharedState, event: MergeRequestHook) -> Result<Dispatch, AppError> {
+    let repository = event.project.path_with_namespace;
+    let action = event.object_attributes.action.clone().unwrap_or_default();
+    let pull_request = PullRequest::from(event.object_attributes);
+
+    let linked = record_pull_request(state, &repository, &pull_request).await?;
+    info!(
+        repository = %repository,
+        number = pull_request.number,
+        action = %action,
+        linked,
+        "processed gitlab merge request event"
+    );
+
+    Ok(if linked > 0 {
+        Dispatch::Processed
+    } else {
+        Dispatch::Ignored
+    })
+}
@@ -0,0 +1,176 @@
+use crate::{
+    error::AppError,
+    jobs::queue::{enqueue_at, Job},
+    models::Cycle,
+    routes::insert_cycle,
+    state::SharedState,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::PgPool;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Schedules the next rollover before sweeping, so the job keeps recurring
+/// whether or not this sweep succeeds.
+pub async fn run(state: &SharedState) -> Result<(), AppError> {
+    let interval = Duration::from_std(std::time::Duration::from_secs(state.config.cycle_rollover_interval_secs))
+        .unwrap_or_else(|_| Duration::days(1));
+    enqueue_at(&state.pool, &Job::CycleRollover, Utc::now() + interval).await?;
+
+    sweep(&state.pool).await
+}
+
+async fn sweep(pool: &PgPool) -> Result<(), AppError> {
+    let today = Utc::now().date_naive();
+
+    let due = sqlx::query_as::<_, Cycle>(
+        "SELECT * FROM cycles WHERE completed_at IS NULL AND ends_on <= $1 ORDER BY ends_on",
+    )
+    .bind(today)
+    .fetch_all(pool)
+    .await?;
+
+    // One project's failure must not hold up the others; the next sweep
+    // tries it again.
+    for cycle in due {
+        if let Err(err) = roll_over(pool, cycle.id).await {
+            warn!(cycle_id = %cycle.id, project_id = %cycle.project_id, error = %err, "cycle rollover failed");
+        }
+    }
+
+    ensure_upcoming_cycles(pool, today).await
+}
+
+/// Closes a finished cycle and carries every unfinished task into the cycle
+/// that follows it, creating that cycle if the project has none yet. Projects
+/// without a cycle length get no new cycle; their unfinished tasks go back to
+/// the backlog.
+async fn roll_over(pool: &PgPool, cycle_id: Uuid) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let Some(cycle) = sqlx::query_as::<_, Cycle>(
+        "SELECT * FROM cycles WHERE id = $1 AND completed_at IS NULL FOR UPDATE",
+    )
+    .bind(cycle_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let next = sqlx::query_as::<_, Cycle>(
+        "SELECT * FROM cycles WHERE project_id = $1 AND starts_on >= $2 ORDER BY starts_on LIMIT 1",
+    )
+    .bind(cycle.project_id)
+    .bind(cycle.ends_on)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let next = match next {
+        Some(next) => Some(next),
+        None => {
+            let length_days = sqlx::query_scalar::<_, Option<i32>>("SELECT cycle_length_days FROM projects WHERE id = $1")
+                .bind(cycle.project_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            match length_days {
+                Some(days) => Some(
+                    insert_cycle(&mut tx, cycle.project_id, cycle.ends_on, cycle.ends_on + Duration::days(i64::from(days)))
+                        .await?,
+                ),
+                None => None,
+            }
+        }
+    };
+    let next_id = next.as_ref().map(|next| next.id);
+
+    let carried = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE tasks SET cycle_id = $2
+        WHERE cycle_id = $1 AND status NOT IN ('done', 'canceled')
+        RETURNING id
+        "#,
+    )
+    .bind(cycle.id)
+    .bind(next_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO cycle_scope_changes (cycle_id, task_id, change)
+        SELECT $1, task_id, 'carried_out' FROM UNNEST($3::uuid[]) AS task_id
+        UNION ALL
+        SELECT $2, task_id, 'carried_in' FROM UNNEST($3::uuid[]) AS task_id WHERE $2::uuid IS NOT NULL
+        "#,
+    )
+    .bind(cycle.id)
+    .bind(next_id)
+    .bind(&carried)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE cycles SET completed_at = now() WHERE id = $1")
+        .bind(cycle.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    info!(
+        cycle_id = %cycle.id,
+        next_cycle_id = ?next_id,
+        carried_over = carried.len(),
+        "cycle rolled over"
+    );
+
+    Ok(())
+}
+
+/// Keeps one upcoming cycle queued for every project with automatic cycles,
+/// so tasks can be planned into the next cycle before the current one ends.
+/// A project that fails is logged and retried on the next sweep.
+async fn ensure_upcoming_cycles(pool: &PgPool, today: NaiveDate) -> Result<(), AppError> {
+    let projects = sqlx::query_as::<_, (Uuid, i32)>(
+        r#"
+        SELECT p.id, p.cycle_length_days
+        FROM projects p
+        WHERE p.cycle_length_days IS NOT NULL
+          AND NOT EXISTS (SELECT 1 FROM cycles c WHERE c.project_id = p.id AND c.starts_on > $1)
+          AND EXISTS (SELECT 1 FROM cycles c WHERE c.project_id = p.id)
+        "#,
+    )
+    .bind(today)
+    .fetch_all(pool)
+    .await?;
+
+    for (project_id, length_days) in projects {
+        if let Err(err) = queue_upcoming_cycle(pool, project_id, length_days, today).await {
+            warn!(project_id = %project_id, error = %err, "could not queue upcoming cycle");
+        }
+    }
+
+    Ok(())
+}
+
+async fn queue_upcoming_cycle(pool: &PgPool, project_id: Uuid, length_days: i32, today: NaiveDate) -> Result<(), AppError> {
+    let length = Duration::days(i64::from(length_days));
+    let mut tx = pool.begin().await?;
+    sqlx::query("SELECT id FROM projects WHERE id = $1 FOR UPDATE")
+        .bind(project_id)
+        .execute(&mut *tx)
+        .await?;
+    let (mut starts_on, mut ends_on) = sqlx::query_as::<_, (NaiveDate, NaiveDate)>(
+        "SELECT starts_on, ends_on FROM cycles WHERE project_id = $1 ORDER BY starts_on DESC LIMIT 1",
+    )
+    .bind(project_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Loops more than once only to catch up after downtime.
+    while starts_on <= today {
+        let cycle = insert_cycle(&mut tx, project_id, ends_on, ends_on + length).await?;
+        starts_on = cycle.starts_on;
+        ends_on = cycle.ends_on;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,377 @@
+use crate::{
+    error::AppError,
+    forge::{ForgeIssue, ForgeKind},
+    github::{
+        client::token_fingerprint,
+        graphql::fetch_issue_batch,
+        issues::{fetch_issue_page, IssuePage},
+        parse_repository,
+        pull_requests::link_pull_request,
+        sync::{link_issue_task, sync_task, SyncContext, SyncOutcome},
+        tokens::access_token_for,
+    },
+    models::GitHubImport,
+    state::SharedState,
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const ISSUES_PER_PAGE: u32 = 100;
+
+/// Runs a queued import. A failure is recorded on the import before the
+/// queue retries it; the retry picks the import up again where it stopped.
+pub async fn run(state: &SharedState, import_id: Uuid) -> Result<(), AppError> {
+    let Err(err) = run_import(state, import_id).await else {
+        return Ok(());
+    };
+
+    let result = sqlx::query("UPDATE github_imports SET status = 'failed', error = $2, finished_at = now() WHERE id = $1")
+        .bind(import_id)
+        .bind(err.to_string())
+        .execute(&state.pool)
+        .await;
+    if let Err(err) = result {
+        warn!(import_id = %import_id, error = %err, "could not record import failure");
+    }
+
+    Err(err)
+}
+
+/// GitHub imports with a token use GraphQL, which fetches labels, assignees
+/// and linked pull requests in the same request; anonymous imports fall back
+/// to the REST listing because GraphQL requires authentication. Other forges
+/// are paged through their provider.
+async fn run_import(state: &SharedState, import_id: Uuid) -> Result<(), AppError> {
+    // Only a retry finds the import running or failed; the queue never runs
+    // the same job twice at once.
+    let import = sqlx::query_as::<_, GitHubImport>(
+        r#"
+        UPDATE github_imports
+        SET status = 'running', error = NULL, finished_at = NULL, started_at = COALESCE(started_at, now())
+        WHERE id = $1 AND status IN ('queued', 'running', 'failed')
+        RETURNING *
+        "#,
+    )
+    .bind(import_id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some(import) = import else {
+        info!(import_id = %import_id, "import already finished or deleted");
+        return Ok(());
+    };
+
+    let kind = ForgeKind::parse(&import.forge)?;
+    let access_token = access_token_for(state, kind, &import.repository, import.requested_by).await?;
+    let ctx = SyncContext {
+        pool: &state.pool,
+        forge: state.forges.get(kind)?,
+        access_token: access_token.as_deref(),
+    };
+    match (kind, access_token.as_deref()) {
+        (ForgeKind::GitHub, Some(token)) => import_with_graphql(state, &ctx, &import, token).await?,
+        (ForgeKind::GitHub, None) => import_with_rest(state, &ctx, &import).await?,
+        _ => import_with_provider(state, &ctx, &import).await?,
+    }
+
+    sqlx::query("UPDATE github_imports SET status = 'completed', finished_at = now() WHERE id = $1")
+        .bind(import.id)
+        .execute(&state.pool)
+        .await?;
+    info!(import_id = %import.id, forge = %import.forge, repository = %import.repository, "issue import completed");
+
+    Ok(())
+}
+
+/// Pages through the repository's issues over GraphQL, recording the cursor
+/// after every page. A retried import resumes from its own cursor, and when
+/// the previous import of the same repository failed, a new one resumes from
+/// that import's last cursor instead of starting over.
+async fn import_with_graphql(
+    state: &SharedState,
+    ctx: &SyncContext<'_>,
+    import: &GitHubImport,
+    access_token: &str,
+) -> Result<(), AppError> {
+    let (owner, name) = parse_repository(&import.repository)
+        .ok_or_else(|| AppError::BadRequest("repository must look like owner/name".into()))?;
+
+    let previous = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT status, graphql_cursor
+        FROM github_imports
+        WHERE project_id = $1 AND repository = $2 AND id <> $3
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(import.project_id)
+    .bind(&import.repository)
+    .bind(import.id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let mut cursor = import
+        .graphql_cursor
+        .clone()
+        .or_else(|| previous.and_then(|(status, cursor)| (status == "failed").then_some(cursor).flatten()));
+    if let Some(cursor) = &cursor {
+        info!(import_id = %import.id, cursor = %cursor, "resuming github import after failed attempt");
+    }
+
+    loop {
+        let batch = fetch_issue_batch(
+            &state.github,
+            &state.config.github_graphql_url(),
+            access_token,
+            owner,
+            name,
+            cursor.as_deref(),
+        )
+        .await?;
+        info!(
+            import_id = %import.id,
+            cost = batch.cost.cost,
+            remaining = batch.cost.remaining,
+            reset_at = %batch.cost.reset_at,
+            "fetched github issue batch"
+        );
+
+        let mut issues = Vec::with_capacity(batch.issues.len());
+        let mut pull_requests = Vec::new();
+        for fetched in batch.issues {
+            for (repository, pull_request) in fetched.pull_requests {
+                pull_requests.push((fetched.issue.node_id.clone(), repository, pull_request));
+            }
+            issues.push(fetched.issue);
+        }
+        let counts = import_issues(ctx, import, issues).await?;
+
+        let mut tx = state.pool.begin().await?;
+        for (node_id, repository, pull_request) in &pull_requests {
+            let task_id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM tasks WHERE github_node_id = $1")
+                .bind(node_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if let Some(task_id) = task_id {
+                link_pull_request(&mut tx, task_id, repository, pull_request).await?;
+            }
+        }
+        sqlx::query(
+            r#"
+            UPDATE github_imports
+            SET pages_fetched = pages_fetched + 1,
+                issues_seen = issues_seen + $2,
+                tasks_created = tasks_created + $3,
+                tasks_updated = tasks_updated + $4,
+                graphql_cursor = COALESCE($5, graphql_cursor),
+                graphql_cost = graphql_cost + $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(import.id)
+        .bind(counts.seen)
+        .bind(counts.created)
+        .bind(counts.updated)
+        .bind(&batch.end_cursor)
+        .bind(batch.cost.cost)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        if !batch.has_next_page || batch.end_cursor.is_none() {
+            return Ok(());
+        }
+        cursor = batch.end_cursor;
+    }
+}
+
+/// Pages through every issue of the repository, most recently updated first.
+/// Because of that ordering a `304 Not Modified` on the first page means
+/// nothing changed since the last sync, and the import can stop right away,
+/// but only when the project's last import of the repository got through
+/// every page and this one has not started paging yet. Otherwise later pages
+/// may never have been fetched, so paging continues from the cached next
+/// link. Validators are cached per project, so a repository imported into
+/// another project is fetched in full.
+async fn import_with_rest(state: &SharedState, ctx: &SyncContext<'_>, import: &GitHubImport) -> Result<(), AppError> {
+    let mut next = Some(format!(
+        "{}/repos/{}/issues?state=all&sort=updated&direction=desc&per_page={}",
+        state.config.github_api_url, import.repository, ISSUES_PER_PAGE
+    ));
+    let fingerprint = token_fingerprint(ctx.access_token);
+    let previous_status = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT status FROM github_imports
+        WHERE forge = 'github' AND repository = $1 AND project_id = $4 AND id <> $2 AND created_at <= $3
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(&import.repository)
+    .bind(import.id)
+    .bind(import.created_at)
+    .bind(import.project_id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let mut may_stop_early = previous_status.as_deref() == Some("completed")
+        && import.pages_fetched == 0
+        && import.pages_not_modified == 0;
+
+    while let Some(url) = next.take() {
+        let cached = sqlx::query_as::<_, (String, Option<String>)>(
+            "SELECT etag, next_url FROM github_etags WHERE url = $1 AND token_fingerprint = $2 AND project_id = $3",
+        )
+        .bind(&url)
+        .bind(&fingerprint)
+        .bind(import.project_id)
+        .fetch_optional(&state.pool)
+        .await?;
+        let cached_etag = cached.as_ref().map(|(etag, _)| etag.as_str());
+
+        match fetch_issue_page(&state.github, &url, ctx.access_token, cached_etag).await? {
+            IssuePage::NotModified => {
+                sqlx::query("UPDATE github_imports SET pages_not_modified = pages_not_modified + 1 WHERE id = $1")
+                    .bind(import.id)
+                    .execute(&state.pool)
+                    .await?;
+                if !may_stop_early {
+                    next = cached.and_then(|(_, next_url)| next_url);
+                }
+            }
+            IssuePage::Fetched {
+                issues,
+                etag,
+                next_url,
+            } => {
+                let issues = issues.into_iter().filter(|issue| issue.pull_request.is_none()).collect();
+                let counts = import_issues(ctx, import, issues).await?;
+
+                let mut tx = state.pool.begin().await?;
+                if let Some(etag) = etag {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO github_etags (url, token_fingerprint, project_id, etag, next_url)
+                        VALUES ($1, $4, $5, $2, $3)
+                        ON CONFLICT (url, token_fingerprint, project_id) DO UPDATE
+                        SET etag = EXCLUDED.etag, next_url = EXCLUDED.next_url, fetched_at = now()
+                        "#,
+                    )
+                    .bind(&url)
+                    .bind(etag)
+                    .bind(&next_url)
+                    .bind(&fingerprint)
+                    .bind(import.project_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                sqlx::query(
+                    r#"
+                    UPDATE github_imports
+                    SET pages_fetched = pages_fetched + 1,
+                        issues_seen = issues_seen + $2,
+                        tasks_created = tasks_created + $3,
+                        tasks_updated = tasks_updated + $4
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(import.id)
+                .bind(counts.seen)
+                .bind(counts.created)
+                .bind(counts.updated)
+                .execute(&mut *tx)
+                .await?;
+                tx.commit().await?;
+
+                next = next_url;
+            }
+        }
+        may_stop_early = false;
+    }
+
+    Ok(())
+}
+
+/// Pages through the repository's issues with the forge's own listing.
+async fn import_with_provider(state: &SharedState, ctx: &SyncContext<'_>, import: &GitHubImport) -> Result<(), AppError> {
+    let mut page = 1;
+    loop {
+        let issues = ctx
+            .forge
+            .list_issues(&import.repository, page, ISSUES_PER_PAGE, ctx.access_token)
+            .await?;
+        let last_page = issues.len() < ISSUES_PER_PAGE as usize;
+        let issues = issues.into_iter().filter(|issue| issue.pull_request.is_none()).collect();
+        let counts = import_issues(ctx, import, issues).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE github_imports
+            SET pages_fetched = pages_fetched + 1,
+                issues_seen = issues_seen + $2,
+                tasks_created = tasks_created + $3,
+                tasks_updated = tasks_updated + $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(import.id)
+        .bind(counts.seen)
+        .bind(counts.created)
+        .bind(counts.updated)
+        .execute(&state.pool)
+        .await?;
+
+        if last_page {
+            return Ok(());
+        }
+        page += 1;
+    }
+}
+
+struct PageCounts {
+    seen: i32,
+    created: i32,
+    updated: i32,
+}
+
+/// Links every issue to a task first, then lets the sync engine reconcile
+/// contents so local edits made since the last import are kept (or pushed)
+/// instead of being overwritten.
+async fn import_issues(
+    ctx: &SyncContext<'_>,
+    import: &GitHubImport,
+    issues: Vec<ForgeIssue>,
+) -> Result<PageCounts, AppError> {
+    let mut tx = ctx.pool.begin().await?;
+    let mut linked = Vec::new();
+    let mut created = 0;
+    for issue in issues {
+        let (task_id, is_new) = link_issue_task(
+            &mut tx,
+            ctx.forge.kind(),
+            import.project_id,
+            &import.repository,
+            import.requested_by,
+            &issue,
+        )
+        .await?;
+        if is_new {
+            created += 1;
+        }
+        linked.push((task_id, is_new, issue));
+    }
+    tx.commit().await?;
+
+    let seen = linked.len() as i32;
+    let mut updated = 0;
+    for (task_id, is_new, issue) in linked {
+        match sync_task(ctx, task_id, Some(issue)).await {
+            Ok(SyncOutcome::Synced { .. }) if !is_new => updated += 1,
+            Ok(_) => {}
+            Err(err) => {
+                warn!(task_id = %task_id, error = %err, "could not sync imported issue");
+            }
+        }
+    }
+
+    Ok(PageCounts { seen, created, updated })
+}
@@ -0,0 +1,50 @@
+use crate::{
+    error::AppError,
+    forge::ForgeKind,
+    github::{
+        sync::{sync_task, SyncContext, SyncOutcome},
+        tokens::access_token_for,
+    },
+    state::SharedState,
+};
+use tracing::info;
+use uuid::Uuid;
+
+/// Pushes a local edit to the linked issue (and pulls anything changed there
+/// meanwhile) after the request that made the edit has returned. The change
+/// is pushed with the editor's own token for that forge when they have one.
+pub async fn run(state: &SharedState, task_id: Uuid, actor_id: Option<Uuid>) -> Result<(), AppError> {
+    if let SyncOutcome::Synced {
+        pulled,
+        pushed,
+        conflicts,
+    } = run_sync(state, task_id, actor_id).await?
+    {
+        if conflicts > 0 {
+            info!(task_id = %task_id, pulled, pushed, conflicts, "issue sync resolved conflicting edits");
+        }
+    }
+    Ok(())
+}
+
+async fn run_sync(state: &SharedState, task_id: Uuid, actor_id: Option<Uuid>) -> Result<SyncOutcome, AppError> {
+    let linked = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT forge, github_repository FROM tasks WHERE id = $1",
+    )
+    .bind(task_id)
+    .fetch_optional(&state.pool)
+    .await?;
+    let Some((forge, Some(repository))) = linked else {
+        return Ok(SyncOutcome::NotLinked);
+    };
+
+    let forge = state.forges.get(ForgeKind::parse(&forge)?)?;
+    let access_token = access_token_for(state, forge.kind(), &repository, actor_id).await?;
+    let ctx = SyncContext {
+        pool: &state.pool,
+        forge,
+        access_token: access_token.as_deref(),
+    };
+
+    sync_task(&ctx, task_id, None).await
+}
@@ -0,0 +1,51 @@
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeKind},
+    state::SharedState,
+};
+use serde_json::Value;
+use tracing::warn;
+
+/// Processes a stored delivery after the webhook has been acknowledged, since
+/// forges give up on deliveries that take longer than ten seconds to answer.
+/// Every attempt's outcome is recorded on the delivery; a failed one is
+/// retried by the queue.
+pub async fn run(state: &SharedState, delivery_id: &str) -> Result<(), AppError> {
+    let result = process_delivery(state, delivery_id).await;
+    let (status, error) = match &result {
+        Ok(Dispatch::Processed) => ("processed", None),
+        Ok(Dispatch::Ignored) => ("ignored", None),
+        Err(err) => ("failed", Some(err.to_string())),
+    };
+
+    let recorded = sqlx::query(
+        r#"
+        UPDATE github_webhook_deliveries
+        SET status = $2, error = $3, processed_at = now()
+        WHERE delivery_id = $1
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(status)
+    .bind(error)
+    .execute(&state.pool)
+    .await;
+    if let Err(err) = recorded {
+        warn!(delivery_id = %delivery_id, error = %err, "could not record webhook outcome");
+    }
+
+    result.map(|_| ())
+}
+
+async fn process_delivery(state: &SharedState, delivery_id: &str) -> Result<Dispatch, AppError> {
+    let (forge, event, payload) = sqlx::query_as::<_, (String, String, Value)>(
+        "SELECT forge, event, payload FROM github_webhook_deliveries WHERE delivery_id = $1",
+    )
+    .bind(delivery_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let forge = state.forges.get(ForgeKind::parse(&forge)?)?;
+    forge.handle_webhook(state, &event, payload).await
+}
@@ -0,0 +1,41 @@
+use crate::{error::AppError, github::tokens::reseal_tokens, state::SharedState};
+use tracing::{info, warn};
+
+mod cycles;
+mod github_import;
+mod github_sync;
+mod github_webhooks;
+pub mod queue;
+mod worker;
+
+pub use queue::{enqueue, Job};
+pub use worker::Workers;
+
+/// Starts the worker pool and queues the recurring jobs. Individual jobs
+/// log and retry their failures rather than propagate them; only failing to
+/// reach the queue at startup is fatal.
+pub async fn spawn_background_jobs(state: SharedState) -> Result<Workers, AppError> {
+    tokio::spawn(reseal_stored_tokens(state.clone()));
+    enqueue(&state.pool, &Job::CycleRollover).await?;
+
+    Ok(Workers::spawn(state.clone(), state.config.job_workers))
+}
+
+async fn perform(state: &SharedState, job: &Job) -> Result<(), AppError> {
+    match job {
+        Job::Import { import_id } => github_import::run(state, *import_id).await,
+        Job::IssueSync { task_id, actor_id } => github_sync::run(state, *task_id, *actor_id).await,
+        Job::WebhookDelivery { delivery_id } => github_webhooks::run(state, delivery_id).await,
+        Job::CycleRollover => cycles::run(state).await,
+    }
+}
+
+/// One-off pass after startup that moves stored tokens onto the primary
+/// encryption key.
+async fn reseal_stored_tokens(state: SharedState) {
+    match reseal_tokens(&state).await {
+        Ok(0) => {}
+        Ok(count) => info!(count, "re-encrypted github tokens with the primary key"),
+        Err(err) => warn!(error = %err, "could not re-encrypt github tokens"),
+    }
+}
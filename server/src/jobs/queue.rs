@@ -0,0 +1,332 @@
+//! The job queue itself: a `jobs` table that workers claim rows from with
+//! `FOR UPDATE SKIP LOCKED`, so any number of workers (and processes) can
+//! share it without handing the same job out twice.
+//!
+//! A finished job is deleted. A failed one is queued again after an
+//! exponential backoff until it runs out of attempts, and then stays behind
+//! as `dead` for an admin to look at and retry.
+
+use crate::{error::AppError, models::JobRecord};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgExecutor, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A running job whose heartbeat is older than this is assumed to have lost
+/// its worker and is handed out again.
+const STALE_AFTER: Duration = Duration::from_secs(300);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(15);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(3600);
+
+/// Work that can be queued. Stored as JSON, tagged with its `kind`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    Import { import_id: Uuid },
+    /// Pushes local edits to the linked issue, with the editor's token when
+    /// there is one.
+    IssueSync { task_id: Uuid, actor_id: Option<Uuid> },
+    WebhookDelivery { delivery_id: String },
+    CycleRollover,
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::Import { .. } => "import",
+            Job::IssueSync { .. } => "issue_sync",
+            Job::WebhookDelivery { .. } => "webhook_delivery",
+            Job::CycleRollover => "cycle_rollover",
+        }
+    }
+
+    /// Jobs with the same key are queued only once: a sync already waiting
+    /// will pick up later edits too.
+    fn unique_key(&self) -> String {
+        match self {
+            Job::Import { import_id } => format!("import:{}", import_id),
+            Job::IssueSync { task_id, .. } => format!("issue_sync:{}", task_id),
+            Job::WebhookDelivery { delivery_id } => format!("webhook_delivery:{}", delivery_id),
+            Job::CycleRollover => "cycle_rollover".to_string(),
+        }
+    }
+
+    /// Recurring jobs queue their next run before doing any work, so a failed
+    /// run is dropped instead of being retried or kept as dead; the next run
+    /// is the retry.
+    fn recurs(&self) -> bool {
+        matches!(self, Job::CycleRollover)
+    }
+
+    fn max_attempts(&self) -> i32 {
+        match self {
+            Job::Import { .. } => 3,
+            Job::CycleRollover => 1,
+            Job::IssueSync { .. } | Job::WebhookDelivery { .. } => 8,
+        }
+    }
+}
+
+/// A job a worker holds. `attempts` includes the current one.
+pub struct ClaimedJob {
+    pub id: Uuid,
+    pub job: Job,
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+/// Queues a job to run as soon as a worker is free. Pass a transaction to
+/// queue it only if the rest of the change commits. Returns false when the
+/// same job is already waiting.
+pub async fn enqueue<'e>(executor: impl PgExecutor<'e>, job: &Job) -> Result<bool, AppError> {
+    enqueue_at(executor, job, Utc::now()).await
+}
+
+pub async fn enqueue_at<'e>(executor: impl PgExecutor<'e>, job: &Job, run_at: DateTime<Utc>) -> Result<bool, AppError> {
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO jobs (kind, payload, unique_key, max_attempts, run_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (unique_key) WHERE unique_key IS NOT NULL AND status = 'queued' DO NOTHING
+        "#,
+    )
+    .bind(job.kind())
+    .bind(serde_json::to_value(job)?)
+    .bind(job.unique_key())
+    .bind(job.max_attempts())
+    .bind(run_at)
+    .execute(executor)
+    .await?;
+
+    Ok(inserted.rows_affected() > 0)
+}
+
+/// Takes the job that has been due longest, if any. Jobs abandoned by a
+/// worker that died count that run as an attempt; those that have none left
+/// are buried instead of being handed out again.
+pub async fn claim(pool: &PgPool) -> Result<Option<ClaimedJob>, AppError> {
+    bury_exhausted(pool).await?;
+
+    let claimed = sqlx::query_as::<_, (Uuid, Value, i32, i32)>(
+        r#"
+        UPDATE jobs
+        SET status = 'running', attempts = attempts + 1, locked_at = now(), updated_at = now()
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = 'queued' AND run_at <= now())
+               OR (status = 'running'
+                   AND locked_at < now() - make_interval(secs => $1)
+                   AND attempts < max_attempts)
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, payload, attempts, max_attempts
+        "#,
+    )
+    .bind(STALE_AFTER.as_secs_f64())
+    .fetch_optional(pool)
+    .await?;
+    let Some((id, payload, attempts, max_attempts)) = claimed else {
+        return Ok(None);
+    };
+
+    match serde_json::from_value::<Job>(payload) {
+        Ok(job) => Ok(Some(ClaimedJob {
+            id,
+            job,
+            attempts,
+            max_attempts,
+        })),
+        // Queued by a newer version, or by one that dropped the job kind.
+        Err(err) => {
+            bury(pool, id, &format!("unknown job: {}", err)).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Buries abandoned jobs that used up their attempts, most likely by taking
+/// their worker down with them every time.
+async fn bury_exhausted(pool: &PgPool) -> Result<(), AppError> {
+    let recurring = [Job::CycleRollover.kind()];
+    sqlx::query(
+        r#"
+        DELETE FROM jobs
+        WHERE status = 'running'
+          AND locked_at < now() - make_interval(secs => $1)
+          AND attempts >= max_attempts
+          AND kind = ANY($2)
+        "#,
+    )
+    .bind(STALE_AFTER.as_secs_f64())
+    .bind(&recurring[..])
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'dead', locked_at = NULL, last_error = 'abandoned by its worker', updated_at = now()
+        WHERE status = 'running'
+          AND locked_at < now() - make_interval(secs => $1)
+          AND attempts >= max_attempts
+        "#,
+    )
+    .bind(STALE_AFTER.as_secs_f64())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Keeps a long-running job from being taken for abandoned.
+pub async fn heartbeat(pool: &PgPool, id: Uuid) -> Result<(), AppError> {
+    sqlx::query("UPDATE jobs SET locked_at = now() WHERE id = $1 AND status = 'running'")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn complete(pool: &PgPool, id: Uuid) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM jobs WHERE id = $1").bind(id).execute(pool).await?;
+    Ok(())
+}
+
+/// Queues the job again after a backoff, or marks it dead once it is out of
+/// attempts. Recurring jobs are dropped. A retry is dropped when the same job was queued meanwhile,
+/// since that run does the same work.
+pub async fn fail(pool: &PgPool, claimed: &ClaimedJob, error: &AppError) -> Result<(), AppError> {
+    if claimed.job.recurs() {
+        return complete(pool, claimed.id).await;
+    }
+    if claimed.attempts >= claimed.max_attempts {
+        return bury(pool, claimed.id, &error.to_string()).await;
+    }
+
+    let requeued = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'queued',
+            locked_at = NULL,
+            run_at = now() + make_interval(secs => $3),
+            last_error = $2,
+            updated_at = now()
+        WHERE id = $1
+          AND NOT EXISTS (
+              SELECT 1 FROM jobs q WHERE q.unique_key = jobs.unique_key AND q.status = 'queued'
+          )
+        "#,
+    )
+    .bind(claimed.id)
+    .bind(error.to_string())
+    .bind(retry_delay(claimed.attempts, error).as_secs_f64())
+    .execute(pool)
+    .await?;
+    if requeued.rows_affected() == 0 {
+        complete(pool, claimed.id).await?;
+    }
+
+    Ok(())
+}
+
+async fn bury(pool: &PgPool, id: Uuid, error: &str) -> Result<(), AppError> {
+    sqlx::query(
+        "UPDATE jobs SET status = 'dead', locked_at = NULL, last_error = $2, updated_at = now() WHERE id = $1",
+    )
+    .bind(id)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Doubles with every attempt, but never retries before GitHub said it
+/// would accept calls again.
+fn retry_delay(attempts: i32, error: &AppError) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1)).unwrap_or(0).min(16);
+    let backoff = FIRST_RETRY_DELAY.saturating_mul(1 << exponent).min(MAX_RETRY_DELAY);
+    match error {
+        AppError::GitHubRateLimited(wait) | AppError::GitHubUnavailable(wait) => backoff.max(*wait),
+        _ => backoff,
+    }
+}
+
+/// Jobs in `status`, oldest change first.
+pub async fn list_jobs(pool: &PgPool, status: &str, limit: i64) -> Result<Vec<JobRecord>, AppError> {
+    let jobs = sqlx::query_as::<_, JobRecord>(
+        "SELECT * FROM jobs WHERE status = $1 ORDER BY updated_at LIMIT $2",
+    )
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
+
+/// Gives a dead job a fresh set of attempts, starting now.
+pub async fn retry_job(pool: &PgPool, id: Uuid) -> Result<JobRecord, AppError> {
+    let retried = sqlx::query_as::<_, JobRecord>(
+        r#"
+        UPDATE jobs
+        SET status = 'queued', attempts = 0, run_at = now(), updated_at = now()
+        WHERE id = $1
+          AND status = 'dead'
+          AND NOT EXISTS (
+              SELECT 1 FROM jobs q WHERE q.unique_key = jobs.unique_key AND q.status = 'queued'
+          )
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(job) = retried {
+        return Ok(job);
+    }
+
+    let status = sqlx::query_scalar::<_, String>("SELECT status FROM jobs WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Err(match status.as_deref() {
+        None => AppError::NotFound,
+        Some("dead") => AppError::Conflict("the same job is already queued".into()),
+        Some(_) => AppError::Conflict("only dead jobs can be retried".into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=10)
+            .map(|attempt| retry_delay(attempt, &AppError::Internal).as_secs())
+            .collect();
+        assert_eq!(delays, vec![15, 30, 60, 120, 240, 480, 960, 1920, 3600, 3600]);
+        assert_eq!(retry_delay(i32::MAX, &AppError::Internal), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn retry_delay_waits_out_github_limits() {
+        let limited = AppError::GitHubRateLimited(Duration::from_secs(1800));
+        assert_eq!(retry_delay(1, &limited), Duration::from_secs(1800));
+        assert_eq!(retry_delay(10, &limited), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn jobs_round_trip_through_their_payload() {
+        let job = Job::IssueSync {
+            task_id: Uuid::nil(),
+            actor_id: None,
+        };
+        let payload = serde_json::to_value(&job).unwrap();
+        assert_eq!(payload["kind"], "issue_sync");
+        assert_eq!(serde_json::from_value::<Job>(payload).unwrap(), job);
+        assert_eq!(job.unique_key(), format!("issue_sync:{}", Uuid::nil()));
+    }
+}
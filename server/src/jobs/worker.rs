@@ -0,0 +1,105 @@
+use crate::{
+    error::AppError,
+    jobs::{
+        perform,
+        queue::{self, ClaimedJob},
+    },
+    state::SharedState,
+};
+use std::time::Duration;
+use tokio::{sync::watch, task::JoinHandle, time::sleep};
+use tracing::{info, warn};
+
+/// How long an idle worker waits before looking for due jobs again.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The worker pool of this process.
+pub struct Workers {
+    shutdown: watch::Sender<bool>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Workers {
+    pub fn spawn(state: SharedState, count: usize) -> Self {
+        let (shutdown, receiver) = watch::channel(false);
+        let handles = (0..count)
+            .map(|_| tokio::spawn(work(state.clone(), receiver.clone())))
+            .collect();
+
+        Self { shutdown, handles }
+    }
+
+    /// Stops claiming jobs and waits up to `timeout` for the running ones.
+    /// Jobs still running after that are abandoned; their heartbeat stops
+    /// and another process picks them up again.
+    pub async fn drain(self, timeout: Duration) {
+        let _ = self.shutdown.send(true);
+        let running = async {
+            for handle in self.handles {
+                let _ = handle.await;
+            }
+        };
+        match tokio::time::timeout(timeout, running).await {
+            Ok(_) => info!("background workers drained"),
+            Err(_) => warn!(timeout_secs = timeout.as_secs(), "abandoning background jobs still running"),
+        }
+    }
+}
+
+async fn work(state: SharedState, mut shutdown: watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        match queue::claim(&state.pool).await {
+            Ok(Some(claimed)) => {
+                run(&state, claimed).await;
+                continue;
+            }
+            Ok(None) => {}
+            Err(err) => warn!(error = %err, "could not claim background job"),
+        }
+
+        tokio::select! {
+            _ = sleep(POLL_INTERVAL) => {}
+            _ = shutdown.changed() => {}
+        }
+    }
+}
+
+/// Runs the job on its own task, so a panic fails the job rather than the
+/// worker, and keeps its lock fresh until it finishes.
+async fn run(state: &SharedState, claimed: ClaimedJob) {
+    let mut job = tokio::spawn({
+        let (state, job) = (state.clone(), claimed.job.clone());
+        async move { perform(&state, &job).await }
+    });
+    let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+
+    let result = loop {
+        tokio::select! {
+            finished = &mut job => break finished.unwrap_or(Err(AppError::Internal)),
+            _ = heartbeat.tick() => {
+                if let Err(err) = queue::heartbeat(&state.pool, claimed.id).await {
+                    warn!(job_id = %claimed.id, error = %err, "could not refresh background job lock");
+                }
+            }
+        }
+    };
+
+    let recorded = match &result {
+        Ok(()) => queue::complete(&state.pool, claimed.id).await,
+        Err(err) => {
+            warn!(
+                job_id = %claimed.id,
+                kind = claimed.job.kind(),
+                attempt = claimed.attempts,
+                max_attempts = claimed.max_attempts,
+                error = %err,
+                "background job failed"
+            );
+            queue::fail(&state.pool, &claimed, err).await
+        }
+    };
+    if let Err(err) = recorded {
+        warn!(job_id = %claimed.id, error = %err, "could not record background job outcome");
+    }
+}
@@ -1,18 +1,27 @@
 mod config;
 mod db;
 mod error;
+mod forge;
+mod gitea;
+mod github;
+mod gitlab;
+mod jobs;
 mod models;
 mod routes;
 mod session;
 mod state;
 mod security;
+#[cfg(test)]
+mod test_support;
 
 use crate::{
     config::AppConfig,
     db::init_pool,
     error::AppError,
+    github::app::GitHubApp,
+    jobs::spawn_background_jobs,
     routes::build_router,
-    security::rate_limit::RateLimiter,
+    security::{rate_limit::RateLimiter, token_cipher::TokenCipher},
     session::SessionSigner,
     state::AppState,
 };
@@ -43,20 +52,45 @@ async fn main() -> Result<(), AppError> {
     sqlx::migrate!("./migrations").run(&pool).await?;
 
     let session_signer = SessionSigner::new(&config.session_signing_keys)?;
-    let oauth_client = config.oauth_client()?;
-    let http_client = reqwest::Client::builder()
+    let token_cipher = TokenCipher::new(&config.token_encryption_keys)?;
+    let mut http_client = reqwest::Client::builder()
         .user_agent("keyflow-server")
-        .timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(10));
+    if let Some(bundle) = &config.github_ca_bundle {
+        let certificates = reqwest::Certificate::from_pem_bundle(bundle)
+            .map_err(|e| AppError::Config(format!("invalid GITHUB_CA_CERT_PATH bundle: {}", e)))?;
+        for certificate in certificates {
+            http_client = http_client.add_root_certificate(certificate);
+        }
+    }
+    let http_client = http_client
         .build()
         .map_err(|e| AppError::Config(format!("http client init error: {}", e)))?;
+    let github_app = config
+        .github_app
+        .as_ref()
+        .map(|app| GitHubApp::new(app.app_id, &app.private_key_pem))
+        .transpose()?;
 
     let auth_rate_limiter = RateLimiter::new(
         config.auth_rate_limit_per_minute,
         config.auth_rate_limit_burst,
     );
+    let webhook_rate_limiter = RateLimiter::new(config.webhook_rate_limit_per_minute, 0);
 
-    let shared_state =
-        AppState::new(pool, session_signer, oauth_client, http_client, config.clone(), auth_rate_limiter).shared();
+    let shared_state = AppState::new(
+        pool,
+        session_signer,
+        token_cipher,
+        http_client,
+        github_app,
+        config.clone(),
+        auth_rate_limiter,
+        webhook_rate_limiter,
+    )?
+    .shared();
+
+    let workers = spawn_background_jobs(shared_state.clone()).await?;
 
     let cors = build_cors(&config)?;
 
@@ -91,10 +125,7 @@ async fn main() -> Result<(), AppError> {
         ))
         .layer(SetResponseHeaderLayer::if_not_present(
             CONTENT_SECURITY_POLICY,
-            HeaderValue::from_static(
-                "default-src 'self'; img-src 'self' https://avatars.githubusercontent.com data:; \
-                 connect-src 'self' https://api.github.com; script-src 'self'; style-src 'self' 'unsafe-inline'",
-            ),
+            content_security_policy(&config)?,
         ));
 
     let app = build_router(shared_state).layer(
@@ -108,12 +139,42 @@ async fn main() -> Result<(), AppError> {
     info!("listening on {}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .map_err(|e| AppError::Config(format!("server error: {}", e)))?;
 
+    info!("shutting down; draining background jobs");
+    workers
+        .drain(Duration::from_secs(config.job_drain_timeout_secs))
+        .await;
+
     Ok(())
 }
 
+/// Resolves on Ctrl-C, or on SIGTERM from a process manager.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 fn init_tracing() {
     let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
         tracing_subscriber::EnvFilter::new("info,tower_http=info,sqlx=warn")
@@ -141,3 +202,25 @@ fn build_cors(config: &AppConfig) -> Result<CorsLayer, AppError> {
         .allow_headers(AllowHeaders::any())
         .allow_credentials(true))
 }
+
+/// Avatars and browser-side API calls go to whichever GitHub (and GitLab or
+/// Gitea) the server is configured for, so the policy is built from those hosts.
+fn content_security_policy(config: &AppConfig) -> Result<HeaderValue, AppError> {
+    let api_origin = reqwest::Url::parse(&config.github_api_url)
+        .map(|url| url.origin().ascii_serialization())
+        .map_err(|e| AppError::Config(format!("invalid GITHUB_API_URL: {}", e)))?;
+    let mut img_origins = config.github_avatar_origins();
+    if let Some(gitlab) = &config.gitlab {
+        img_origins.extend(gitlab.avatar_origins());
+    }
+    if let Some(gitea) = &config.gitea {
+        img_origins.push(gitea.url.clone());
+    }
+    let policy = format!(
+        "default-src 'self'; img-src 'self' {} data:; connect-src 'self' {}; script-src 'self'; style-src 'self' 'unsafe-inline'",
+        img_origins.join(" "),
+        api_origin
+    );
+
+    HeaderValue::from_str(&policy).map_err(|e| AppError::Config(format!("invalid content security policy: {}", e)))
+}
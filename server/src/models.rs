@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Serialize;
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -6,13 +6,255 @@ use uuid::Uuid;
 #[derive(Clone, Debug, FromRow, Serialize)]
 pub struct User {
     pub id: Uuid,
-    pub github_id: i64,
+    pub github_id: Option<i64>,
+    pub gitlab_id: Option<i64>,
+    pub gitea_id: Option<i64>,
     pub login: String,
     pub name: Option<String>,
     pub avatar_url: Option<String>,
     pub email: Option<String>,
+    pub email_verified: bool,
     pub role: String,
+    /// False for placeholders created from issue assignees until that
+    /// person signs in.
+    pub claimed: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Project {
+    pub id: Uuid,
+    pub key: String,
+    pub name: String,
+    pub task_counter: i32,
+    pub cycle_length_days: Option<i32>,
+    pub pr_opened_status: Option<String>,
+    pub pr_merged_status: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Task {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub number: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub status: String,
+    pub assignee_id: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub cycle_id: Option<Uuid>,
+    /// Forge of the linked issue, which the `github_*` fields describe.
+    pub forge: String,
+    pub github_node_id: Option<String>,
+    pub github_repository: Option<String>,
+    pub github_issue_number: Option<i32>,
+    pub github_url: Option<String>,
+    pub github_updated_at: Option<DateTime<Utc>>,
+    pub github_milestone_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct ChecklistItem {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub body: String,
+    pub position: i32,
+    pub completed: bool,
+    pub completed_by: Option<Uuid>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Comment {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub author_id: Option<Uuid>,
+    pub body: String,
+    pub github_comment_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Label {
+    pub id: Uuid,
+    pub name: String,
+    pub color: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskRelation {
+    pub id: Uuid,
+    pub kind: String,
+    pub source_task_id: Uuid,
+    pub target_task_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskEvent {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub kind: String,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskMerge {
+    pub id: Uuid,
+    pub source_task_id: Uuid,
+    pub target_task_id: Uuid,
+    pub merged_by: Option<Uuid>,
+    pub previous_status: String,
+    pub previous_completed_at: Option<DateTime<Utc>>,
+    pub moved_comment_ids: Vec<Uuid>,
+    pub added_label_ids: Vec<Uuid>,
+    pub moved_outgoing_relation_ids: Vec<Uuid>,
+    pub moved_incoming_relation_ids: Vec<Uuid>,
+    pub back_reference_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub reverted_at: Option<DateTime<Utc>>,
+    pub reverted_by: Option<Uuid>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskWatcher {
+    pub task_id: Uuid,
+    pub user_id: Uuid,
+    pub reason: String,
+    pub subscribed: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub task_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub kind: String,
+    pub data: serde_json::Value,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct Cycle {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub number: i32,
+    pub name: Option<String>,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct GitHubImport {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub forge: String,
+    pub repository: String,
+    pub status: String,
+    pub pages_fetched: i32,
+    pub pages_not_modified: i32,
+    pub issues_seen: i32,
+    pub tasks_created: i32,
+    pub tasks_updated: i32,
+    pub graphql_cursor: Option<String>,
+    /// GraphQL rate limit points spent by this import.
+    pub graphql_cost: i32,
+    pub error: Option<String>,
+    pub requested_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A background job as shown to admins; see `jobs::queue`.
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct JobRecord {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub unique_key: Option<String>,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskBranch {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub repository: String,
+    pub name: String,
+    pub base_branch: String,
+    pub base_sha: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct TaskCommit {
+    pub task_id: Uuid,
+    pub repository: String,
+    pub sha: String,
+    pub message: String,
+    pub url: String,
+    pub author_name: String,
+    pub author_login: Option<String>,
+    pub author_id: Option<Uuid>,
+    pub closes: bool,
+    pub committed_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct GitHubLabelMapping {
+    pub id: Uuid,
+    pub repository: String,
+    pub github_node_id: Option<String>,
+    pub github_name: String,
+    pub label_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, FromRow, Serialize)]
+pub struct GitHubMilestone {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub repository: String,
+    pub github_node_id: String,
+    pub number: i32,
+    pub title: String,
+    pub state: String,
+    pub due_on: Option<NaiveDate>,
+    pub cycle_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,76 @@
+use crate::{
+    error::AppError,
+    github::client::{BreakerStatus, Quota},
+    jobs::queue::{list_jobs as list_job_records, retry_job as retry_job_record},
+    models::JobRecord,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct GitHubClientStatus {
+    circuit_breaker: BreakerStatus,
+    quotas: Vec<Quota>,
+}
+
+/// Remaining GitHub API quota per token and resource, as last reported by
+/// GitHub, plus the state of the client's circuit breaker.
+pub async fn github_status(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+) -> Result<Json<GitHubClientStatus>, AppError> {
+    current_user.require_admin()?;
+
+    Ok(Json(GitHubClientStatus {
+        circuit_breaker: state.github.breaker_status(),
+        quotas: state.github.quotas(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct JobFilter {
+    /// `dead` (the default), `queued` or `running`.
+    status: Option<String>,
+    limit: Option<i64>,
+}
+
+/// Background jobs by status; by default the dead ones, which ran out of
+/// attempts and wait for someone to retry them.
+pub async fn list_jobs(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Query(filter): Query<JobFilter>,
+) -> Result<Json<Vec<JobRecord>>, AppError> {
+    current_user.require_admin()?;
+
+    let status = filter.status.as_deref().unwrap_or("dead");
+    if !matches!(status, "queued" | "running" | "dead") {
+        return Err(AppError::BadRequest("status must be queued, running or dead".into()));
+    }
+    let limit = filter.limit.unwrap_or(100).clamp(1, 500);
+
+    Ok(Json(list_job_records(&state.pool, status, limit).await?))
+}
+
+/// Queues a dead job again with a fresh set of attempts.
+pub async fn retry_job(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<JobRecord>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    current_user.require_admin()?;
+
+    Ok(Json(retry_job_record(&state.pool, job_id).await?))
+}
@@ -1,5 +1,10 @@
 use crate::{
     error::AppError,
+    forge::{ForgeIdentity, ForgeKind},
+    github::{
+        scopes::{granted_scopes, normalize_scopes, BASE_SCOPES, UPGRADABLE_SCOPES},
+        tokens::store_user_token,
+    },
     models::User,
     security::csrf::{ensure_csrf_cookie, expire_csrf_cookie, issue_csrf_cookie, verify_csrf},
     session::SessionClaims,
@@ -7,16 +12,12 @@ use crate::{
 };
 use axum::{
     async_trait,
-    extract::{ConnectInfo, FromRef, FromRequestParts, Query, State},
-    http::{request::Parts, HeaderMap},
+    extract::{ConnectInfo, FromRef, FromRequestParts, Path, Query, State},
+    http::{request::Parts, HeaderMap, StatusCode},
     response::{IntoResponse, Redirect},
     Json,
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
-use oauth2::{
-    AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope, TokenResponse, Url,
-};
-use reqwest::header::USER_AGENT;
 use serde::{Deserialize, Serialize};
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
@@ -33,6 +34,12 @@ pub struct OAuthCallback {
     state: String,
 }
 
+#[derive(Deserialize)]
+pub struct ScopeUpgrade {
+    /// Comma-separated scopes to add, e.g. `repo,read:org`.
+    scopes: String,
+}
+
 #[derive(Serialize)]
 pub struct SessionResponse {
     user: PublicUser,
@@ -47,40 +54,91 @@ pub struct PublicUser {
     name: Option<String>,
     avatar_url: Option<String>,
     email: Option<String>,
+    email_verified: bool,
     role: String,
 }
 
-pub async fn github_login(
+pub async fn login(
     State(state): State<SharedState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
     jar: CookieJar,
+    Path(kind): Path<ForgeKind>,
 ) -> Result<impl IntoResponse, AppError> {
     let client_ip = resolve_client_ip(addr, &headers);
     let limiter = state.auth_rate_limiter.clone();
     limiter.check(client_ip).await?;
 
-    let (auth_url, csrf_token, pkce_verifier) = build_auth_url(&state)?;
+    let forge = state.forges.get(kind)?;
+    let request = forge.authorize_url(&forge.sign_in_scopes());
     let jar = jar
-        .add(build_temp_cookie(OAUTH_STATE_COOKIE, csrf_token.secret(), state.config.cookie_secure))
-        .add(build_temp_cookie(OAUTH_PKCE_COOKIE, pkce_verifier.secret(), state.config.cookie_secure));
+        .add(build_temp_cookie(OAUTH_STATE_COOKIE, &request.state, state.config.cookie_secure))
+        .add(build_temp_cookie(OAUTH_PKCE_COOKIE, &request.pkce_verifier, state.config.cookie_secure));
 
-    info!(client_ip = %client_ip, "redirecting user to GitHub OAuth");
+    info!(client_ip = %client_ip, forge = forge.kind().as_str(), "redirecting user to forge OAuth");
 
-    Ok((jar, Redirect::temporary(auth_url.as_ref())))
+    Ok((jar, Redirect::temporary(&request.url)))
 }
 
-pub async fn github_callback(
+/// Sends a signed-in user back through GitHub authorization asking for extra
+/// scopes on top of those already granted. The callback stores the new token
+/// and its scopes like a regular sign-in.
+pub async fn github_upgrade(
+    current_user: CurrentUser,
     State(state): State<SharedState>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
     jar: CookieJar,
+    Query(query): Query<ScopeUpgrade>,
+) -> Result<impl IntoResponse, AppError> {
+    let client_ip = resolve_client_ip(addr, &headers);
+    let limiter = state.auth_rate_limiter.clone();
+    limiter.check(client_ip).await?;
+
+    let requested = normalize_scopes([query.scopes.as_str()]);
+    if requested.is_empty() {
+        return Err(AppError::BadRequest("no scopes requested".into()));
+    }
+    if let Some(unknown) = requested.iter().find(|scope| !UPGRADABLE_SCOPES.contains(&scope.as_str())) {
+        return Err(AppError::BadRequest(format!("scope {} cannot be requested", unknown)));
+    }
+
+    if current_user.user().github_id.is_none() {
+        return Err(AppError::BadRequest("only GitHub accounts can request additional scopes".into()));
+    }
+    let user_id = current_user.user().id;
+    let granted = granted_scopes(&state, user_id).await?;
+    let scopes = normalize_scopes(
+        BASE_SCOPES
+            .iter()
+            .copied()
+            .chain(granted.iter().map(String::as_str))
+            .chain(requested.iter().map(String::as_str)),
+    );
+
+    let request = state.forges.github().authorize_url(&scopes);
+    let jar = jar
+        .add(build_temp_cookie(OAUTH_STATE_COOKIE, &request.state, state.config.cookie_secure))
+        .add(build_temp_cookie(OAUTH_PKCE_COOKIE, &request.pkce_verifier, state.config.cookie_secure));
+
+    info!(user_id = %user_id, scopes = %scopes.join(","), "redirecting user to GitHub for additional scopes");
+
+    Ok((jar, Redirect::temporary(&request.url)))
+}
+
+pub async fn callback(
+    State(state): State<SharedState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Path(kind): Path<ForgeKind>,
     Query(query): Query<OAuthCallback>,
 ) -> Result<impl IntoResponse, AppError> {
     let client_ip = resolve_client_ip(addr, &headers);
     let limiter = state.auth_rate_limiter.clone();
     limiter.check(client_ip).await?;
 
+    let forge = state.forges.get(kind)?;
     let state_cookie = jar
         .get(OAUTH_STATE_COOKIE)
         .ok_or_else(|| AppError::BadRequest("missing oauth state".into()))?;
@@ -93,27 +151,21 @@ pub async fn github_callback(
         return Err(AppError::BadRequest("invalid oauth state".into()));
     }
 
-    let verifier = PkceCodeVerifier::new(pkce_cookie.value().to_string());
-
-    let token = state
-        .oauth_client
-        .exchange_code(AuthorizationCode::new(query.code))
-        .set_pkce_verifier(verifier)
-        .request_async(&state.http_client)
-        .await?;
+    let token = forge.exchange_code(query.code, pkce_cookie.value().to_string()).await?;
 
     let jar = jar
         .remove(expire_cookie(OAUTH_STATE_COOKIE, state.config.cookie_secure, true))
         .remove(expire_cookie(OAUTH_PKCE_COOKIE, state.config.cookie_secure, true));
 
-    let github_user = fetch_github_user(&state, token.access_token().secret()).await?;
-    let user = upsert_user(&state, &github_user).await?;
+    let identity = forge.fetch_identity(&token.access_token).await?;
+    let user = upsert_user(&state, kind, &identity).await?;
+    store_user_token(&state, kind, user.id, &token).await?;
 
     let session_token = state.session_signer.issue(user.id)?;
     let jar = jar.add(build_session_cookie(SESSION_COOKIE, &session_token, state.config.cookie_secure));
     let (jar, _csrf_token) = issue_csrf_cookie(jar, state.config.cookie_secure);
 
-    info!(user_id = %user.id, login = %user.login, client_ip = %client_ip, "user authenticated via GitHub");
+    info!(user_id = %user.id, login = %user.login, client_ip = %client_ip, forge = forge.kind().as_str(), "user authenticated");
 
     Ok((jar, Redirect::temporary(&state.config.frontend_origin)))
 }
@@ -138,7 +190,7 @@ pub async fn logout(
     let jar = jar
         .remove(expire_cookie(SESSION_COOKIE, state.config.cookie_secure, true))
         .remove(expire_csrf_cookie(state.config.cookie_secure));
-    Ok((jar, axum::http::StatusCode::NO_CONTENT))
+    Ok((jar, StatusCode::NO_CONTENT))
 }
 
 pub async fn current_session(
@@ -175,6 +227,7 @@ impl From<User> for PublicUser {
             name: value.name,
             avatar_url: value.avatar_url,
             email: value.email,
+            email_verified: value.email_verified,
             role: value.role,
         }
     }
@@ -249,7 +302,7 @@ async fn authenticate_from_cookies(
     Ok(CurrentUser { user, client_ip })
 }
 
-fn resolve_client_ip(addr: SocketAddr, headers: &HeaderMap) -> IpAddr {
+pub(super) fn resolve_client_ip(addr: SocketAddr, headers: &HeaderMap) -> IpAddr {
     if let Some(forwarded_for) = headers
         .get("x-forwarded-for")
         .and_then(|value| value.to_str().ok())
@@ -314,70 +367,47 @@ fn build_session_cookie(name: &'static str, value: &str, secure: bool) -> Cookie
         .finish()
 }
 
-fn build_auth_url(state: &SharedState) -> Result<(Url, CsrfToken, PkceCodeVerifier), AppError> {
-    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
-    let (auth_url, csrf_token) = state
-        .oauth_client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("read:user".to_string()))
-        .set_pkce_challenge(pkce_challenge)
-        .url();
-
-    Ok((auth_url, csrf_token, pkce_verifier))
-}
+/// Only a verified primary address is stored, and only while no account
+/// from another forge holds it; accounts are never merged by email alone.
+/// Without one the stored email and its verification state are left as they
+/// are. A placeholder created for the account as an issue assignee is
+/// claimed, so the person finds the tasks already assigned to them.
+async fn upsert_user(state: &AppState, kind: ForgeKind, identity: &ForgeIdentity) -> Result<User, AppError> {
+    let normalized_email = identity.verified_email.as_deref().map(str::to_lowercase);
 
-#[derive(Debug, Deserialize)]
-struct GitHubUser {
-    id: i64,
-    login: String,
-    name: Option<String>,
-    avatar_url: Option<String>,
-    email: Option<String>,
-}
-
-async fn fetch_github_user(state: &AppState, access_token: &str) -> Result<GitHubUser, AppError> {
-    let response = state
-        .http_client
-        .get("https://api.github.com/user")
-        .bearer_auth(access_token)
-        .header(USER_AGENT, "keyflow-server")
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(AppError::BadRequest(format!(
-            "github api error: {}",
-            response.status()
-        )));
-    }
-
-    Ok(response.json::<GitHubUser>().await?)
-}
-
-async fn upsert_user(state: &AppState, github: &GitHubUser) -> Result<User, AppError> {
-    let normalized_email = github.email.as_ref().map(|email| email.to_lowercase());
-
-    let user = sqlx::query_as::<_, User>(
+    let user = sqlx::query_as::<_, User>(&format!(
         r#"
-        INSERT INTO users (github_id, login, name, avatar_url, email)
-        VALUES ($1, $2, $3, $4, $5)
-        ON CONFLICT (github_id) DO UPDATE
+        WITH verified AS (
+            SELECT CASE
+                WHEN EXISTS (
+                    SELECT 1 FROM users
+                    WHERE LOWER(email) = $5 AND {column} IS DISTINCT FROM $1
+                ) THEN NULL
+                ELSE $5
+            END AS email
+        )
+        INSERT INTO users ({column}, login, name, avatar_url, email, email_verified)
+        SELECT $1, $2, $3, $4, verified.email, verified.email IS NOT NULL
+        FROM verified
+        ON CONFLICT ({column}) DO UPDATE
         SET login = EXCLUDED.login,
             name = EXCLUDED.name,
             avatar_url = EXCLUDED.avatar_url,
             email = COALESCE(EXCLUDED.email, users.email),
+            email_verified = EXCLUDED.email_verified OR users.email_verified,
+            claimed = true,
             updated_at = now()
         RETURNING *
         "#,
-    )
-    .bind(github.id)
-    .bind(&github.login)
-    .bind(&github.name)
-    .bind(&github.avatar_url)
+        column = kind.user_id_column(),
+    ))
+    .bind(identity.id)
+    .bind(&identity.login)
+    .bind(&identity.name)
+    .bind(&identity.avatar_url)
     .bind(normalized_email.as_deref())
     .fetch_one(&state.pool)
     .await?;
 
     Ok(user)
 }
-
@@ -0,0 +1,171 @@
+use crate::{
+    error::AppError,
+    forge::{CreateRef, ForgeKind},
+    github::{
+        app::installation_token_for,
+        branches::{is_valid_branch_name, render_branch_name},
+        git::can_push,
+        scopes::{granted_scopes, missing_scopes},
+        tokens::user_access_token,
+    },
+    models::TaskBranch,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateBranch {
+    /// Defaults to the repository of the task's linked issue. Otherwise it
+    /// must be one the project already imports issues from or links tasks to.
+    repository: Option<String>,
+}
+
+pub async fn list_branches(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskBranch>>, AppError> {
+    let branches = sqlx::query_as::<_, TaskBranch>(
+        "SELECT * FROM task_branches WHERE task_id = $1 ORDER BY created_at",
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(branches))
+}
+
+/// Creates a branch for the task off the repository's default branch, named
+/// from `BRANCH_NAME_TEMPLATE`, and links it to the task.
+pub async fn create_task_branch(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<CreateBranch>,
+) -> Result<Json<TaskBranch>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let user = current_user.user();
+
+    let (key, number, title, forge, linked_repository) =
+        sqlx::query_as::<_, (String, i32, String, String, Option<String>)>(
+            r#"
+            SELECT p.key || '-' || t.number, t.number, t.title, t.forge, t.github_repository
+            FROM tasks t
+            JOIN projects p ON p.id = t.project_id
+            WHERE t.id = $1
+            "#,
+        )
+        .bind(task_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    // A task without a linked issue has the default forge, GitHub.
+    let forge = state.forges.get(ForgeKind::parse(&forge)?)?;
+    let repository = body
+        .repository
+        .or_else(|| linked_repository.clone())
+        .ok_or_else(|| AppError::BadRequest("repository is required for tasks without a linked issue".into()))?;
+    let repository = forge
+        .parse_repository(&repository)
+        .ok_or_else(|| AppError::BadRequest(format!("{:?} is not a valid repository", repository)))?;
+    let is_project_repository = linked_repository.is_some_and(|linked| linked.eq_ignore_ascii_case(&repository))
+        || sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM github_imports i
+                JOIN tasks t ON t.project_id = i.project_id
+                WHERE t.id = $1 AND i.forge = $2 AND LOWER(i.repository) = LOWER($3)
+            ) OR EXISTS (
+                SELECT 1 FROM tasks other
+                JOIN tasks t ON t.project_id = other.project_id
+                WHERE t.id = $1 AND other.forge = $2 AND LOWER(other.github_repository) = LOWER($3)
+            )
+            "#,
+        )
+        .bind(task_id)
+        .bind(forge.kind().as_str())
+        .bind(&repository)
+        .fetch_one(&state.pool)
+        .await?;
+    if !is_project_repository {
+        return Err(AppError::BadRequest(format!("{} is not a repository of this project", repository)));
+    }
+
+    let branch_name = render_branch_name(&state.config.branch_name_template, &user.login, &key, number, &title);
+    if !is_valid_branch_name(&branch_name) {
+        return Err(AppError::BadRequest(format!("{:?} is not a valid branch name", branch_name)));
+    }
+
+    // On GitHub the branch is created as the user when their token may write
+    // to repositories. Otherwise it goes through the app installation, but
+    // only for users GitHub confirms can push there. GitLab tokens always
+    // carry the `api` scope, so the user's own token is used there.
+    let access_token = match forge.kind() {
+        ForgeKind::GitHub => {
+            let scope_required = || AppError::ScopeRequired(vec!["repo".to_string()]);
+            let user_token = user_access_token(&state, ForgeKind::GitHub, user.id)
+                .await?
+                .ok_or_else(scope_required)?;
+            if missing_scopes(&granted_scopes(&state, user.id).await?, &["repo"]).is_empty() {
+                user_token
+            } else if can_push(&state.github, &state.config.github_api_url, &repository, &user_token).await? {
+                installation_token_for(&state, &repository)
+                    .await?
+                    .ok_or_else(scope_required)?
+            } else {
+                return Err(scope_required());
+            }
+        }
+        kind => user_access_token(&state, kind, user.id)
+            .await?
+            .ok_or_else(|| AppError::Forbidden(format!("sign in with {} to create branches", kind.as_str())))?,
+    };
+
+    let (base_branch, base_sha) = forge.default_branch_head(&repository, &access_token).await?;
+    match forge.create_branch(&repository, &access_token, &branch_name, &base_sha).await? {
+        CreateRef::Created => {}
+        CreateRef::AlreadyExists => {
+            return Err(AppError::Conflict(format!(
+                "branch {} already exists in {}",
+                branch_name, repository
+            )));
+        }
+    }
+
+    // A stale row can remain for a branch deleted on the forge and now recreated.
+    let branch = sqlx::query_as::<_, TaskBranch>(
+        r#"
+        INSERT INTO task_branches (task_id, repository, name, base_branch, base_sha, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT ((LOWER(repository)), name) DO UPDATE
+        SET task_id = EXCLUDED.task_id,
+            base_branch = EXCLUDED.base_branch,
+            base_sha = EXCLUDED.base_sha,
+            created_by = EXCLUDED.created_by,
+            created_at = now()
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(&repository)
+    .bind(&branch_name)
+    .bind(&base_branch)
+    .bind(&base_sha)
+    .bind(user.id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(branch))
+}
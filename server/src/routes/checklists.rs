@@ -0,0 +1,245 @@
+use crate::{
+    error::AppError,
+    models::ChecklistItem,
+    routes::{
+        tasks::{fetch_task_view, insert_task, validate_title, TaskView},
+        watchers::auto_subscribe,
+        CurrentUser,
+    },
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ChecklistItemBody {
+    body: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderChecklist {
+    item_ids: Vec<Uuid>,
+}
+
+pub async fn list_items(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<ChecklistItem>>, AppError> {
+    let items = sqlx::query_as::<_, ChecklistItem>(
+        "SELECT * FROM task_checklist_items WHERE task_id = $1 ORDER BY position",
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(items))
+}
+
+pub async fn add_item(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<ChecklistItemBody>,
+) -> Result<Json<ChecklistItem>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let text = validate_body(&body.body)?;
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("SELECT id FROM tasks WHERE id = $1 FOR UPDATE")
+        .bind(task_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let item = sqlx::query_as::<_, ChecklistItem>(
+        r#"
+        INSERT INTO task_checklist_items (task_id, body, position)
+        SELECT $1, $2, COALESCE(MAX(position) + 1, 0)
+        FROM task_checklist_items
+        WHERE task_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(text)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(item))
+}
+
+pub async fn update_item(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, item_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<ChecklistItemBody>,
+) -> Result<Json<ChecklistItem>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let text = validate_body(&body.body)?;
+
+    let item = sqlx::query_as::<_, ChecklistItem>(
+        "UPDATE task_checklist_items SET body = $3 WHERE id = $2 AND task_id = $1 RETURNING *",
+    )
+    .bind(task_id)
+    .bind(item_id)
+    .bind(text)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(item))
+}
+
+pub async fn toggle_item(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, item_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ChecklistItem>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let item = sqlx::query_as::<_, ChecklistItem>(
+        r#"
+        UPDATE task_checklist_items
+        SET completed = NOT completed,
+            completed_by = CASE WHEN completed THEN NULL ELSE $3 END,
+            completed_at = CASE WHEN completed THEN NULL ELSE now() END
+        WHERE id = $2 AND task_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(item_id)
+    .bind(current_user.user().id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(item))
+}
+
+pub async fn reorder_items(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<ReorderChecklist>,
+) -> Result<Json<Vec<ChecklistItem>>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let mut tx = state.pool.begin().await?;
+    let existing: HashSet<Uuid> = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM task_checklist_items WHERE task_id = $1 FOR UPDATE",
+    )
+    .bind(task_id)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let requested: HashSet<Uuid> = body.item_ids.iter().copied().collect();
+    if requested.len() != body.item_ids.len() || requested != existing {
+        return Err(AppError::BadRequest(
+            "item_ids must list every checklist item of the task exactly once".into(),
+        ));
+    }
+
+    let mut items = sqlx::query_as::<_, ChecklistItem>(
+        r#"
+        UPDATE task_checklist_items AS i
+        SET position = o.position - 1
+        FROM UNNEST($2::uuid[]) WITH ORDINALITY AS o (id, position)
+        WHERE i.id = o.id AND i.task_id = $1
+        RETURNING i.*
+        "#,
+    )
+    .bind(task_id)
+    .bind(&body.item_ids)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    items.sort_by_key(|item| item.position);
+    Ok(Json(items))
+}
+
+pub async fn delete_item(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, item_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let result = sqlx::query("DELETE FROM task_checklist_items WHERE id = $2 AND task_id = $1")
+        .bind(task_id)
+        .bind(item_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Promotes a checklist item to a standalone task in the same project. The
+/// item is removed from the checklist once the task exists.
+pub async fn convert_item(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, item_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TaskView>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let mut tx = state.pool.begin().await?;
+    let (body, project_id) = sqlx::query_as::<_, (String, Uuid)>(
+        r#"
+        DELETE FROM task_checklist_items AS i
+        USING tasks AS t
+        WHERE i.id = $2 AND i.task_id = $1 AND t.id = i.task_id
+        RETURNING i.body, t.project_id
+        "#,
+    )
+    .bind(task_id)
+    .bind(item_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let user_id = current_user.user().id;
+    let title = validate_title(&body)?;
+    let task = insert_task(&mut tx, project_id, title, None, Some(user_id)).await?;
+    auto_subscribe(&mut tx, task.id, user_id, "creator").await?;
+    tx.commit().await?;
+
+    Ok(Json(fetch_task_view(&state.pool, task.id).await?))
+}
+
+fn validate_body(body: &str) -> Result<&str, AppError> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(AppError::BadRequest("checklist item must not be empty".into()));
+    }
+    Ok(body)
+}
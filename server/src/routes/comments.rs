@@ -0,0 +1,76 @@
+use crate::{
+    error::AppError,
+    models::Comment,
+    routes::{
+        watchers::{auto_subscribe, fan_out},
+        CurrentUser,
+    },
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateComment {
+    body: String,
+}
+
+pub async fn list_comments(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<Comment>>, AppError> {
+    let comments = sqlx::query_as::<_, Comment>(
+        "SELECT * FROM task_comments WHERE task_id = $1 ORDER BY created_at",
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(comments))
+}
+
+pub async fn create_comment(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<CreateComment>,
+) -> Result<Json<Comment>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let text = body.body.trim();
+    if text.is_empty() {
+        return Err(AppError::BadRequest("comment must not be empty".into()));
+    }
+
+    let author_id = current_user.user().id;
+    let mut tx = state.pool.begin().await?;
+    let comment = sqlx::query_as::<_, Comment>(
+        r#"
+        INSERT INTO task_comments (task_id, author_id, body)
+        SELECT id, $2, $3 FROM tasks WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(author_id)
+    .bind(text)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    auto_subscribe(&mut tx, task_id, author_id, "commenter").await?;
+    fan_out(&mut tx, task_id, Some(author_id), "comment_added", json!({ "comment_id": comment.id })).await?;
+    tx.commit().await?;
+
+    Ok(Json(comment))
+}
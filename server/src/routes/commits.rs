@@ -0,0 +1,21 @@
+use crate::{error::AppError, models::TaskCommit, routes::CurrentUser, state::SharedState};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use uuid::Uuid;
+
+pub async fn list_commits(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskCommit>>, AppError> {
+    let commits = sqlx::query_as::<_, TaskCommit>(
+        "SELECT * FROM task_commits WHERE task_id = $1 ORDER BY committed_at DESC",
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(commits))
+}
@@ -0,0 +1,369 @@
+use crate::{
+    error::AppError,
+    models::{Cycle, GitHubMilestone, Project},
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection};
+use uuid::Uuid;
+
+const DEFAULT_CYCLE_LENGTH_DAYS: i32 = 14;
+
+#[derive(Deserialize)]
+pub struct CreateCycle {
+    starts_on: NaiveDate,
+    length_days: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct CycleSettings {
+    /// `None` stops new cycles from being created automatically.
+    cycle_length_days: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct AssignCycle {
+    cycle_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct CycleAssignment {
+    task_id: Uuid,
+    cycle_id: Option<Uuid>,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct CycleProgress {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    cycle: Cycle,
+    /// Tasks currently in the cycle plus those carried out of it at rollover.
+    total_scope: i64,
+    /// Tasks added after the cycle started.
+    scope_added: i64,
+    scope_removed: i64,
+    completed: i64,
+    carried_in: i64,
+    carried_over: i64,
+}
+
+pub async fn list_cycles(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<Cycle>>, AppError> {
+    let cycles = sqlx::query_as::<_, Cycle>("SELECT * FROM cycles WHERE project_id = $1 ORDER BY number")
+        .bind(project_id)
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(cycles))
+}
+
+/// Starts cycles for a project. The chosen length is stored on the project so
+/// the rollover job keeps creating cycles of the same length afterwards.
+pub async fn create_cycle(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(project_id): Path<Uuid>,
+    Json(body): Json<CreateCycle>,
+) -> Result<Json<Cycle>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let length_days = validate_length(body.length_days.unwrap_or(DEFAULT_CYCLE_LENGTH_DAYS))?;
+    let ends_on = body.starts_on + Duration::days(i64::from(length_days));
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query("UPDATE projects SET cycle_length_days = $2 WHERE id = $1 RETURNING id")
+        .bind(project_id)
+        .bind(length_days)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let overlaps = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM cycles
+            WHERE project_id = $1 AND daterange(starts_on, ends_on) && daterange($2, $3)
+        )
+        "#,
+    )
+    .bind(project_id)
+    .bind(body.starts_on)
+    .bind(ends_on)
+    .fetch_one(&mut *tx)
+    .await?;
+    if overlaps {
+        return Err(AppError::BadRequest("cycle overlaps an existing cycle".into()));
+    }
+
+    let cycle = insert_cycle(&mut tx, project_id, body.starts_on, ends_on).await?;
+    tx.commit().await?;
+
+    Ok(Json(cycle))
+}
+
+pub async fn update_cycle_settings(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(project_id): Path<Uuid>,
+    Json(body): Json<CycleSettings>,
+) -> Result<Json<Project>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let length_days = body.cycle_length_days.map(validate_length).transpose()?;
+
+    let project = sqlx::query_as::<_, Project>(
+        "UPDATE projects SET cycle_length_days = $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(project_id)
+    .bind(length_days)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(project))
+}
+
+pub async fn assign_task_cycle(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<AssignCycle>,
+) -> Result<Json<CycleAssignment>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let mut tx = state.pool.begin().await?;
+    let (project_id, previous_cycle_id) = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+        "SELECT project_id, cycle_id FROM tasks WHERE id = $1 FOR UPDATE",
+    )
+    .bind(task_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    if let Some(cycle_id) = body.cycle_id {
+        let open = sqlx::query_scalar::<_, bool>(
+            "SELECT completed_at IS NULL FROM cycles WHERE id = $1 AND project_id = $2",
+        )
+        .bind(cycle_id)
+        .bind(project_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("cycle does not belong to the task's project".into()))?;
+        if !open {
+            return Err(AppError::BadRequest("cycle has already ended".into()));
+        }
+    }
+
+    move_task_to_cycle(&mut tx, task_id, previous_cycle_id, body.cycle_id).await?;
+    tx.commit().await?;
+
+    Ok(Json(CycleAssignment {
+        task_id,
+        cycle_id: body.cycle_id,
+    }))
+}
+
+pub async fn list_milestones(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<GitHubMilestone>>, AppError> {
+    let milestones = sqlx::query_as::<_, GitHubMilestone>(
+        "SELECT * FROM github_milestones WHERE project_id = $1 ORDER BY due_on NULLS LAST, title",
+    )
+    .bind(project_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(milestones))
+}
+
+/// Maps a GitHub milestone to a cycle and moves the open tasks whose issues
+/// carry the milestone into it.
+pub async fn assign_milestone_cycle(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(milestone_id): Path<Uuid>,
+    Json(body): Json<AssignCycle>,
+) -> Result<Json<GitHubMilestone>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let mut tx = state.pool.begin().await?;
+    let (project_id, previous_cycle_id) = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+        "SELECT project_id, cycle_id FROM github_milestones WHERE id = $1 FOR UPDATE",
+    )
+    .bind(milestone_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    if let Some(cycle_id) = body.cycle_id {
+        let open = sqlx::query_scalar::<_, bool>(
+            "SELECT completed_at IS NULL FROM cycles WHERE id = $1 AND project_id = $2",
+        )
+        .bind(cycle_id)
+        .bind(project_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("cycle does not belong to the milestone's project".into()))?;
+        if !open {
+            return Err(AppError::BadRequest("cycle has already ended".into()));
+        }
+    }
+
+    let milestone = sqlx::query_as::<_, GitHubMilestone>(
+        "UPDATE github_milestones SET cycle_id = $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(milestone_id)
+    .bind(body.cycle_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if previous_cycle_id != body.cycle_id {
+        let tasks = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+            r#"
+            SELECT id, cycle_id FROM tasks
+            WHERE project_id = $1 AND github_milestone_id = $2 AND status NOT IN ('done', 'canceled')
+            FOR UPDATE
+            "#,
+        )
+        .bind(project_id)
+        .bind(&milestone.github_node_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (task_id, cycle_id) in tasks {
+            move_task_to_cycle(&mut tx, task_id, cycle_id, body.cycle_id).await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(Json(milestone))
+}
+
+pub async fn cycle_progress(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(cycle_id): Path<Uuid>,
+) -> Result<Json<CycleProgress>, AppError> {
+    let progress = sqlx::query_as::<_, CycleProgress>(
+        r#"
+        SELECT c.*,
+               (SELECT COUNT(*) FROM tasks t WHERE t.cycle_id = c.id)
+                 + COALESCE(s.carried_over, 0) AS total_scope,
+               COALESCE(s.scope_added, 0) AS scope_added,
+               COALESCE(s.scope_removed, 0) AS scope_removed,
+               (SELECT COUNT(*) FROM tasks t WHERE t.cycle_id = c.id AND t.status = 'done') AS completed,
+               COALESCE(s.carried_in, 0) AS carried_in,
+               COALESCE(s.carried_over, 0) AS carried_over
+        FROM cycles c
+        LEFT JOIN LATERAL (
+            SELECT COUNT(DISTINCT task_id) FILTER (
+                       WHERE change = 'added' AND created_at >= c.starts_on::timestamptz
+                   ) AS scope_added,
+                   COUNT(DISTINCT task_id) FILTER (WHERE change = 'removed') AS scope_removed,
+                   COUNT(DISTINCT task_id) FILTER (WHERE change = 'carried_in') AS carried_in,
+                   COUNT(DISTINCT task_id) FILTER (WHERE change = 'carried_out') AS carried_over
+            FROM cycle_scope_changes
+            WHERE cycle_id = c.id
+        ) s ON true
+        WHERE c.id = $1
+        "#,
+    )
+    .bind(cycle_id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(progress))
+}
+
+pub(crate) async fn insert_cycle(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+) -> Result<Cycle, AppError> {
+    let cycle = sqlx::query_as::<_, Cycle>(
+        r#"
+        INSERT INTO cycles (project_id, number, starts_on, ends_on)
+        SELECT $1, COALESCE(MAX(number) + 1, 1), $2, $3 FROM cycles WHERE project_id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(project_id)
+    .bind(starts_on)
+    .bind(ends_on)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(cycle)
+}
+
+/// Moves a task from one cycle to another, recording the scope change on
+/// both.
+pub(crate) async fn move_task_to_cycle(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    previous_cycle_id: Option<Uuid>,
+    next_cycle_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    if previous_cycle_id == next_cycle_id {
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE tasks SET cycle_id = $2 WHERE id = $1")
+        .bind(task_id)
+        .bind(next_cycle_id)
+        .execute(&mut *conn)
+        .await?;
+    if let Some(previous) = previous_cycle_id {
+        record_scope_change(conn, previous, task_id, "removed").await?;
+    }
+    if let Some(next) = next_cycle_id {
+        record_scope_change(conn, next, task_id, "added").await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn record_scope_change(
+    conn: &mut PgConnection,
+    cycle_id: Uuid,
+    task_id: Uuid,
+    change: &str,
+) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO cycle_scope_changes (cycle_id, task_id, change) VALUES ($1, $2, $3)")
+        .bind(cycle_id)
+        .bind(task_id)
+        .bind(change)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+fn validate_length(days: i32) -> Result<i32, AppError> {
+    if (1..=90).contains(&days) {
+        Ok(days)
+    } else {
+        Err(AppError::BadRequest("cycle length must be between 1 and 90 days".into()))
+    }
+}
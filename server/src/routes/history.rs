@@ -0,0 +1,40 @@
+use crate::{error::AppError, models::TaskEvent, routes::CurrentUser, state::SharedState};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+pub async fn list_history(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<TaskEvent>>, AppError> {
+    let events = sqlx::query_as::<_, TaskEvent>(
+        "SELECT * FROM task_events WHERE task_id = $1 ORDER BY created_at",
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(events))
+}
+
+pub(crate) async fn record_event(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    actor_id: Option<Uuid>,
+    kind: &str,
+    data: serde_json::Value,
+) -> Result<(), AppError> {
+    sqlx::query("INSERT INTO task_events (task_id, actor_id, kind, data) VALUES ($1, $2, $3, $4)")
+        .bind(task_id)
+        .bind(actor_id)
+        .bind(kind)
+        .bind(data)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
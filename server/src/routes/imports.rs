@@ -0,0 +1,126 @@
+use crate::{
+    error::AppError,
+    forge::ForgeKind,
+    github::{app::installation_for_repository, scopes::require_scopes, tokens::access_token_for},
+    jobs::{enqueue, Job},
+    models::GitHubImport,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateImport {
+    repository: String,
+}
+
+pub async fn create_import(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((project_id, kind)): Path<(Uuid, ForgeKind)>,
+    Json(body): Json<CreateImport>,
+) -> Result<Json<GitHubImport>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let repository = state
+        .forges
+        .get(kind)?
+        .parse_repository(&body.repository)
+        .ok_or_else(|| AppError::BadRequest(format!("{:?} is not a valid repository", body.repository)))?;
+
+    // Without an app installation or server token the import runs with the
+    // requester's own token, which needs repository access.
+    let user_id = current_user.user().id;
+    match kind {
+        ForgeKind::GitHub => {
+            if state.config.github_api_token.is_none()
+                && installation_for_repository(&state, &repository).await?.is_none()
+            {
+                require_scopes(&state, user_id, &["repo"]).await?;
+            }
+        }
+        kind => {
+            if access_token_for(&state, kind, &repository, Some(user_id)).await?.is_none() {
+                return Err(AppError::Forbidden(format!(
+                    "sign in with {} to import its issues",
+                    kind.as_str()
+                )));
+            }
+        }
+    }
+
+    let mut tx = state.pool.begin().await?;
+    let import = sqlx::query_as::<_, GitHubImport>(
+        r#"
+        INSERT INTO github_imports (project_id, forge, repository, requested_by)
+        SELECT id, $4, $2, $3 FROM projects WHERE id = $1
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(project_id)
+    .bind(&repository)
+    .bind(user_id)
+    .bind(kind.as_str())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let import = match import {
+        Some(import) => import,
+        None => {
+            let project_exists =
+                sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM projects WHERE id = $1)")
+                    .bind(project_id)
+                    .fetch_one(&state.pool)
+                    .await?;
+            return Err(if project_exists {
+                AppError::BadRequest(format!("an import of {} is already in progress", repository))
+            } else {
+                AppError::NotFound
+            });
+        }
+    };
+
+    enqueue(&mut *tx, &Job::Import { import_id: import.id }).await?;
+    tx.commit().await?;
+
+    Ok(Json(import))
+}
+
+pub async fn list_imports(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(project_id): Path<Uuid>,
+) -> Result<Json<Vec<GitHubImport>>, AppError> {
+    let imports = sqlx::query_as::<_, GitHubImport>(
+        "SELECT * FROM github_imports WHERE project_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(project_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(imports))
+}
+
+pub async fn get_import(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(import_id): Path<Uuid>,
+) -> Result<Json<GitHubImport>, AppError> {
+    let import = sqlx::query_as::<_, GitHubImport>("SELECT * FROM github_imports WHERE id = $1")
+        .bind(import_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(Json(import))
+}
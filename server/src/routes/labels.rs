@@ -0,0 +1,304 @@
+use crate::{
+    error::AppError,
+    github::parse_repository,
+    jobs::{enqueue, Job},
+    models::{GitHubLabelMapping, Label},
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateLabel {
+    name: String,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateLabel {
+    name: Option<String>,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LabelMappingFilter {
+    repository: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateLabelMapping {
+    repository: String,
+    github_name: String,
+    label_id: Uuid,
+}
+
+pub async fn list_labels(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<Label>>, AppError> {
+    let labels = sqlx::query_as::<_, Label>("SELECT * FROM labels ORDER BY LOWER(name)")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(labels))
+}
+
+pub async fn create_label(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<CreateLabel>,
+) -> Result<Json<Label>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("label name must not be empty".into()));
+    }
+    let color = body
+        .color
+        .as_deref()
+        .map(normalize_color)
+        .transpose()?
+        .unwrap_or_else(|| "6b7280".to_string());
+
+    let label = sqlx::query_as::<_, Label>(
+        r#"
+        INSERT INTO labels (name, color)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(name)
+    .bind(&color)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::BadRequest(format!("label {} already exists", name)))?;
+
+    Ok(Json(label))
+}
+
+/// Renames or recolors a label. GitHub label mappings refer to the label by
+/// ID, so they keep working under the new name.
+pub async fn update_label(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(label_id): Path<Uuid>,
+    Json(body): Json<UpdateLabel>,
+) -> Result<Json<Label>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let name = body.name.as_deref().map(str::trim);
+    if name == Some("") {
+        return Err(AppError::BadRequest("label name must not be empty".into()));
+    }
+    let color = body.color.as_deref().map(normalize_color).transpose()?;
+
+    if let Some(name) = name {
+        let taken = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM labels WHERE LOWER(name) = LOWER($1) AND id <> $2)",
+        )
+        .bind(name)
+        .bind(label_id)
+        .fetch_one(&state.pool)
+        .await?;
+        if taken {
+            return Err(AppError::BadRequest(format!("label {} already exists", name)));
+        }
+    }
+
+    let label = sqlx::query_as::<_, Label>(
+        "UPDATE labels SET name = COALESCE($2, name), color = COALESCE($3, color) WHERE id = $1 RETURNING *",
+    )
+    .bind(label_id)
+    .bind(name)
+    .bind(&color)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(label))
+}
+
+pub async fn attach_label(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, label_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let found = sqlx::query_scalar::<_, bool>(
+        r#"
+        WITH target AS (
+            SELECT t.id AS task_id, l.id AS label_id
+            FROM tasks t, labels l
+            WHERE t.id = $1 AND l.id = $2
+        ), inserted AS (
+            INSERT INTO task_labels (task_id, label_id)
+            SELECT task_id, label_id FROM target
+            ON CONFLICT DO NOTHING
+        )
+        SELECT EXISTS (SELECT 1 FROM target)
+        "#,
+    )
+    .bind(task_id)
+    .bind(label_id)
+    .fetch_one(&state.pool)
+    .await?;
+
+    if found {
+        let job = Job::IssueSync {
+            task_id,
+            actor_id: Some(current_user.user().id),
+        };
+        enqueue(&state.pool, &job).await?;
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+pub async fn detach_label(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, label_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    sqlx::query("DELETE FROM task_labels WHERE task_id = $1 AND label_id = $2")
+        .bind(task_id)
+        .bind(label_id)
+        .execute(&state.pool)
+        .await?;
+    let job = Job::IssueSync {
+        task_id,
+        actor_id: Some(current_user.user().id),
+    };
+    enqueue(&state.pool, &job).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_label_mappings(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Query(filter): Query<LabelMappingFilter>,
+) -> Result<Json<Vec<GitHubLabelMapping>>, AppError> {
+    let mappings = sqlx::query_as::<_, GitHubLabelMapping>(
+        "SELECT * FROM github_label_mappings WHERE repository = $1 ORDER BY LOWER(github_name)",
+    )
+    .bind(filter.repository.trim())
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(mappings))
+}
+
+/// Maps a GitHub label of a repository to a Keyflow label, e.g. `type:bug` to
+/// `bug`, replacing the earlier mapping of that GitHub label. Tasks pick up
+/// the new mapping on their next sync.
+pub async fn create_label_mapping(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<CreateLabelMapping>,
+) -> Result<Json<GitHubLabelMapping>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let (owner, name) = parse_repository(&body.repository)
+        .ok_or_else(|| AppError::BadRequest("repository must look like owner/name".into()))?;
+    let repository = format!("{}/{}", owner, name);
+    let github_name = body.github_name.trim();
+    if github_name.is_empty() {
+        return Err(AppError::BadRequest("github label name must not be empty".into()));
+    }
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query_scalar::<_, Uuid>("SELECT id FROM labels WHERE id = $1")
+        .bind(body.label_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let mapped_from = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT github_name FROM github_label_mappings
+        WHERE repository = $1 AND label_id = $2 AND LOWER(github_name) <> LOWER($3)
+        "#,
+    )
+    .bind(&repository)
+    .bind(body.label_id)
+    .bind(github_name)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(mapped_from) = mapped_from {
+        return Err(AppError::Conflict(format!(
+            "label is already mapped from {} in {}",
+            mapped_from, repository
+        )));
+    }
+
+    let mapping = sqlx::query_as::<_, GitHubLabelMapping>(
+        r#"
+        INSERT INTO github_label_mappings (repository, github_name, label_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (repository, LOWER(github_name)) DO UPDATE SET label_id = EXCLUDED.label_id
+        RETURNING *
+        "#,
+    )
+    .bind(&repository)
+    .bind(github_name)
+    .bind(body.label_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(mapping))
+}
+
+pub async fn delete_label_mapping(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(mapping_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let deleted = sqlx::query("DELETE FROM github_label_mappings WHERE id = $1")
+        .bind(mapping_id)
+        .execute(&state.pool)
+        .await?;
+
+    if deleted.rows_affected() > 0 {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+/// Accepts `#RRGGBB` or `RRGGBB` and stores the lowercase form GitHub uses.
+pub(crate) fn normalize_color(color: &str) -> Result<String, AppError> {
+    let hex = color.trim().trim_start_matches('#').to_ascii_lowercase();
+    if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(hex)
+    } else {
+        Err(AppError::BadRequest(format!("invalid label color: {}", color)))
+    }
+}
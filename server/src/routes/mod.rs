@@ -1,22 +1,116 @@
 use crate::state::SharedState;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
+mod admin;
 mod auth;
+mod branches;
+mod checklists;
+mod comments;
+mod commits;
+mod cycles;
 mod health;
+mod history;
+mod imports;
+mod labels;
+mod notifications;
+mod pins;
+mod projects;
+mod relations;
+mod tasks;
+mod watchers;
+mod webhooks;
 
 pub use auth::{CurrentUser, SESSION_COOKIE};
+pub(crate) use cycles::{insert_cycle, move_task_to_cycle};
+pub(crate) use history::record_event;
+pub(crate) use labels::normalize_color;
+pub(crate) use tasks::{insert_task, TASK_STATUSES};
+pub(crate) use watchers::fan_out;
 
 pub fn build_router(state: SharedState) -> Router<SharedState> {
     Router::new()
         .route("/health", get(health::health))
-        .route("/auth/github/login", get(auth::github_login))
-        .route("/auth/github/callback", get(auth::github_callback))
+        .route("/auth/github/upgrade", get(auth::github_upgrade))
+        .route("/auth/:forge/login", get(auth::login))
+        .route("/auth/:forge/callback", get(auth::callback))
         .route("/auth/logout", post(auth::logout))
         .route("/session", get(auth::current_session))
         .route("/me", get(auth::me))
+        .route("/projects", get(projects::list_projects).post(projects::create_project))
+        .route("/tasks", get(tasks::list_tasks).post(tasks::create_task))
+        .route(
+            "/tasks/:task_id",
+            get(tasks::get_task).put(tasks::update_task).delete(tasks::delete_task),
+        )
+        .route(
+            "/tasks/:task_id/checklist",
+            get(checklists::list_items).post(checklists::add_item),
+        )
+        .route("/tasks/:task_id/checklist/order", put(checklists::reorder_items))
+        .route(
+            "/tasks/:task_id/checklist/:item_id",
+            put(checklists::update_item).delete(checklists::delete_item),
+        )
+        .route("/tasks/:task_id/checklist/:item_id/toggle", post(checklists::toggle_item))
+        .route("/tasks/:task_id/checklist/:item_id/convert", post(checklists::convert_item))
+        .route(
+            "/tasks/:task_id/comments",
+            get(comments::list_comments).post(comments::create_comment),
+        )
+        .route("/labels", get(labels::list_labels).post(labels::create_label))
+        .route("/labels/:label_id", put(labels::update_label))
+        .route(
+            "/label-mappings",
+            get(labels::list_label_mappings).post(labels::create_label_mapping),
+        )
+        .route("/label-mappings/:mapping_id", delete(labels::delete_label_mapping))
+        .route(
+            "/tasks/:task_id/labels/:label_id",
+            put(labels::attach_label).delete(labels::detach_label),
+        )
+        .route(
+            "/tasks/:task_id/relations",
+            get(relations::list_relations).post(relations::create_relation),
+        )
+        .route("/tasks/:task_id/relations/:relation_id", delete(relations::delete_relation))
+        .route("/tasks/:task_id/merge-into/:other_id", post(relations::merge_into))
+        .route("/task-merges/:merge_id/revert", post(relations::revert_merge))
+        .route("/tasks/:task_id/history", get(history::list_history))
+        .route(
+            "/tasks/:task_id/branches",
+            get(branches::list_branches).post(branches::create_task_branch),
+        )
+        .route("/tasks/:task_id/commits", get(commits::list_commits))
+        .route("/tasks/:task_id/watchers", get(watchers::list_watchers))
+        .route(
+            "/tasks/:task_id/watch",
+            put(watchers::subscribe).delete(watchers::unsubscribe),
+        )
+        .route("/admin/github", get(admin::github_status))
+        .route("/admin/jobs", get(admin::list_jobs))
+        .route("/admin/jobs/:job_id/retry", post(admin::retry_job))
+        .route("/me/notifications", get(notifications::list_notifications))
+        .route("/me/notifications/:notification_id/read", post(notifications::mark_read))
+        .route(
+            "/projects/:project_id/cycles",
+            get(cycles::list_cycles).post(cycles::create_cycle),
+        )
+        .route("/projects/:project_id/cycle-settings", put(cycles::update_cycle_settings))
+        .route("/projects/:project_id/pr-transitions", put(projects::update_pr_transitions))
+        .route("/projects/:project_id/milestones", get(cycles::list_milestones))
+        .route("/milestones/:milestone_id/cycle", put(cycles::assign_milestone_cycle))
+        .route("/cycles/:cycle_id/progress", get(cycles::cycle_progress))
+        .route("/tasks/:task_id/cycle", put(cycles::assign_task_cycle))
+        .route("/projects/:project_id/imports", get(imports::list_imports))
+        .route("/projects/:project_id/imports/:forge", post(imports::create_import))
+        .route("/imports/:import_id", get(imports::get_import))
+        .route("/me/pins", get(pins::list_pins).post(pins::create_pin))
+        .route("/me/pins/order", put(pins::reorder_pins))
+        .route("/me/pins/:pin_id", delete(pins::delete_pin))
+        .route("/webhooks/:forge", post(webhooks::forge_webhook))
         .with_state(state)
 }
 
@@ -0,0 +1,69 @@
+use crate::{
+    error::AppError,
+    models::Notification,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+
+const NOTIFICATION_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct NotificationFilter {
+    #[serde(default)]
+    unread: bool,
+}
+
+pub async fn list_notifications(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Query(filter): Query<NotificationFilter>,
+) -> Result<Json<Vec<Notification>>, AppError> {
+    let notifications = sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT * FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(current_user.user().id)
+    .bind(filter.unread)
+    .bind(NOTIFICATION_PAGE_SIZE)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(notifications))
+}
+
+pub async fn mark_read(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(notification_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let result = sqlx::query(
+        "UPDATE notifications SET read_at = COALESCE(read_at, now()) WHERE id = $1 AND user_id = $2",
+    )
+    .bind(notification_id)
+    .bind(current_user.user().id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
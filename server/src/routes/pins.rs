@@ -0,0 +1,193 @@
+use crate::{
+    error::AppError,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Pins beyond this position have no `g <n>` shortcut.
+const SHORTCUT_SLOTS: usize = 9;
+
+#[derive(FromRow, Serialize)]
+pub struct PinView {
+    id: Uuid,
+    target_type: String,
+    target_id: Uuid,
+    key: String,
+    title: String,
+    position: i32,
+    #[sqlx(skip)]
+    shortcut: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePin {
+    task_id: Option<Uuid>,
+    project_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderPins {
+    pin_ids: Vec<Uuid>,
+}
+
+pub async fn list_pins(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<PinView>>, AppError> {
+    Ok(Json(fetch_pins(&state.pool, current_user.user().id).await?))
+}
+
+pub async fn create_pin(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<CreatePin>,
+) -> Result<Json<Vec<PinView>>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let user_id = current_user.user().id;
+
+    let (exists_query, target_id, column) = match (body.task_id, body.project_id) {
+        (Some(task_id), None) => ("SELECT id FROM tasks WHERE id = $1", task_id, "task_id"),
+        (None, Some(project_id)) => ("SELECT id FROM projects WHERE id = $1", project_id, "project_id"),
+        _ => {
+            return Err(AppError::BadRequest(
+                "exactly one of task_id or project_id is required".into(),
+            ))
+        }
+    };
+
+    let mut tx = state.pool.begin().await?;
+    sqlx::query(exists_query)
+        .bind(target_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    // Serialise concurrent pins for the same user so positions stay unique.
+    sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let inserted = sqlx::query(&format!(
+        r#"
+        INSERT INTO user_pins (user_id, {column}, position)
+        SELECT $1, $2, COALESCE(MAX(position) + 1, 0) FROM user_pins WHERE user_id = $1
+        ON CONFLICT DO NOTHING
+        "#
+    ))
+    .bind(user_id)
+    .bind(target_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    if inserted.rows_affected() == 0 {
+        return Err(AppError::BadRequest("already pinned".into()));
+    }
+
+    Ok(Json(fetch_pins(&state.pool, user_id).await?))
+}
+
+pub async fn delete_pin(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(pin_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let result = sqlx::query("DELETE FROM user_pins WHERE id = $1 AND user_id = $2")
+        .bind(pin_id)
+        .bind(current_user.user().id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn reorder_pins(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<ReorderPins>,
+) -> Result<Json<Vec<PinView>>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let user_id = current_user.user().id;
+
+    let mut tx = state.pool.begin().await?;
+    let existing: HashSet<Uuid> =
+        sqlx::query_scalar::<_, Uuid>("SELECT id FROM user_pins WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let requested: HashSet<Uuid> = body.pin_ids.iter().copied().collect();
+    if requested.len() != body.pin_ids.len() || requested != existing {
+        return Err(AppError::BadRequest("pin_ids must list every pin exactly once".into()));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE user_pins AS pin
+        SET position = o.position - 1
+        FROM UNNEST($2::uuid[]) WITH ORDINALITY AS o (id, position)
+        WHERE pin.id = o.id AND pin.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&body.pin_ids)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(fetch_pins(&state.pool, user_id).await?))
+}
+
+async fn fetch_pins(pool: &PgPool, user_id: Uuid) -> Result<Vec<PinView>, AppError> {
+    let mut pins = sqlx::query_as::<_, PinView>(
+        r#"
+        SELECT pin.id,
+               CASE WHEN pin.task_id IS NOT NULL THEN 'task' ELSE 'project' END AS target_type,
+               COALESCE(pin.task_id, pin.project_id) AS target_id,
+               COALESCE(tp.key || '-' || t.number, p.key) AS key,
+               COALESCE(t.title, p.name) AS title,
+               pin.position
+        FROM user_pins pin
+        LEFT JOIN tasks t ON t.id = pin.task_id
+        LEFT JOIN projects tp ON tp.id = t.project_id
+        LEFT JOIN projects p ON p.id = pin.project_id
+        WHERE pin.user_id = $1
+        ORDER BY pin.position, pin.created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    for (index, pin) in pins.iter_mut().take(SHORTCUT_SLOTS).enumerate() {
+        pin.shortcut = Some(format!("g {}", index + 1));
+    }
+
+    Ok(pins)
+}
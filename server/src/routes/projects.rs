@@ -0,0 +1,111 @@
+use crate::{
+    error::AppError,
+    models::Project,
+    routes::{tasks::validate_status, CurrentUser},
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateProject {
+    key: String,
+    name: String,
+}
+
+pub async fn list_projects(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<Project>>, AppError> {
+    let projects = sqlx::query_as::<_, Project>("SELECT * FROM projects ORDER BY key")
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(projects))
+}
+
+pub async fn create_project(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<CreateProject>,
+) -> Result<Json<Project>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let key = body.key.trim().to_uppercase();
+    if !is_valid_project_key(&key) {
+        return Err(AppError::BadRequest(
+            "project key must be 2-10 letters or digits and start with a letter".into(),
+        ));
+    }
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("project name must not be empty".into()));
+    }
+
+    let project = sqlx::query_as::<_, Project>(
+        r#"
+        INSERT INTO projects (key, name, created_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (key) DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(&key)
+    .bind(name)
+    .bind(current_user.user().id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::BadRequest(format!("project key {} is already in use", key)))?;
+
+    Ok(Json(project))
+}
+
+#[derive(Deserialize)]
+pub struct PullRequestTransitions {
+    /// Status for tasks whose pull request opens; `None` leaves them alone.
+    pr_opened_status: Option<String>,
+    /// Status for tasks whose pull request merges; `None` leaves them alone.
+    pr_merged_status: Option<String>,
+}
+
+pub async fn update_pr_transitions(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(project_id): Path<Uuid>,
+    Json(body): Json<PullRequestTransitions>,
+) -> Result<Json<Project>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    for status in [&body.pr_opened_status, &body.pr_merged_status].into_iter().flatten() {
+        validate_status(status)?;
+    }
+
+    let project = sqlx::query_as::<_, Project>(
+        "UPDATE projects SET pr_opened_status = $2, pr_merged_status = $3 WHERE id = $1 RETURNING *",
+    )
+    .bind(project_id)
+    .bind(&body.pr_opened_status)
+    .bind(&body.pr_merged_status)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(project))
+}
+
+fn is_valid_project_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_uppercase())
+        && (2..=10).contains(&key.len())
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
@@ -0,0 +1,409 @@
+use crate::{
+    error::AppError,
+    models::{TaskMerge, TaskRelation},
+    routes::{
+        history::record_event,
+        tasks::{fetch_task_view, TaskView},
+        watchers::fan_out,
+        CurrentUser,
+    },
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// How long after a merge it can still be undone.
+const MERGE_REVERT_WINDOW_HOURS: i64 = 72;
+
+#[derive(Deserialize)]
+pub struct CreateRelation {
+    kind: String,
+    task_id: Uuid,
+}
+
+/// A relation seen from one task's side: `duplicate_of` rows show up as
+/// `duplicated_by` on the task they point at.
+#[derive(FromRow, Serialize)]
+pub struct RelationView {
+    id: Uuid,
+    kind: String,
+    task_id: Uuid,
+    task_key: String,
+    task_title: String,
+    task_status: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct MergeResponse {
+    merge: TaskMerge,
+    task: TaskView,
+}
+
+pub async fn list_relations(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<RelationView>>, AppError> {
+    let relations = sqlx::query_as::<_, RelationView>(
+        r#"
+        SELECT r.id,
+               CASE
+                   WHEN r.source_task_id = $1 THEN r.kind
+                   WHEN r.kind = 'duplicate_of' THEN 'duplicated_by'
+                   ELSE r.kind
+               END AS kind,
+               o.id AS task_id,
+               p.key || '-' || o.number AS task_key,
+               o.title AS task_title,
+               o.status AS task_status,
+               r.created_at
+        FROM task_relations r
+        JOIN tasks o
+          ON o.id = CASE WHEN r.source_task_id = $1 THEN r.target_task_id ELSE r.source_task_id END
+        JOIN projects p ON p.id = o.project_id
+        WHERE r.source_task_id = $1 OR r.target_task_id = $1
+        ORDER BY r.created_at
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(relations))
+}
+
+pub async fn create_relation(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<CreateRelation>,
+) -> Result<Json<TaskRelation>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let (kind, source, target) = match body.kind.as_str() {
+        "duplicate_of" | "relates_to" => (body.kind.as_str(), task_id, body.task_id),
+        "duplicated_by" => ("duplicate_of", body.task_id, task_id),
+        other => return Err(AppError::BadRequest(format!("unknown relation kind: {}", other))),
+    };
+    if source == target {
+        return Err(AppError::BadRequest("a task cannot be related to itself".into()));
+    }
+
+    let found = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM tasks WHERE id = $1 OR id = $2")
+        .bind(source)
+        .bind(target)
+        .fetch_one(&state.pool)
+        .await?;
+    if found != 2 {
+        return Err(AppError::NotFound);
+    }
+
+    let relation = sqlx::query_as::<_, TaskRelation>(
+        r#"
+        INSERT INTO task_relations (kind, source_task_id, target_task_id, created_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        RETURNING *
+        "#,
+    )
+    .bind(kind)
+    .bind(source)
+    .bind(target)
+    .bind(current_user.user().id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("tasks are already related".into()))?;
+
+    Ok(Json(relation))
+}
+
+pub async fn delete_relation(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((task_id, relation_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let result = sqlx::query(
+        "DELETE FROM task_relations WHERE id = $2 AND (source_task_id = $1 OR target_task_id = $1)",
+    )
+    .bind(task_id)
+    .bind(relation_id)
+    .execute(&state.pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Folds a duplicate task into the surviving one: comments, labels and
+/// relations move across, the duplicate is canceled with a `duplicate_of`
+/// back-reference, and everything moved is recorded so the merge can be
+/// reverted.
+pub async fn merge_into(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path((source_id, target_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<MergeResponse>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    if source_id == target_id {
+        return Err(AppError::BadRequest("a task cannot be merged into itself".into()));
+    }
+    let actor_id = current_user.user().id;
+
+    let mut tx = state.pool.begin().await?;
+    let locked = sqlx::query_as::<_, (Uuid, String, Option<DateTime<Utc>>)>(
+        "SELECT id, status, completed_at FROM tasks WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+    )
+    .bind(vec![source_id, target_id])
+    .fetch_all(&mut *tx)
+    .await?;
+    if locked.len() != 2 {
+        return Err(AppError::NotFound);
+    }
+    let (_, previous_status, previous_completed_at) = locked
+        .into_iter()
+        .find(|(id, _, _)| *id == source_id)
+        .ok_or(AppError::NotFound)?;
+
+    let already_merged = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM task_merges WHERE source_task_id = ANY($1) AND reverted_at IS NULL)",
+    )
+    .bind(vec![source_id, target_id])
+    .fetch_one(&mut *tx)
+    .await?;
+    if already_merged {
+        return Err(AppError::BadRequest(
+            "one of the tasks has already been merged elsewhere".into(),
+        ));
+    }
+
+    let moved_comment_ids = sqlx::query_scalar::<_, Uuid>(
+        "UPDATE task_comments SET task_id = $2 WHERE task_id = $1 RETURNING id",
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let added_label_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO task_labels (task_id, label_id)
+        SELECT $2, label_id FROM task_labels WHERE task_id = $1
+        ON CONFLICT DO NOTHING
+        RETURNING label_id
+        "#,
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let moved_outgoing_relation_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE task_relations r
+        SET source_task_id = $2
+        WHERE r.source_task_id = $1
+          AND r.target_task_id <> $2
+          AND NOT EXISTS (
+              SELECT 1 FROM task_relations x
+              WHERE x.kind = r.kind
+                AND ((x.source_task_id = $2 AND x.target_task_id = r.target_task_id)
+                  OR (x.target_task_id = $2 AND x.source_task_id = r.target_task_id))
+          )
+        RETURNING r.id
+        "#,
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let moved_incoming_relation_ids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE task_relations r
+        SET target_task_id = $2
+        WHERE r.target_task_id = $1
+          AND r.source_task_id <> $2
+          AND NOT EXISTS (
+              SELECT 1 FROM task_relations x
+              WHERE x.kind = r.kind
+                AND ((x.source_task_id = $2 AND x.target_task_id = r.source_task_id)
+                  OR (x.target_task_id = $2 AND x.source_task_id = r.source_task_id))
+          )
+        RETURNING r.id
+        "#,
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let back_reference_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO task_relations (kind, source_task_id, target_task_id, created_by)
+        VALUES ('duplicate_of', $1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .bind(actor_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "UPDATE tasks SET status = 'canceled', completed_at = COALESCE(completed_at, now()) WHERE id = $1",
+    )
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let merge = sqlx::query_as::<_, TaskMerge>(
+        r#"
+        INSERT INTO task_merges (
+            source_task_id, target_task_id, merged_by, previous_status, previous_completed_at,
+            moved_comment_ids, added_label_ids, moved_outgoing_relation_ids,
+            moved_incoming_relation_ids, back_reference_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        "#,
+    )
+    .bind(source_id)
+    .bind(target_id)
+    .bind(actor_id)
+    .bind(&previous_status)
+    .bind(previous_completed_at)
+    .bind(&moved_comment_ids)
+    .bind(&added_label_ids)
+    .bind(&moved_outgoing_relation_ids)
+    .bind(&moved_incoming_relation_ids)
+    .bind(back_reference_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let merged_into = json!({ "merge_id": merge.id, "task_id": target_id });
+    record_event(&mut tx, source_id, Some(actor_id), "merged_into", merged_into.clone()).await?;
+    fan_out(&mut tx, source_id, Some(actor_id), "merged_into", merged_into).await?;
+    let merged_from = json!({
+        "merge_id": merge.id,
+        "task_id": source_id,
+        "comments": moved_comment_ids.len(),
+        "labels": added_label_ids.len(),
+        "relations": moved_outgoing_relation_ids.len() + moved_incoming_relation_ids.len(),
+    });
+    record_event(&mut tx, target_id, Some(actor_id), "merged_from", merged_from.clone()).await?;
+    fan_out(&mut tx, target_id, Some(actor_id), "merged_from", merged_from).await?;
+    tx.commit().await?;
+
+    let task = fetch_task_view(&state.pool, target_id).await?;
+    Ok(Json(MergeResponse { merge, task }))
+}
+
+/// Undoes a merge made within the last `MERGE_REVERT_WINDOW_HOURS`. Only the
+/// rows the merge itself moved are touched, so later edits on the surviving
+/// task are kept.
+pub async fn revert_merge(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(merge_id): Path<Uuid>,
+) -> Result<Json<TaskMerge>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let actor_id = current_user.user().id;
+
+    let mut tx = state.pool.begin().await?;
+    let merge = sqlx::query_as::<_, TaskMerge>("SELECT * FROM task_merges WHERE id = $1 FOR UPDATE")
+        .bind(merge_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    if merge.reverted_at.is_some() {
+        return Err(AppError::BadRequest("merge has already been reverted".into()));
+    }
+    if merge.created_at < Utc::now() - Duration::hours(MERGE_REVERT_WINDOW_HOURS) {
+        return Err(AppError::BadRequest("merge can no longer be reverted".into()));
+    }
+
+    sqlx::query("UPDATE task_comments SET task_id = $1 WHERE id = ANY($3) AND task_id = $2")
+        .bind(merge.source_task_id)
+        .bind(merge.target_task_id)
+        .bind(&merge.moved_comment_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM task_labels WHERE task_id = $1 AND label_id = ANY($2)")
+        .bind(merge.target_task_id)
+        .bind(&merge.added_label_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE task_relations SET source_task_id = $1 WHERE id = ANY($3) AND source_task_id = $2")
+        .bind(merge.source_task_id)
+        .bind(merge.target_task_id)
+        .bind(&merge.moved_outgoing_relation_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE task_relations SET target_task_id = $1 WHERE id = ANY($3) AND target_task_id = $2")
+        .bind(merge.source_task_id)
+        .bind(merge.target_task_id)
+        .bind(&merge.moved_incoming_relation_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    if let Some(back_reference_id) = merge.back_reference_id {
+        sqlx::query("DELETE FROM task_relations WHERE id = $1")
+            .bind(back_reference_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("UPDATE tasks SET status = $2, completed_at = $3 WHERE id = $1")
+        .bind(merge.source_task_id)
+        .bind(&merge.previous_status)
+        .bind(merge.previous_completed_at)
+        .execute(&mut *tx)
+        .await?;
+
+    let merge = sqlx::query_as::<_, TaskMerge>(
+        "UPDATE task_merges SET reverted_at = now(), reverted_by = $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(merge_id)
+    .bind(actor_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for task_id in [merge.source_task_id, merge.target_task_id] {
+        let data = json!({ "merge_id": merge.id });
+        record_event(&mut tx, task_id, Some(actor_id), "merge_reverted", data.clone()).await?;
+        fan_out(&mut tx, task_id, Some(actor_id), "merge_reverted", data).await?;
+    }
+    tx.commit().await?;
+
+    Ok(Json(merge))
+}
@@ -0,0 +1,301 @@
+use crate::{
+    error::AppError,
+    jobs::{enqueue, Job},
+    models::Task,
+    routes::{
+        watchers::{auto_subscribe, fan_out},
+        CurrentUser,
+    },
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+pub const TASK_STATUSES: &[&str] = &["backlog", "todo", "in_progress", "in_review", "done", "canceled"];
+
+const TASK_VIEW_SELECT: &str = r#"
+    SELECT t.*,
+           p.key || '-' || t.number AS key,
+           COALESCE(c.total, 0) AS checklist_total,
+           COALESCE(c.completed, 0) AS checklist_completed,
+           ARRAY(
+               SELECT l.name FROM task_labels tl
+               JOIN labels l ON l.id = tl.label_id
+               WHERE tl.task_id = t.id
+               ORDER BY LOWER(l.name)
+           ) AS labels,
+           COALESCE((
+               SELECT json_agg(json_build_object(
+                          'repository', pr.repository,
+                          'number', pr.number,
+                          'title', pr.title,
+                          'url', pr.url,
+                          'state', pr.state,
+                          'merged_at', pr.merged_at
+                      ) ORDER BY pr.created_at)
+               FROM task_pull_requests pr
+               WHERE pr.task_id = t.id
+           ), '[]'::json) AS pull_requests
+    FROM tasks t
+    JOIN projects p ON p.id = t.project_id
+    LEFT JOIN LATERAL (
+        SELECT COUNT(*) AS total, COUNT(*) FILTER (WHERE completed) AS completed
+        FROM task_checklist_items
+        WHERE task_id = t.id
+    ) c ON true
+"#;
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct TaskView {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    task: Task,
+    key: String,
+    checklist_total: i64,
+    checklist_completed: i64,
+    labels: Vec<String>,
+    pull_requests: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct TaskFilter {
+    project_id: Option<Uuid>,
+    status: Option<String>,
+    assignee_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTask {
+    project_id: Uuid,
+    title: String,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTask {
+    title: String,
+    description: Option<String>,
+    status: String,
+    assignee_id: Option<Uuid>,
+}
+
+pub async fn list_tasks(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Query(filter): Query<TaskFilter>,
+) -> Result<Json<Vec<TaskView>>, AppError> {
+    let query = format!(
+        r#"
+        {TASK_VIEW_SELECT}
+        WHERE ($1::uuid IS NULL OR t.project_id = $1)
+          AND ($2::text IS NULL OR t.status = $2)
+          AND ($3::uuid IS NULL OR t.assignee_id = $3)
+        ORDER BY p.key, t.number DESC
+        "#
+    );
+
+    let tasks = sqlx::query_as::<_, TaskView>(&query)
+        .bind(filter.project_id)
+        .bind(filter.status)
+        .bind(filter.assignee_id)
+        .fetch_all(&state.pool)
+        .await?;
+
+    Ok(Json(tasks))
+}
+
+pub async fn get_task(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskView>, AppError> {
+    Ok(Json(fetch_task_view(&state.pool, task_id).await?))
+}
+
+pub async fn create_task(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(body): Json<CreateTask>,
+) -> Result<Json<TaskView>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let title = validate_title(&body.title)?;
+
+    let user_id = current_user.user().id;
+    let mut tx = state.pool.begin().await?;
+    let task = insert_task(&mut tx, body.project_id, title, body.description.as_deref(), Some(user_id)).await?;
+    auto_subscribe(&mut tx, task.id, user_id, "creator").await?;
+    tx.commit().await?;
+
+    Ok(Json(fetch_task_view(&state.pool, task.id).await?))
+}
+
+pub async fn update_task(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+    Json(body): Json<UpdateTask>,
+) -> Result<Json<TaskView>, AppError> {
+    verify_csrf(&jar, &headers)?;
+    let title = validate_title(&body.title)?;
+    validate_status(&body.status)?;
+    let actor_id = current_user.user().id;
+
+    let mut tx = state.pool.begin().await?;
+    let previous = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = $1 FOR UPDATE")
+        .bind(task_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    sqlx::query(
+        r#"
+        UPDATE tasks
+        SET title = $2,
+            description = $3,
+            status = $4,
+            assignee_id = $5,
+            completed_at = CASE
+                WHEN $4 IN ('done', 'canceled') THEN COALESCE(completed_at, now())
+                ELSE NULL
+            END
+        WHERE id = $1
+        "#,
+    )
+    .bind(task_id)
+    .bind(title)
+    .bind(&body.description)
+    .bind(&body.status)
+    .bind(body.assignee_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut changes = serde_json::Map::new();
+    if previous.title != title {
+        changes.insert("title".into(), json!({ "from": previous.title, "to": title }));
+    }
+    if previous.description != body.description {
+        changes.insert("description".into(), json!({}));
+    }
+    if previous.status != body.status {
+        changes.insert("status".into(), json!({ "from": previous.status, "to": body.status }));
+    }
+    if previous.assignee_id != body.assignee_id {
+        changes.insert(
+            "assignee_id".into(),
+            json!({ "from": previous.assignee_id, "to": body.assignee_id }),
+        );
+        if let Some(assignee_id) = body.assignee_id {
+            auto_subscribe(&mut tx, task_id, assignee_id, "assignee").await?;
+        }
+    }
+    let changed = !changes.is_empty();
+    if changed {
+        fan_out(&mut tx, task_id, Some(actor_id), "task_updated", changes.into()).await?;
+    }
+    if changed && previous.github_node_id.is_some() {
+        enqueue(
+            &mut *tx,
+            &Job::IssueSync {
+                task_id,
+                actor_id: Some(actor_id),
+            },
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(Json(fetch_task_view(&state.pool, task_id).await?))
+}
+
+pub async fn delete_task(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let result = sqlx::query("DELETE FROM tasks WHERE id = $1")
+        .bind(task_id)
+        .execute(&state.pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn fetch_task_view(pool: &PgPool, task_id: Uuid) -> Result<TaskView, AppError> {
+    sqlx::query_as::<_, TaskView>(&format!("{TASK_VIEW_SELECT} WHERE t.id = $1"))
+        .bind(task_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// Allocates the next per-project task number and inserts the task. Callers
+/// must run this inside a transaction so the counter and row stay in step.
+pub(crate) async fn insert_task(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    title: &str,
+    description: Option<&str>,
+    created_by: Option<Uuid>,
+) -> Result<Task, AppError> {
+    let number = sqlx::query_scalar::<_, i32>(
+        "UPDATE projects SET task_counter = task_counter + 1 WHERE id = $1 RETURNING task_counter",
+    )
+    .bind(project_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    let task = sqlx::query_as::<_, Task>(
+        r#"
+        INSERT INTO tasks (project_id, number, title, description, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(project_id)
+    .bind(number)
+    .bind(title)
+    .bind(description)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(task)
+}
+
+pub(super) fn validate_title(title: &str) -> Result<&str, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("title must not be empty".into()));
+    }
+    Ok(title)
+}
+
+pub(super) fn validate_status(status: &str) -> Result<(), AppError> {
+    if TASK_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!("unknown task status: {}", status)))
+    }
+}
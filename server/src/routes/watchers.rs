@@ -0,0 +1,154 @@
+use crate::{
+    error::AppError,
+    models::TaskWatcher,
+    routes::CurrentUser,
+    security::csrf::verify_csrf,
+    state::SharedState,
+};
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, PgConnection};
+use uuid::Uuid;
+
+#[derive(FromRow, Serialize)]
+pub struct WatcherView {
+    user_id: Uuid,
+    login: String,
+    avatar_url: Option<String>,
+    reason: String,
+    created_at: DateTime<Utc>,
+}
+
+pub async fn list_watchers(
+    _current_user: CurrentUser,
+    State(state): State<SharedState>,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<Vec<WatcherView>>, AppError> {
+    let watchers = sqlx::query_as::<_, WatcherView>(
+        r#"
+        SELECT w.user_id, u.login, u.avatar_url, w.reason, w.created_at
+        FROM task_watchers w
+        JOIN users u ON u.id = w.user_id
+        WHERE w.task_id = $1 AND w.subscribed
+        ORDER BY w.created_at
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(watchers))
+}
+
+pub async fn subscribe(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+) -> Result<Json<TaskWatcher>, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    let watcher = sqlx::query_as::<_, TaskWatcher>(
+        r#"
+        INSERT INTO task_watchers (task_id, user_id, reason)
+        SELECT id, $2, 'manual' FROM tasks WHERE id = $1
+        ON CONFLICT (task_id, user_id) DO UPDATE
+        SET subscribed = true
+        RETURNING *
+        "#,
+    )
+    .bind(task_id)
+    .bind(current_user.user().id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(Json(watcher))
+}
+
+pub async fn unsubscribe(
+    current_user: CurrentUser,
+    State(state): State<SharedState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(task_id): Path<Uuid>,
+) -> Result<StatusCode, AppError> {
+    verify_csrf(&jar, &headers)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO task_watchers (task_id, user_id, reason, subscribed)
+        SELECT id, $2, 'manual', false FROM tasks WHERE id = $1
+        ON CONFLICT (task_id, user_id) DO UPDATE
+        SET subscribed = false
+        RETURNING task_id
+        "#,
+    )
+    .bind(task_id)
+    .bind(current_user.user().id)
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Subscribes a user as a side effect of creating, commenting on or being
+/// assigned a task. An earlier explicit unsubscribe is left untouched.
+pub(crate) async fn auto_subscribe(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    user_id: Uuid,
+    reason: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO task_watchers (task_id, user_id, reason)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (task_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(task_id)
+    .bind(user_id)
+    .bind(reason)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Writes one notification per subscribed watcher of the task. The actor is
+/// skipped so nobody is notified about their own change.
+pub(crate) async fn fan_out(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    actor_id: Option<Uuid>,
+    kind: &str,
+    data: serde_json::Value,
+) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, task_id, actor_id, kind, data)
+        SELECT w.user_id, w.task_id, $2, $3, $4
+        FROM task_watchers w
+        WHERE w.task_id = $1
+          AND w.subscribed
+          AND w.user_id IS DISTINCT FROM $2
+        "#,
+    )
+    .bind(task_id)
+    .bind(actor_id)
+    .bind(kind)
+    .bind(data)
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
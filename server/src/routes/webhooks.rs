@@ -0,0 +1,68 @@
+use crate::{
+    error::AppError,
+    forge::ForgeKind,
+    jobs::{enqueue, Job},
+    routes::auth::resolve_client_ip,
+    state::SharedState,
+};
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use serde_json::Value;
+use std::net::SocketAddr;
+use tracing::{info, warn};
+
+/// Receives forge webhook deliveries. Authentication is the shared secret
+/// the forge signs or sends with each delivery rather than a session, so this
+/// route takes no cookies and needs no CSRF token.
+pub async fn forge_webhook(
+    State(state): State<SharedState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(kind): Path<ForgeKind>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, AppError> {
+    let client_ip = resolve_client_ip(addr, &headers);
+    state.webhook_rate_limiter.check(client_ip).await?;
+
+    let delivery = match state.forges.get(kind)?.verify_webhook(&headers, &body) {
+        Err(AppError::Unauthorized) => {
+            warn!(client_ip = %client_ip, forge = kind.as_str(), "rejected webhook with invalid signature");
+            return Err(AppError::Unauthorized);
+        }
+        delivery => delivery?,
+    };
+    let (event, delivery_id) = (delivery.event, delivery.id);
+
+    let payload: Value = serde_json::from_slice(&body)
+        .map_err(|_| AppError::BadRequest("webhook payload is not valid json".into()))?;
+    let action = payload.get("action").and_then(Value::as_str).map(str::to_string);
+
+    let mut tx = state.pool.begin().await?;
+    let stored = sqlx::query(
+        r#"
+        INSERT INTO github_webhook_deliveries (delivery_id, forge, event, action, payload)
+        VALUES ($1, $5, $2, $3, $4)
+        ON CONFLICT (delivery_id) DO NOTHING
+        "#,
+    )
+    .bind(&delivery_id)
+    .bind(&event)
+    .bind(&action)
+    .bind(&payload)
+    .bind(kind.as_str())
+    .execute(&mut *tx)
+    .await?;
+
+    if stored.rows_affected() == 0 {
+        info!(delivery_id = %delivery_id, "ignoring duplicate webhook delivery");
+        return Ok(StatusCode::OK);
+    }
+
+    enqueue(&mut *tx, &Job::WebhookDelivery { delivery_id }).await?;
+    tx.commit().await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -1,3 +1,4 @@
 pub mod csrf;
 pub mod rate_limit;
+pub mod token_cipher;
 
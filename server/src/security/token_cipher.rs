@@ -0,0 +1,106 @@
+use crate::error::AppError;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::RngCore;
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts secrets at rest with AES-256-GCM. Every ciphertext records the ID
+/// of the key that produced it, so older keys can stay configured for
+/// decryption while new writes use the first (primary) key.
+#[derive(Clone)]
+pub struct TokenCipher {
+    primary_key_id: String,
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+pub struct Sealed {
+    pub key_id: String,
+    /// Nonce followed by the ciphertext and tag.
+    pub bytes: Vec<u8>,
+}
+
+impl TokenCipher {
+    /// Accepts `key-id:base64-key` entries; each key must decode to 32 bytes.
+    pub fn new(entries: &[String]) -> Result<Self, AppError> {
+        let mut keys: Vec<(String, Aes256Gcm)> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let (key_id, encoded) = entry
+                .split_once(':')
+                .ok_or_else(|| AppError::Config("token encryption keys must look like key-id:base64-key".into()))?;
+            let key_id = key_id.trim();
+            if key_id.is_empty() || keys.iter().any(|(existing, _)| existing == key_id) {
+                return Err(AppError::Config(format!("invalid or duplicate token key id: {:?}", key_id)));
+            }
+            let key = STANDARD
+                .decode(encoded.trim())
+                .map_err(|_| AppError::Config(format!("token key {} is not valid base64", key_id)))?;
+            let cipher = Aes256Gcm::new_from_slice(&key)
+                .map_err(|_| AppError::Config(format!("token key {} must be 32 bytes", key_id)))?;
+            keys.push((key_id.to_string(), cipher));
+        }
+
+        let primary_key_id = keys
+            .first()
+            .map(|(key_id, _)| key_id.clone())
+            .ok_or_else(|| AppError::Config("no token encryption keys configured".into()))?;
+
+        Ok(Self { primary_key_id, keys })
+    }
+
+    pub fn primary_key_id(&self) -> &str {
+        &self.primary_key_id
+    }
+
+    /// `context` is bound to the ciphertext as associated data, so a value
+    /// copied to another row fails to decrypt.
+    pub fn seal(&self, plaintext: &str, context: &[u8]) -> Result<Sealed, AppError> {
+        let (key_id, cipher) = &self.keys[0];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: context,
+                },
+            )
+            .map_err(|_| AppError::Internal)?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&ciphertext);
+        Ok(Sealed {
+            key_id: key_id.clone(),
+            bytes,
+        })
+    }
+
+    pub fn open(&self, key_id: &str, bytes: &[u8], context: &[u8]) -> Result<String, AppError> {
+        let (_, cipher) = self
+            .keys
+            .iter()
+            .find(|(candidate, _)| candidate == key_id)
+            .ok_or_else(|| AppError::Token(format!("token encryption key {} is no longer configured", key_id)))?;
+        if bytes.len() <= NONCE_LEN {
+            return Err(AppError::Token("stored token is truncated".into()));
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: context,
+                },
+            )
+            .map_err(|_| AppError::Token("stored token could not be decrypted".into()))?;
+
+        String::from_utf8(plaintext).map_err(|_| AppError::Token("stored token is not utf-8".into()))
+    }
+}
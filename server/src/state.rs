@@ -1,5 +1,13 @@
-use crate::{config::AppConfig, security::rate_limit::RateLimiter, session::SessionSigner};
-use oauth2::basic::BasicClient;
+use crate::{
+    config::AppConfig,
+    error::AppError,
+    forge::{ForgeProvider, Forges},
+    github::{app::GitHubApp, client::GitHubClient, forge::GitHubForge},
+    gitea::forge::GiteaForge,
+    gitlab::forge::GitLabForge,
+    security::{rate_limit::RateLimiter, token_cipher::TokenCipher},
+    session::SessionSigner,
+};
 use reqwest::Client;
 use sqlx::PgPool;
 use std::sync::Arc;
@@ -8,10 +16,14 @@ use std::sync::Arc;
 pub struct AppState {
     pub pool: PgPool,
     pub session_signer: SessionSigner,
-    pub oauth_client: BasicClient,
+    pub token_cipher: TokenCipher,
     pub http_client: Client,
+    pub github: GitHubClient,
+    pub forges: Forges,
+    pub github_app: Option<GitHubApp>,
     pub config: AppConfig,
     pub auth_rate_limiter: RateLimiter,
+    pub webhook_rate_limiter: RateLimiter,
 }
 
 pub type SharedState = Arc<AppState>;
@@ -20,19 +32,40 @@ impl AppState {
     pub fn new(
         pool: PgPool,
         session_signer: SessionSigner,
-        oauth_client: BasicClient,
+        token_cipher: TokenCipher,
         http_client: Client,
+        github_app: Option<GitHubApp>,
         config: AppConfig,
         auth_rate_limiter: RateLimiter,
-    ) -> Self {
-        Self {
+        webhook_rate_limiter: RateLimiter,
+    ) -> Result<Self, AppError> {
+        let github = GitHubClient::new(http_client.clone());
+        let gitlab = match &config.gitlab {
+            Some(gitlab) => Some(Arc::new(GitLabForge::new(&config, gitlab, http_client.clone())?) as Arc<dyn ForgeProvider>),
+            None => None,
+        };
+        let gitea = match &config.gitea {
+            Some(gitea) => Some(Arc::new(GiteaForge::new(&config, gitea, http_client.clone())?) as Arc<dyn ForgeProvider>),
+            None => None,
+        };
+        let forges = Forges::new(
+            Arc::new(GitHubForge::new(&config, github.clone(), http_client.clone())?),
+            gitlab,
+            gitea,
+        );
+
+        Ok(Self {
             pool,
             session_signer,
-            oauth_client,
+            token_cipher,
+            github,
+            forges,
             http_client,
+            github_app,
             config,
             auth_rate_limiter,
-        }
+            webhook_rate_limiter,
+        })
     }
 
     pub fn shared(self) -> SharedState {
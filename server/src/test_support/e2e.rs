@@ -0,0 +1,717 @@
+use super::{
+    fake_github::sign_payload, set_cookies, test_config, test_database, test_router, test_state, unused_pool, with_gitea,
+    with_gitlab, FakeEmail, FakeGitHub, FakeGitLab, FakeUser,
+};
+use crate::{
+    error::AppError,
+    forge::{Dispatch, ForgeKind},
+    gitea::webhooks::verify_signature,
+    github::sync::{sync_task, SyncContext, SyncOutcome},
+    jobs::{enqueue, queue::retry_job, Job},
+    routes::{insert_cycle, insert_task},
+    state::SharedState,
+};
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use rand::Rng;
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn fake_user() -> FakeUser {
+    let id = rand::thread_rng().gen_range(1_000_000..i64::from(i32::MAX));
+    FakeUser {
+        id,
+        login: format!("octo{}", id),
+        name: Some("Octo Cat".to_string()),
+        emails: vec![
+            FakeEmail {
+                email: format!("octo{}@users.noreply.example", id),
+                primary: false,
+                verified: true,
+            },
+            FakeEmail {
+                email: format!("Octo{}@Example.com", id),
+                primary: true,
+                verified: true,
+            },
+        ],
+    }
+}
+
+struct LinkedTask {
+    project_id: Uuid,
+    task_id: Uuid,
+    repository: String,
+}
+
+/// A new project with one task linked to issue #7 of its own fake repository;
+/// node IDs are unique across the database, so runs must not share issues.
+async fn linked_task(pool: &PgPool, github: &FakeGitHub) -> LinkedTask {
+    let key = format!("T{}", &Uuid::new_v4().simple().to_string()[..6].to_ascii_uppercase());
+    let repository = format!("acme/widgets-{}", key.to_lowercase());
+    github.add_issue(&repository, 7, "Widgets wobble", Some("Seen on staging"));
+
+    let project_id = sqlx::query_scalar::<_, Uuid>("INSERT INTO projects (key, name) VALUES ($1, 'Sync test') RETURNING id")
+        .bind(&key)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    let task = insert_task(&mut conn, project_id, "Widgets wobble", Some("Seen on staging"), None)
+        .await
+        .unwrap();
+    let issue = github.issue(&repository, 7).unwrap();
+    sqlx::query(
+        "UPDATE tasks SET github_node_id = $2, github_repository = $3, github_issue_number = 7, github_url = $4 WHERE id = $1",
+    )
+    .bind(task.id)
+    .bind(issue["node_id"].as_str())
+    .bind(&repository)
+    .bind(issue["html_url"].as_str())
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    LinkedTask {
+        project_id,
+        task_id: task.id,
+        repository,
+    }
+}
+
+/// Runs the OAuth web flow against the fake GitHub for whichever user it
+/// has signed in, returning the session and CSRF cookies.
+async fn sign_in(router: &Router, state: &SharedState) -> String {
+    sign_in_with(router, state, ForgeKind::GitHub).await
+}
+
+async fn sign_in_with(router: &Router, state: &SharedState, forge: ForgeKind) -> String {
+    let login = router
+        .clone()
+        .oneshot(
+            Request::get(format!("/auth/{}/login", forge.as_str()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let oauth_cookies = set_cookies(&login).join("; ");
+    let authorize_url = login.headers()[header::LOCATION].to_str().unwrap().to_string();
+
+    // The user approves on the forge, which redirects back with a code.
+    let approved = state.http_client.get(&authorize_url).send().await.unwrap();
+    let callback = approved.headers()[header::LOCATION].to_str().unwrap();
+    let callback_path = callback.strip_prefix(&state.config.app_base_url).unwrap().to_string();
+
+    let response = router
+        .clone()
+        .oneshot(
+            Request::get(callback_path)
+                .header(header::COOKIE, oauth_cookies)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    set_cookies(&response)
+        .into_iter()
+        .filter(|cookie| cookie.starts_with("kf_session=") || cookie.starts_with("kf_csrf="))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Names and colors of a task's labels, by name.
+async fn task_labels(conn: &mut PgConnection, task_id: Uuid) -> Vec<(String, String)> {
+    sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT l.name, l.color FROM task_labels tl JOIN labels l ON l.id = tl.label_id
+        WHERE tl.task_id = $1 ORDER BY l.name
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(conn)
+    .await
+    .unwrap()
+}
+
+async fn json_body(response: axum::response::Response) -> Value {
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.expect("response body");
+    serde_json::from_slice(&bytes).expect("json body")
+}
+
+#[tokio::test]
+async fn login_redirects_to_configured_github() {
+    let github = FakeGitHub::start().await;
+    let router = test_router(test_state(test_config(&github), unused_pool()));
+
+    let response = router
+        .oneshot(Request::get("/auth/github/login").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    let location = response.headers()[header::LOCATION].to_str().unwrap();
+    assert!(location.starts_with(&format!("{}/login/oauth/authorize?", github.web_url())));
+    assert!(location.contains("user%3Aemail"));
+    let cookies = set_cookies(&response);
+    assert!(cookies.iter().any(|cookie| cookie.starts_with("kf_oauth_state=")));
+    assert!(cookies.iter().any(|cookie| cookie.starts_with("kf_oauth_pkce=")));
+}
+
+#[tokio::test]
+async fn github_client_retries_server_errors() {
+    let github = FakeGitHub::start().await;
+    let user = fake_user();
+    github.add_user(user.clone());
+    let token = github.issue_token(user.id);
+    let state = test_state(test_config(&github), unused_pool());
+
+    github.fail_next(StatusCode::BAD_GATEWAY, &[]);
+    let response = state
+        .github
+        .get(format!("{}/user", github.api_url()))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(github.requests(), vec!["GET /api/v3/user", "GET /api/v3/user"]);
+}
+
+#[tokio::test]
+async fn github_client_reports_exhausted_quota() {
+    let github = FakeGitHub::start().await;
+    let state = test_state(test_config(&github), unused_pool());
+    let reset = (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp().to_string();
+
+    github.fail_next(
+        StatusCode::FORBIDDEN,
+        &[
+            ("x-ratelimit-limit", "60"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset),
+            ("x-ratelimit-resource", "core"),
+        ],
+    );
+    let result = state.github.get(format!("{}/user", github.api_url())).send().await;
+
+    assert!(matches!(result, Err(AppError::GitHubRateLimited(_))));
+    let quotas = state.github.quotas();
+    assert_eq!(quotas.len(), 1);
+    assert_eq!((quotas[0].token.as_str(), quotas[0].remaining), ("anonymous", 0));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn login_creates_session_with_verified_email() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let user = fake_user();
+    github.add_user(user.clone());
+    let state = test_state(test_config(&github), pool);
+    let router = test_router(state.clone());
+
+    let session_cookies = sign_in(&router, &state).await;
+
+    let me = router
+        .oneshot(
+            Request::get("/me")
+                .header(header::COOKIE, session_cookies)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(me.status(), StatusCode::OK);
+    let me = json_body(me).await;
+    assert_eq!(me["login"], json!(user.login));
+    assert_eq!(me["email"], json!(format!("octo{}@example.com", user.id)));
+    assert_eq!(me["email_verified"], json!(true));
+}
+
+#[tokio::test]
+async fn webhook_with_bad_signature_is_rejected() {
+    let github = FakeGitHub::start().await;
+    let router = test_router(test_state(test_config(&github), unused_pool()));
+
+    let request = github.webhook_request("not-the-secret", "ping", &json!({ "zen": "Keep it logically awesome." }));
+    let response = router.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn webhook_delivery_is_accepted_once() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let state = test_state(test_config(&github), pool);
+    let router = test_router(state.clone());
+    let secret = state.config.github_webhook_secret.clone().unwrap();
+
+    let request = github.webhook_request(&secret, "ping", &json!({ "zen": "Design for failure." }));
+    let delivery_id = request.headers()["x-github-delivery"].to_str().unwrap().to_string();
+    let retry = github.webhook_request(&secret, "ping", &json!({ "zen": "Design for failure." }));
+    let (mut retry_parts, retry_body) = retry.into_parts();
+    retry_parts.headers.insert("x-github-delivery", delivery_id.parse().unwrap());
+    let retry = Request::from_parts(retry_parts, retry_body);
+
+    let first = router.clone().oneshot(request).await.unwrap();
+    let second = router.oneshot(retry).await.unwrap();
+
+    assert_eq!(first.status(), StatusCode::ACCEPTED);
+    assert_eq!(second.status(), StatusCode::OK);
+    let queued = sqlx::query_scalar::<_, i64>("SELECT count(*) FROM jobs WHERE unique_key = $1")
+        .bind(format!("webhook_delivery:{}", delivery_id))
+        .fetch_one(&state.pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 1);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn dead_jobs_are_retried_unless_already_queued() {
+    let pool = test_database().await;
+    let import_id = Uuid::new_v4();
+    let job = Job::Import { import_id };
+    let unique_key = format!("import:{}", import_id);
+
+    assert!(enqueue(&pool, &job).await.unwrap());
+    assert!(!enqueue(&pool, &job).await.unwrap());
+
+    let dead_id = sqlx::query_scalar::<_, Uuid>(
+        "UPDATE jobs SET status = 'dead', attempts = 3, last_error = 'boom' WHERE unique_key = $1 RETURNING id",
+    )
+    .bind(&unique_key)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert!(enqueue(&pool, &job).await.unwrap());
+    assert!(matches!(retry_job(&pool, dead_id).await, Err(AppError::Conflict(_))));
+
+    sqlx::query("DELETE FROM jobs WHERE unique_key = $1 AND status = 'queued'")
+        .bind(&unique_key)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let retried = retry_job(&pool, dead_id).await.unwrap();
+    assert_eq!((retried.status.as_str(), retried.attempts), ("queued", 0));
+    assert!(matches!(retry_job(&pool, dead_id).await, Err(AppError::Conflict(_))));
+
+    sqlx::query("DELETE FROM jobs WHERE unique_key = $1").bind(&unique_key).execute(&pool).await.unwrap();
+    assert!(matches!(retry_job(&pool, dead_id).await, Err(AppError::NotFound)));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn sync_pushes_local_edits_and_pulls_remote_ones() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let user = fake_user();
+    github.add_user(user.clone());
+    let token = github.issue_token(user.id);
+    let state = test_state(test_config(&github), pool.clone());
+    let linked = linked_task(&pool, &github).await;
+    let (task_id, repository) = (linked.task_id, linked.repository.as_str());
+    let mut conn = pool.acquire().await.unwrap();
+
+    let ctx = SyncContext {
+        pool: &pool,
+        forge: state.forges.github(),
+        access_token: Some(&token),
+    };
+    sync_task(&ctx, task_id, None).await.unwrap();
+
+    sqlx::query("UPDATE tasks SET title = 'Widgets wobble on resize' WHERE id = $1")
+        .bind(task_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let pushed = sync_task(&ctx, task_id, None).await.unwrap();
+    assert!(matches!(pushed, SyncOutcome::Synced { pushed: 1, .. }));
+    assert_eq!(github.issue(repository, 7).unwrap()["title"], json!("Widgets wobble on resize"));
+
+    github.edit_issue(repository, 7, |issue| issue["body"] = json!("Seen on staging and production"));
+    let pulled = sync_task(&ctx, task_id, None).await.unwrap();
+    assert!(matches!(pulled, SyncOutcome::Synced { pulled: 1, .. }));
+    let description = sqlx::query_scalar::<_, Option<String>>("SELECT description FROM tasks WHERE id = $1")
+        .bind(task_id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(description.as_deref(), Some("Seen on staging and production"));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn sync_maps_labels_and_milestones_by_id() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let state = test_state(test_config(&github), pool.clone());
+    let linked = linked_task(&pool, &github).await;
+    let repository = linked.repository.as_str();
+    let mut conn = pool.acquire().await.unwrap();
+
+    let suffix = linked.project_id.simple().to_string();
+    let bug = format!("bug-{}", suffix);
+    let triage = format!("triage-{}", suffix);
+    let bug_id = sqlx::query_scalar::<_, Uuid>("INSERT INTO labels (name) VALUES ($1) RETURNING id")
+        .bind(&bug)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO github_label_mappings (repository, github_name, label_id) VALUES ($1, 'type:bug', $2)")
+        .bind(repository)
+        .bind(bug_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let today = chrono::Utc::now().date_naive();
+    let cycle = insert_cycle(&mut conn, linked.project_id, today, today + chrono::Duration::days(14))
+        .await
+        .unwrap();
+
+    github.edit_issue(repository, 7, |issue| {
+        issue["labels"] = json!([
+            { "node_id": format!("LA_bug_{}", suffix), "name": "type:bug", "color": "d73a4a" },
+            { "node_id": format!("LA_triage_{}", suffix), "name": triage, "color": "FBCA04" },
+        ]);
+        issue["milestone"] = json!({
+            "node_id": format!("MI_{}", suffix),
+            "number": 1,
+            "title": "1.0",
+            "state": "open",
+            "due_on": chrono::Utc::now() + chrono::Duration::days(7),
+        });
+    });
+    let ctx = SyncContext {
+        pool: &pool,
+        forge: state.forges.github(),
+        access_token: None,
+    };
+    sync_task(&ctx, linked.task_id, None).await.unwrap();
+
+    let labels = task_labels(&mut conn, linked.task_id).await;
+    assert_eq!(
+        labels,
+        vec![(bug.clone(), "6b7280".to_string()), (triage.clone(), "fbca04".to_string())]
+    );
+    let (cycle_id, cycle_name) = sqlx::query_as::<_, (Option<Uuid>, Option<String>)>(
+        "SELECT t.cycle_id, c.name FROM tasks t LEFT JOIN cycles c ON c.id = t.cycle_id WHERE t.id = $1",
+    )
+    .bind(linked.task_id)
+    .fetch_one(&mut *conn)
+    .await
+    .unwrap();
+    assert_eq!((cycle_id, cycle_name.as_deref()), (Some(cycle.id), Some("1.0")));
+
+    // Renamed on GitHub: the mapping follows the label's ID, a mirrored
+    // label takes the new name and the cycle follows the milestone.
+    github.edit_issue(repository, 7, |issue| {
+        issue["labels"][0]["name"] = json!("kind:bug");
+        issue["labels"][1]["name"] = json!(format!("needs-{}", triage));
+        issue["milestone"]["title"] = json!("1.0 \"Wobble-free\"");
+    });
+    sync_task(&ctx, linked.task_id, None).await.unwrap();
+
+    let labels = task_labels(&mut conn, linked.task_id).await;
+    assert_eq!(
+        labels,
+        vec![(bug, "6b7280".to_string()), (format!("needs-{}", triage), "fbca04".to_string())]
+    );
+    let github_name = sqlx::query_scalar::<_, String>("SELECT github_name FROM github_label_mappings WHERE label_id = $1")
+        .bind(bug_id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(github_name, "kind:bug");
+    let cycle_name = sqlx::query_scalar::<_, Option<String>>("SELECT name FROM cycles WHERE id = $1")
+        .bind(cycle.id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(cycle_name.as_deref(), Some("1.0 \"Wobble-free\""));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn github_assignees_become_claimable_users() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let (maintainer, outsider) = (fake_user(), fake_user());
+    github.add_user(maintainer.clone());
+    github.add_user(outsider.clone());
+    let token = github.issue_token(maintainer.id);
+    let state = test_state(test_config(&github), pool.clone());
+    let router = test_router(state.clone());
+    let linked = linked_task(&pool, &github).await;
+    github.add_collaborator(&linked.repository, maintainer.id);
+
+    github.edit_issue(&linked.repository, 7, |issue| {
+        issue["assignees"] = json!([{ "id": maintainer.id, "login": maintainer.login, "avatar_url": null }]);
+    });
+    let ctx = SyncContext {
+        pool: &pool,
+        forge: state.forges.github(),
+        access_token: Some(&token),
+    };
+    sync_task(&ctx, linked.task_id, None).await.unwrap();
+
+    let (assignee_id, github_id, claimed) = sqlx::query_as::<_, (Uuid, i64, bool)>(
+        "SELECT u.id, u.github_id, u.claimed FROM tasks t JOIN users u ON u.id = t.assignee_id WHERE t.id = $1",
+    )
+    .bind(linked.task_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((github_id, claimed), (maintainer.id, false));
+
+    // Signing in claims the placeholder instead of creating a second user.
+    github.sign_in_as(maintainer.id);
+    let cookies = sign_in(&router, &state).await;
+    let me = router
+        .oneshot(Request::get("/me").header(header::COOKIE, cookies).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(json_body(me).await["id"], json!(assignee_id));
+    let claimed = sqlx::query_scalar::<_, bool>("SELECT claimed FROM users WHERE id = $1")
+        .bind(assignee_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(claimed);
+
+    // Someone without access to the repository stays assigned in Keyflow
+    // only; GitHub keeps its assignee and the next sync does not undo it.
+    let outsider_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO users (github_id, login, claimed) VALUES ($1, $2, false) RETURNING id",
+    )
+    .bind(outsider.id)
+    .bind(&outsider.login)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    sqlx::query("UPDATE tasks SET assignee_id = $2 WHERE id = $1")
+        .bind(linked.task_id)
+        .bind(outsider_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let outcome = sync_task(&ctx, linked.task_id, None).await.unwrap();
+
+    assert_eq!(outcome, SyncOutcome::InSync);
+    assert_eq!(
+        github.issue(&linked.repository, 7).unwrap()["assignees"][0]["login"],
+        json!(maintainer.login)
+    );
+    let assignee = sqlx::query_scalar::<_, Option<Uuid>>("SELECT assignee_id FROM tasks WHERE id = $1")
+        .bind(linked.task_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(assignee, Some(outsider_id));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn issue_comment_author_is_matched_by_github_id() {
+    let pool = test_database().await;
+    let github = FakeGitHub::start().await;
+    let state = test_state(test_config(&github), pool.clone());
+    let linked = linked_task(&pool, &github).await;
+    let commenter = fake_user();
+
+    // A GitLab account with the same login must neither break nor take the lookup.
+    sqlx::query("INSERT INTO users (gitlab_id, login, claimed) VALUES ($1, $2, false)")
+        .bind(commenter.id)
+        .bind(&commenter.login)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let author_id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO users (github_id, login, claimed) VALUES ($1, $2, false) RETURNING id",
+    )
+    .bind(commenter.id)
+    .bind(&commenter.login)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let comment_id = rand::thread_rng().gen_range(1_000_000..i64::from(i32::MAX));
+    let payload = json!({
+        "action": "created",
+        "issue": github.issue(&linked.repository, 7).unwrap(),
+        "comment": {
+            "id": comment_id,
+            "body": "Still wobbling",
+            "user": { "id": commenter.id, "login": commenter.login },
+        },
+    });
+    let dispatched = state
+        .forges
+        .github()
+        .handle_webhook(&state, "issue_comment", payload)
+        .await
+        .unwrap();
+
+    assert_eq!(dispatched, Dispatch::Processed);
+    let stored = sqlx::query_scalar::<_, Option<Uuid>>("SELECT author_id FROM task_comments WHERE github_comment_id = $1")
+        .bind(comment_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, Some(author_id));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn gitlab_login_creates_user_with_gitlab_id() {
+    let pool = test_database().await;
+    let (github, gitlab) = (FakeGitHub::start().await, FakeGitLab::start().await);
+    let user = fake_user();
+    gitlab.add_user(user.clone());
+    let state = test_state(with_gitlab(test_config(&github), &gitlab, None), pool.clone());
+    let router = test_router(state.clone());
+
+    let session_cookies = sign_in_with(&router, &state, ForgeKind::GitLab).await;
+
+    let me = router
+        .oneshot(
+            Request::get("/me")
+                .header(header::COOKIE, session_cookies)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let me = json_body(me).await;
+    assert_eq!(me["login"], json!(user.login));
+    let (github_id, gitlab_id) =
+        sqlx::query_as::<_, (Option<i64>, Option<i64>)>("SELECT github_id, gitlab_id FROM users WHERE id = $1")
+            .bind(Uuid::parse_str(me["id"].as_str().unwrap()).unwrap())
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!((github_id, gitlab_id), (None, Some(user.id)));
+    let forges = sqlx::query_scalar::<_, String>(
+        "SELECT t.forge FROM user_github_tokens t JOIN users u ON u.id = t.user_id WHERE u.gitlab_id = $1",
+    )
+    .bind(user.id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(forges, vec!["gitlab"]);
+}
+
+#[tokio::test]
+async fn gitlab_webhook_with_bad_token_is_rejected() {
+    let (github, gitlab) = (FakeGitHub::start().await, FakeGitLab::start().await);
+    let router = test_router(test_state(with_gitlab(test_config(&github), &gitlab, None), unused_pool()));
+
+    let request = gitlab.webhook_request("not-the-token", "Issue Hook", &json!({ "object_kind": "issue" }));
+    let response = router.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn gitlab_issue_hook_pulls_changes_and_sync_pushes_edits() {
+    let pool = test_database().await;
+    let (github, gitlab) = (FakeGitHub::start().await, FakeGitLab::start().await);
+    let user = fake_user();
+    gitlab.add_user(user.clone());
+    let token = gitlab.issue_token(user.id);
+    let state = test_state(with_gitlab(test_config(&github), &gitlab, Some(token.clone())), pool.clone());
+    let forge = state.forges.get(ForgeKind::GitLab).unwrap();
+
+    let key = format!("T{}", &Uuid::new_v4().simple().to_string()[..6].to_ascii_uppercase());
+    let project = format!("acme/platform/widgets-{}", key.to_lowercase());
+    let issue_id = gitlab.add_issue(&project, 3, "Widgets wobble", None);
+    let project_id = sqlx::query_scalar::<_, Uuid>("INSERT INTO projects (key, name) VALUES ($1, 'GitLab test') RETURNING id")
+        .bind(&key)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    let task = insert_task(&mut conn, project_id, "Widgets wobble", None, None).await.unwrap();
+    sqlx::query(
+        r#"
+        UPDATE tasks
+        SET forge = 'gitlab', github_node_id = $2, github_repository = $3, github_issue_number = 3, github_url = $4
+        WHERE id = $1
+        "#,
+    )
+    .bind(task.id)
+    .bind(format!("gid://gitlab/Issue/{}", issue_id))
+    .bind(&project)
+    .bind(gitlab.issue(&project, 3).unwrap()["web_url"].as_str())
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    gitlab.edit_issue(&project, 3, |issue| issue["description"] = json!("Seen on staging"));
+    let hook = json!({
+        "object_kind": "issue",
+        "project": { "path_with_namespace": project },
+        "object_attributes": { "iid": 3, "action": "update" },
+    });
+    let dispatched = forge.handle_webhook(&state, "Issue Hook", hook).await.unwrap();
+    assert_eq!(dispatched, Dispatch::Processed);
+    let description = sqlx::query_scalar::<_, Option<String>>("SELECT description FROM tasks WHERE id = $1")
+        .bind(task.id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(description.as_deref(), Some("Seen on staging"));
+
+    sqlx::query("UPDATE tasks SET title = 'Widgets wobble on resize', status = 'done' WHERE id = $1")
+        .bind(task.id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let ctx = SyncContext {
+        pool: &pool,
+        forge,
+        access_token: Some(&token),
+    };
+    let pushed = sync_task(&ctx, task.id, None).await.unwrap();
+
+    assert!(matches!(pushed, SyncOutcome::Synced { pushed: 2, .. }));
+    let issue = gitlab.issue(&project, 3).unwrap();
+    assert_eq!(
+        (&issue["title"], &issue["state"]),
+        (&json!("Widgets wobble on resize"), &json!("closed"))
+    );
+}
+
+#[tokio::test]
+async fn gitea_webhook_signature_is_bare_hex() {
+    let github = FakeGitHub::start().await;
+    let router = test_router(test_state(with_gitea(test_config(&github), "http://gitea.invalid"), unused_pool()));
+    let body = br#"{"action":"opened"}"#;
+    let signature = sign_payload("test-gitea-webhook-secret", body);
+    let hex_digest = signature.strip_prefix("sha256=").unwrap();
+
+    assert!(verify_signature("test-gitea-webhook-secret", body, Some(hex_digest)));
+    assert!(!verify_signature("test-gitea-webhook-secret", body, Some(&signature)));
+
+    let request = Request::post("/webhooks/gitea")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("x-gitea-event", "issues")
+        .header("x-gitea-delivery", Uuid::new_v4().to_string())
+        .header("x-gitea-signature", sign_payload("not-the-secret", body).strip_prefix("sha256=").unwrap())
+        .body(Body::from(body.to_vec()))
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,406 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// An in-process stand-in for GitHub (laid out like Enterprise Server, with
+/// the API under `/api/v3`) serving the OAuth web flow, `/user`,
+/// `/user/emails` and repository issues from memory.
+pub struct FakeGitHub {
+    addr: SocketAddr,
+    inner: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Clone)]
+pub struct FakeUser {
+    pub id: i64,
+    pub login: String,
+    pub name: Option<String>,
+    pub emails: Vec<FakeEmail>,
+}
+
+#[derive(Clone)]
+pub struct FakeEmail {
+    pub email: String,
+    pub primary: bool,
+    pub verified: bool,
+}
+
+#[derive(Default)]
+struct FakeState {
+    users: Vec<FakeUser>,
+    /// User the authorize endpoint signs in, as if they clicked "Authorize".
+    signed_in: Option<i64>,
+    codes: HashMap<String, i64>,
+    tokens: HashMap<String, i64>,
+    issues: HashMap<String, Vec<Value>>,
+    collaborators: HashMap<String, Vec<i64>>,
+    failures: VecDeque<(StatusCode, Vec<(String, String)>)>,
+    requests: Vec<String>,
+}
+
+impl FakeGitHub {
+    /// Binds a random local port and serves until the test's runtime ends.
+    pub async fn start() -> Self {
+        let inner = Arc::new(Mutex::new(FakeState::default()));
+        let app = Router::new()
+            .route("/login/oauth/authorize", get(authorize))
+            .route("/login/oauth/access_token", post(access_token))
+            .route("/api/v3/user", get(user))
+            .route("/api/v3/user/emails", get(user_emails))
+            .route("/api/v3/repos/:owner/:name/issues", get(list_issues))
+            .route("/api/v3/repos/:owner/:name/assignees/:login", get(check_assignee))
+            .route(
+                "/api/v3/repos/:owner/:name/issues/:number",
+                get(get_issue).patch(update_issue),
+            )
+            .layer(middleware::from_fn_with_state(inner.clone(), record_and_fail))
+            .with_state(inner.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind fake github");
+        let addr = listener.local_addr().expect("fake github address");
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("fake github server");
+        });
+
+        Self { addr, inner }
+    }
+
+    pub fn web_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn api_url(&self) -> String {
+        format!("http://{}/api/v3", self.addr)
+    }
+
+    /// Adds a user; the first one added is the one who signs in.
+    pub fn add_user(&self, user: FakeUser) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.signed_in.get_or_insert(user.id);
+        inner.users.push(user);
+    }
+
+    pub fn sign_in_as(&self, user_id: i64) {
+        self.inner.lock().unwrap().signed_in = Some(user_id);
+    }
+
+    /// A token for the user, as the OAuth flow would have issued.
+    pub fn issue_token(&self, user_id: i64) -> String {
+        let token = format!("gho_{}", Uuid::new_v4().simple());
+        self.inner.lock().unwrap().tokens.insert(token.clone(), user_id);
+        token
+    }
+
+    /// Lets the user be assigned issues in the repository.
+    pub fn add_collaborator(&self, repository: &str, user_id: i64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .collaborators
+            .entry(repository.to_string())
+            .or_default()
+            .push(user_id);
+    }
+
+    pub fn add_issue(&self, repository: &str, number: i32, title: &str, body: Option<&str>) {
+        let issue = json!({
+            "node_id": format!("I_{}_{}", repository.replace('/', "_"), number),
+            "number": number,
+            "title": title,
+            "body": body,
+            "state": "open",
+            "state_reason": null,
+            "html_url": format!("{}/{}/issues/{}", self.web_url(), repository, number),
+            "updated_at": Utc::now(),
+            "labels": [],
+            "assignees": [],
+        });
+        self.inner
+            .lock()
+            .unwrap()
+            .issues
+            .entry(repository.to_string())
+            .or_default()
+            .push(issue);
+    }
+
+    /// Changes an issue as if someone edited it on GitHub.
+    pub fn edit_issue(&self, repository: &str, number: i32, edit: impl FnOnce(&mut Value)) {
+        let mut inner = self.inner.lock().unwrap();
+        let issue = inner
+            .issues
+            .get_mut(repository)
+            .and_then(|issues| issues.iter_mut().find(|issue| issue["number"] == number))
+            .expect("issue exists");
+        edit(issue);
+        issue["updated_at"] = json!(Utc::now());
+    }
+
+    pub fn issue(&self, repository: &str, number: i32) -> Option<Value> {
+        let inner = self.inner.lock().unwrap();
+        find_issue(&inner, repository, number).cloned()
+    }
+
+    /// Answers the next request with `status` and `headers` instead of
+    /// serving it, to simulate outages and rate limits.
+    pub fn fail_next(&self, status: StatusCode, headers: &[(&str, &str)]) {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        self.inner.lock().unwrap().failures.push_back((status, headers));
+    }
+
+    /// `METHOD /path` of every request received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.inner.lock().unwrap().requests.clone()
+    }
+
+    /// A webhook delivery as GitHub would send it, signed with `secret`.
+    pub fn webhook_request(&self, secret: &str, event: &str, payload: &Value) -> Request {
+        let body = serde_json::to_vec(payload).expect("webhook payload");
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/webhooks/github")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-github-event", event)
+            .header("x-github-delivery", Uuid::new_v4().to_string())
+            .header("x-hub-signature-256", sign_payload(secret, &body))
+            .body(Body::from(body))
+            .expect("webhook request")
+    }
+}
+
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac key");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+type Shared = Arc<Mutex<FakeState>>;
+
+async fn record_and_fail(State(inner): State<Shared>, request: Request, next: Next) -> Response {
+    let failure = {
+        let mut inner = inner.lock().unwrap();
+        inner
+            .requests
+            .push(format!("{} {}", request.method(), request.uri().path()));
+        inner.failures.pop_front()
+    };
+
+    match failure {
+        Some((status, headers)) => {
+            let mut response = (status, Json(json!({ "message": "simulated failure" }))).into_response();
+            for (name, value) in headers {
+                response.headers_mut().insert(
+                    header::HeaderName::try_from(name).expect("header name"),
+                    value.parse().expect("header value"),
+                );
+            }
+            response
+        }
+        None => next.run(request).await,
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    redirect_uri: String,
+    state: String,
+}
+
+async fn authorize(State(inner): State<Shared>, Query(query): Query<AuthorizeQuery>) -> Response {
+    let mut inner = inner.lock().unwrap();
+    let Some(user_id) = inner.signed_in else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let code = Uuid::new_v4().simple().to_string();
+    inner.codes.insert(code.clone(), user_id);
+
+    Redirect::to(&format!("{}?code={}&state={}", query.redirect_uri, code, query.state)).into_response()
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    code: String,
+}
+
+async fn access_token(State(inner): State<Shared>, Form(form): Form<TokenRequest>) -> Response {
+    let mut inner = inner.lock().unwrap();
+    let Some(user_id) = inner.codes.remove(&form.code) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" }))).into_response();
+    };
+    let token = format!("gho_{}", Uuid::new_v4().simple());
+    inner.tokens.insert(token.clone(), user_id);
+
+    Json(json!({
+        "access_token": token,
+        "token_type": "bearer",
+        "scope": "read:user,user:email",
+    }))
+    .into_response()
+}
+
+fn authenticated(inner: &FakeState, headers: &HeaderMap) -> Option<FakeUser> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    let user_id = inner.tokens.get(token)?;
+    inner.users.iter().find(|user| user.id == *user_id).cloned()
+}
+
+async fn user(State(inner): State<Shared>, headers: HeaderMap) -> Response {
+    let inner = inner.lock().unwrap();
+    let Some(user) = authenticated(&inner, &headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    Json(json!({
+        "id": user.id,
+        "login": user.login,
+        "name": user.name,
+        "avatar_url": null,
+        // Like most real accounts, the profile email is kept private.
+        "email": null,
+    }))
+    .into_response()
+}
+
+async fn user_emails(State(inner): State<Shared>, headers: HeaderMap) -> Response {
+    let inner = inner.lock().unwrap();
+    let Some(user) = authenticated(&inner, &headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let emails: Vec<Value> = user
+        .emails
+        .iter()
+        .map(|email| {
+            json!({
+                "email": email.email,
+                "primary": email.primary,
+                "verified": email.verified,
+                "visibility": null,
+            })
+        })
+        .collect();
+
+    Json(emails).into_response()
+}
+
+fn find_issue<'a>(inner: &'a FakeState, repository: &str, number: i32) -> Option<&'a Value> {
+    inner
+        .issues
+        .get(repository)?
+        .iter()
+        .find(|issue| issue["number"] == number)
+}
+
+async fn list_issues(State(inner): State<Shared>, Path((owner, name)): Path<(String, String)>) -> Response {
+    let inner = inner.lock().unwrap();
+    let issues = inner
+        .issues
+        .get(&format!("{}/{}", owner, name))
+        .cloned()
+        .unwrap_or_default();
+
+    Json(issues).into_response()
+}
+
+async fn get_issue(
+    State(inner): State<Shared>,
+    Path((owner, name, number)): Path<(String, String, i32)>,
+) -> Response {
+    let inner = inner.lock().unwrap();
+    match find_issue(&inner, &format!("{}/{}", owner, name), number) {
+        Some(issue) => Json(issue.clone()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn update_issue(
+    State(inner): State<Shared>,
+    headers: HeaderMap,
+    Path((owner, name, number)): Path<(String, String, i32)>,
+    Json(update): Json<Map<String, Value>>,
+) -> Response {
+    let mut inner = inner.lock().unwrap();
+    if authenticated(&inner, &headers).is_none() {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let repository = format!("{}/{}", owner, name);
+    let assignable = assignable_users(&inner.users, &inner.collaborators, &repository);
+    let Some(issue) = inner
+        .issues
+        .get_mut(&repository)
+        .and_then(|issues| issues.iter_mut().find(|issue| issue["number"] == number))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    for (field, value) in update {
+        let value = match field.as_str() {
+            "labels" => json!(names(&value)
+                .map(|name| json!({ "node_id": label_node_id(&repository, name), "name": name, "color": "ededed" }))
+                .collect::<Vec<_>>()),
+            // Like GitHub, assignees without access are dropped silently.
+            "assignees" => json!(names(&value)
+                .filter_map(|login| assignable.iter().find(|user| user.login.eq_ignore_ascii_case(login)))
+                .map(|user| json!({ "id": user.id, "login": user.login, "avatar_url": null }))
+                .collect::<Vec<_>>()),
+            _ => value,
+        };
+        issue[field.as_str()] = value;
+    }
+    issue["updated_at"] = json!(Utc::now());
+
+    Json(issue.clone()).into_response()
+}
+
+async fn check_assignee(
+    State(inner): State<Shared>,
+    Path((owner, name, login)): Path<(String, String, String)>,
+) -> StatusCode {
+    let inner = inner.lock().unwrap();
+    let assignable = assignable_users(&inner.users, &inner.collaborators, &format!("{}/{}", owner, name));
+    if assignable.iter().any(|user| user.login.eq_ignore_ascii_case(&login)) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+fn assignable_users(users: &[FakeUser], collaborators: &HashMap<String, Vec<i64>>, repository: &str) -> Vec<FakeUser> {
+    let ids = collaborators.get(repository).map(Vec::as_slice).unwrap_or_default();
+    users.iter().filter(|user| ids.contains(&user.id)).cloned().collect()
+}
+
+/// GitHub creates labels it does not know yet; the fake derives a stable ID
+/// from the name instead of keeping a label list.
+fn label_node_id(repository: &str, name: &str) -> String {
+    format!("LA_{}", hex::encode(format!("{}:{}", repository, name.to_lowercase())))
+}
+
+fn names(value: &Value) -> impl Iterator<Item = &str> {
+    value.as_array().into_iter().flatten().filter_map(Value::as_str)
+}
@@ -0,0 +1,291 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post, put},
+    Form, Json, Router,
+};
+use super::FakeUser;
+use chrono::Utc;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// An in-process stand-in for a GitLab instance serving the OAuth web flow,
+/// `/user` and project issues from memory. Users are [`FakeUser`]s;
+/// their first primary email is reported as the confirmed address.
+pub struct FakeGitLab {
+    addr: SocketAddr,
+    inner: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    users: Vec<FakeUser>,
+    signed_in: Option<i64>,
+    codes: HashMap<String, i64>,
+    tokens: HashMap<String, i64>,
+    issues: HashMap<String, Vec<Value>>,
+    next_id: i64,
+}
+
+impl FakeGitLab {
+    /// Binds a random local port and serves until the test's runtime ends.
+    pub async fn start() -> Self {
+        // Issue IDs are global on GitLab and end up in unique node IDs, so
+        // each fake starts from its own range.
+        let inner = Arc::new(Mutex::new(FakeState {
+            next_id: rand::thread_rng().gen_range(1_000_000..i64::from(i32::MAX)) * 1_000,
+            ..FakeState::default()
+        }));
+        let app = Router::new()
+            .route("/oauth/authorize", get(authorize))
+            .route("/oauth/token", post(access_token))
+            .route("/api/v4/user", get(user))
+            .route("/api/v4/projects/:project/issues", get(list_issues))
+            .route("/api/v4/projects/:project/issues/:iid", put(update_issue))
+            .with_state(inner.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind fake gitlab");
+        let addr = listener.local_addr().expect("fake gitlab address");
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("fake gitlab server");
+        });
+
+        Self { addr, inner }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Adds a user; the first one added is the one who signs in.
+    pub fn add_user(&self, user: FakeUser) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.signed_in.get_or_insert(user.id);
+        inner.users.push(user);
+    }
+
+    /// A token for the user, as the OAuth flow would have issued.
+    pub fn issue_token(&self, user_id: i64) -> String {
+        let token = format!("glpat-{}", Uuid::new_v4().simple());
+        self.inner.lock().unwrap().tokens.insert(token.clone(), user_id);
+        token
+    }
+
+    /// Adds an issue and returns its global ID.
+    pub fn add_issue(&self, project: &str, iid: i32, title: &str, description: Option<&str>) -> i64 {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id += 1;
+        let issue = json!({
+            "id": id,
+            "iid": iid,
+            "title": title,
+            "description": description,
+            "state": "opened",
+            "web_url": format!("http://{}/{}/-/issues/{}", self.addr, project, iid),
+            "updated_at": Utc::now(),
+            "labels": [],
+            "assignees": [],
+            "milestone": null,
+        });
+        inner.issues.entry(project.to_string()).or_default().push(issue);
+        id
+    }
+
+    /// Changes an issue as if someone edited it on GitLab.
+    pub fn edit_issue(&self, project: &str, iid: i32, edit: impl FnOnce(&mut Value)) {
+        let mut inner = self.inner.lock().unwrap();
+        let issue = find_issue(&mut inner, project, iid).expect("issue exists");
+        edit(issue);
+        issue["updated_at"] = json!(Utc::now());
+    }
+
+    pub fn issue(&self, project: &str, iid: i32) -> Option<Value> {
+        let mut inner = self.inner.lock().unwrap();
+        find_issue(&mut inner, project, iid).cloned()
+    }
+
+    /// A webhook delivery as GitLab would send it, carrying `token`.
+    pub fn webhook_request(&self, token: &str, event: &str, payload: &Value) -> Request {
+        axum::http::Request::builder()
+            .method("POST")
+            .uri("/webhooks/gitlab")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("x-gitlab-event", event)
+            .header("x-gitlab-event-uuid", Uuid::new_v4().to_string())
+            .header("x-gitlab-token", token)
+            .body(Body::from(serde_json::to_vec(payload).expect("webhook payload")))
+            .expect("webhook request")
+    }
+}
+
+type Shared = Arc<Mutex<FakeState>>;
+
+fn find_issue<'a>(inner: &'a mut FakeState, project: &str, iid: i32) -> Option<&'a mut Value> {
+    inner
+        .issues
+        .get_mut(project)?
+        .iter_mut()
+        .find(|issue| issue["iid"] == iid)
+}
+
+fn authenticated(inner: &FakeState, headers: &HeaderMap) -> Option<FakeUser> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    let user_id = inner.tokens.get(token)?;
+    inner.users.iter().find(|user| user.id == *user_id).cloned()
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    redirect_uri: String,
+    state: String,
+}
+
+async fn authorize(State(inner): State<Shared>, Query(query): Query<AuthorizeQuery>) -> Response {
+    let mut inner = inner.lock().unwrap();
+    let Some(user_id) = inner.signed_in else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let code = Uuid::new_v4().simple().to_string();
+    inner.codes.insert(code.clone(), user_id);
+
+    Redirect::to(&format!("{}?code={}&state={}", query.redirect_uri, code, query.state)).into_response()
+}
+
+#[derive(Deserialize)]
+struct TokenRequest {
+    code: String,
+}
+
+async fn access_token(State(inner): State<Shared>, Form(form): Form<TokenRequest>) -> Response {
+    let mut inner = inner.lock().unwrap();
+    let Some(user_id) = inner.codes.remove(&form.code) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" }))).into_response();
+    };
+    let token = format!("glpat-{}", Uuid::new_v4().simple());
+    inner.tokens.insert(token.clone(), user_id);
+
+    Json(json!({
+        "access_token": token,
+        "token_type": "Bearer",
+        "expires_in": 7200,
+        "refresh_token": Uuid::new_v4().simple().to_string(),
+        "scope": "read_user api",
+    }))
+    .into_response()
+}
+
+async fn user(State(inner): State<Shared>, headers: HeaderMap) -> Response {
+    let inner = inner.lock().unwrap();
+    let Some(user) = authenticated(&inner, &headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let email = user.emails.iter().find(|email| email.primary);
+
+    Json(json!({
+        "id": user.id,
+        "username": user.login,
+        "name": user.name,
+        "avatar_url": null,
+        "email": email.map(|email| &email.email),
+        "confirmed_at": email.filter(|email| email.verified).map(|_| Utc::now()),
+    }))
+    .into_response()
+}
+
+async fn list_issues(
+    State(inner): State<Shared>,
+    Path(project): Path<String>,
+    Query(query): Query<Vec<(String, String)>>,
+) -> Response {
+    let inner = inner.lock().unwrap();
+    let iids: Vec<String> = query
+        .into_iter()
+        .filter(|(name, _)| name == "iids[]")
+        .map(|(_, value)| value)
+        .collect();
+    let issues: Vec<Value> = inner
+        .issues
+        .get(&project)
+        .into_iter()
+        .flatten()
+        .filter(|issue| iids.is_empty() || iids.contains(&issue["iid"].to_string()))
+        .cloned()
+        .collect();
+
+    Json(issues).into_response()
+}
+
+async fn update_issue(
+    State(inner): State<Shared>,
+    headers: HeaderMap,
+    Path((project, iid)): Path<(String, i32)>,
+    Json(update): Json<Map<String, Value>>,
+) -> Response {
+    let mut inner = inner.lock().unwrap();
+    if authenticated(&inner, &headers).is_none() {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let users = inner.users.clone();
+    let Some(issue) = find_issue(&mut inner, &project, iid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    for (field, value) in update {
+        match field.as_str() {
+            "state_event" => {
+                issue["state"] = json!(if value == "close" { "closed" } else { "opened" });
+            }
+            "labels" => {
+                issue["labels"] = json!(value
+                    .as_str()
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| json!({ "id": label_id(name), "name": name, "color": "#ededed" }))
+                    .collect::<Vec<_>>());
+            }
+            "assignee_ids" => {
+                let ids: Vec<i64> = value.as_array().into_iter().flatten().filter_map(Value::as_i64).collect();
+                issue["assignees"] = json!(users
+                    .iter()
+                    .filter(|user| ids.contains(&user.id))
+                    .map(|user| json!({ "id": user.id, "username": user.login, "avatar_url": null }))
+                    .collect::<Vec<_>>());
+            }
+            _ => issue[field.as_str()] = value,
+        }
+    }
+    issue["updated_at"] = json!(Utc::now());
+
+    // Like GitLab, labels in the response are names only.
+    let mut response = issue.clone();
+    response["labels"] = json!(issue["labels"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|label| label["name"].clone())
+        .collect::<Vec<_>>());
+    Json(response).into_response()
+}
+
+/// A stable label ID derived from the name, as GitLab creates labels it does
+/// not know yet.
+fn label_id(name: &str) -> i64 {
+    name.to_lowercase().bytes().fold(17i64, |id, byte| (id * 31 + byte as i64) % 1_000_000_007)
+}
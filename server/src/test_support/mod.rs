@@ -0,0 +1,124 @@
+//! Helpers for end-to-end tests: a fake GitHub and GitLab, application state
+//! pointed at them, and a router that can be driven without a network listener.
+//!
+//! Tests that need Postgres are `#[ignore]`d and read `TEST_DATABASE_URL`;
+//! run them with `cargo test -- --include-ignored`. Everything else runs fully
+//! offline.
+
+mod e2e;
+pub mod fake_github;
+pub mod fake_gitlab;
+
+pub use fake_github::{FakeEmail, FakeGitHub, FakeUser};
+pub use fake_gitlab::FakeGitLab;
+
+use crate::{
+    config::{AppConfig, GiteaConfig, GitLabConfig},
+    routes::build_router,
+    security::{rate_limit::RateLimiter, token_cipher::TokenCipher},
+    session::SessionSigner,
+    state::{AppState, SharedState},
+};
+use axum::{extract::connect_info::MockConnectInfo, http::header::SET_COOKIE, response::Response, Router};
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::net::SocketAddr;
+use tokio::sync::OnceCell;
+
+/// Set once the test database has been migrated, so tests running in
+/// parallel do not race on `_sqlx_migrations`.
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// Postgres for tests that need one, migrated to the current schema.
+pub async fn test_database() -> PgPool {
+    let url = std::env::var("TEST_DATABASE_URL").expect("database tests need TEST_DATABASE_URL");
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&url)
+        .await
+        .expect("connect to TEST_DATABASE_URL");
+    MIGRATED
+        .get_or_init(|| async {
+            sqlx::migrate!("./migrations").run(&pool).await.expect("migrate test database");
+        })
+        .await;
+    pool
+}
+
+/// A pool that never connects, for tests that do not touch the database.
+pub fn unused_pool() -> PgPool {
+    PgPoolOptions::new()
+        .connect_lazy("postgres://unused@localhost/unused")
+        .expect("lazy pool")
+}
+
+/// Configuration pointed at the fake GitHub.
+pub fn test_config(github: &FakeGitHub) -> AppConfig {
+    let database_url = std::env::var("TEST_DATABASE_URL").unwrap_or_default();
+    AppConfig::for_tests(&database_url, &github.web_url(), &github.api_url())
+}
+
+/// `config` with GitLab sign-in and sync enabled against the fake GitLab.
+/// `api_token` stands in for `GITLAB_API_TOKEN`.
+pub fn with_gitlab(mut config: AppConfig, gitlab: &FakeGitLab, api_token: Option<String>) -> AppConfig {
+    config.gitlab = Some(GitLabConfig {
+        url: gitlab.url(),
+        client_id: "test-gitlab-client".to_string(),
+        client_secret: "test-gitlab-secret".to_string(),
+        webhook_token: Some("test-gitlab-webhook-token".to_string()),
+        api_token,
+    });
+    config
+}
+
+/// `config` with a Gitea instance at `url` enabled.
+pub fn with_gitea(mut config: AppConfig, url: &str) -> AppConfig {
+    config.gitea = Some(GiteaConfig {
+        url: url.to_string(),
+        client_id: "test-gitea-client".to_string(),
+        client_secret: "test-gitea-secret".to_string(),
+        webhook_secret: Some("test-gitea-webhook-secret".to_string()),
+        api_token: None,
+    });
+    config
+}
+
+pub fn test_state(config: AppConfig, pool: PgPool) -> SharedState {
+    let http_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("http client");
+
+    AppState::new(
+        pool,
+        SessionSigner::new(&config.session_signing_keys).expect("session signer"),
+        TokenCipher::new(&config.token_encryption_keys).expect("token cipher"),
+        http_client,
+        None,
+        config.clone(),
+        RateLimiter::new(config.auth_rate_limit_per_minute, config.auth_rate_limit_burst),
+        RateLimiter::new(config.webhook_rate_limit_per_minute, 0),
+    )
+    .expect("app state")
+    .shared()
+}
+
+/// The application router as served, with a fixed client address standing in
+/// for the connection info the real listener provides.
+pub fn test_router(state: SharedState) -> Router {
+    build_router(state.clone())
+        .with_state(state)
+        .layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))))
+}
+
+/// `name=value` pairs of the cookies a response sets, ready for a `Cookie`
+/// header.
+pub fn set_cookies(response: &Response) -> Vec<String> {
+    response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .map(str::to_string)
+        .collect()
+}